@@ -0,0 +1,43 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Env, StdResult, Storage};
+use cw_storage_plus::Item;
+use rujira_rs::{mint::ConfigResponse, TokenFactory};
+
+static CONFIG: Item<Config> = Item::new("config");
+
+#[cw_serde]
+pub struct Config {
+    pub id: String,
+    pub denom: String,
+    pub admin: Addr,
+}
+
+impl Config {
+    pub fn new(env: &Env, id: String, admin: Addr) -> Self {
+        let denom = TokenFactory::new(env, id.as_str()).denom();
+        Self { id, denom, admin }
+    }
+
+    /// Rebuilds the `TokenFactory` handle this token was created with, so later lifecycle
+    /// messages (mint, burn, metadata, admin transfer) target the same denom.
+    pub fn factory(&self, env: &Env) -> TokenFactory {
+        TokenFactory::new(env, self.id.as_str())
+    }
+
+    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
+        CONFIG.load(storage)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        CONFIG.save(storage, self)
+    }
+}
+
+impl From<Config> for ConfigResponse {
+    fn from(value: Config) -> Self {
+        Self {
+            denom: value.denom,
+            admin: value.admin.to_string(),
+        }
+    }
+}
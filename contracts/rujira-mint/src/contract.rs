@@ -1,87 +1,217 @@
-#[cfg(not(feature = "library"))]
-use cosmwasm_std::entry_point;
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response};
-use cw2::set_contract_version;
-use rujira_rs::{mint::InstantiateMsg, TokenFactory};
-
-use crate::error::ContractError;
-
-const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
-const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn instantiate(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    msg: InstantiateMsg,
-) -> Result<Response, ContractError> {
-    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    let factory = TokenFactory::new(&env, msg.id.as_str());
-    Ok(Response::default()
-        .add_message(factory.create_msg(msg.metadata))
-        .add_message(factory.mint_msg(msg.amount, info.sender)))
-}
-
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(
-    _deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    _msg: (),
-) -> Result<Response, ContractError> {
-    Err(ContractError::Unauthorized {})
-}
-
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn sudo(_deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
-    Err(ContractError::Unauthorized {})
-}
-
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: ()) -> Result<Binary, ContractError> {
-    Err(ContractError::Unauthorized {})
-}
-
-#[cfg(test)]
-mod tests {
-
-    use super::*;
-    use cosmwasm_std::{coins, Uint128};
-    use cw_multi_test::{ContractWrapper, Executor};
-    use rujira_rs::TokenMetadata;
-    use rujira_rs_testing::mock_rujira_app;
-
-    #[test]
-    fn instantiation() {
-        let mut app = mock_rujira_app();
-        let owner = app.api().addr_make("owner");
-
-        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
-        let code_id = app.store_code(code);
-        app.instantiate_contract(
-            code_id,
-            owner.clone(),
-            &InstantiateMsg {
-                id: "id".to_string(),
-                metadata: TokenMetadata {
-                    description: "description".to_string(),
-                    display: "display".to_string(),
-                    name: "name".to_string(),
-                    symbol: "symbol".to_string(),
-                    uri: None,
-                    uri_hash: None,
-                },
-                amount: Uint128::from(100u128),
-            },
-            &[],
-            "mint",
-            None,
-        )
-        .unwrap();
-
-        #[allow(deprecated)]
-        let balance = app.wrap().query_all_balances(owner).unwrap();
-        assert_eq!(balance, coins(100, "x/id"));
-    }
-}
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response};
+use cw2::set_contract_version;
+use rujira_rs::mint::{ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+
+use crate::config::Config;
+use crate::error::ContractError;
+
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let config = Config::new(&env, msg.id, info.sender.clone());
+    let factory = config.factory(&env);
+    config.save(deps.storage)?;
+    Ok(Response::default()
+        .add_message(factory.create_msg(msg.metadata))
+        .add_message(factory.mint_msg(msg.amount, info.sender)))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let mut config = Config::load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let factory = config.factory(&env);
+
+    match msg {
+        ExecuteMsg::Mint { amount, recipient } => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            Ok(Response::default().add_message(factory.mint_msg(amount, recipient)))
+        }
+        ExecuteMsg::Burn { amount } => {
+            Ok(Response::default().add_message(factory.burn_msg(amount)))
+        }
+        ExecuteMsg::SetMetadata(metadata) => {
+            Ok(Response::default().add_message(factory.set_metadata_msg(metadata)))
+        }
+        ExecuteMsg::ChangeAdmin { new_admin } => {
+            let new_admin = deps.api.addr_validate(&new_admin)?;
+            let msg = factory.change_admin_msg(new_admin.clone());
+            config.admin = new_admin;
+            config.save(deps.storage)?;
+            Ok(Response::default().add_message(msg))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(_deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
+    Err(ContractError::Unauthorized {})
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    match msg {
+        QueryMsg::Config {} => Ok(to_json_binary(&ConfigResponse::from(Config::load(
+            deps.storage,
+        )?))?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use cosmwasm_std::{coins, Uint128};
+    use cw_multi_test::{ContractWrapper, Executor};
+    use rujira_rs::TokenMetadata;
+    use rujira_rs_testing::mock_rujira_app;
+
+    fn metadata() -> TokenMetadata {
+        TokenMetadata {
+            description: "description".to_string(),
+            display: "display".to_string(),
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: None,
+            uri_hash: None,
+        }
+    }
+
+    #[test]
+    fn instantiation() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
+        let code_id = app.store_code(code);
+        app.instantiate_contract(
+            code_id,
+            owner.clone(),
+            &InstantiateMsg {
+                id: "id".to_string(),
+                metadata: metadata(),
+                amount: Uint128::from(100u128),
+            },
+            &[],
+            "mint",
+            None,
+        )
+        .unwrap();
+
+        #[allow(deprecated)]
+        let balance = app.wrap().query_all_balances(owner).unwrap();
+        assert_eq!(balance, coins(100, "x/id"));
+    }
+
+    #[test]
+    fn lifecycle_is_gated_on_the_stored_admin() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let new_admin = app.api().addr_make("new-admin");
+        let recipient = app.api().addr_make("recipient");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    id: "id".to_string(),
+                    metadata: metadata(),
+                    amount: Uint128::from(100u128),
+                },
+                &[],
+                "mint",
+                None,
+            )
+            .unwrap();
+
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(config.denom, "x/id".to_string());
+        assert_eq!(config.admin, owner.to_string());
+
+        // A non-admin can't mint.
+        app.execute_contract(
+            recipient.clone(),
+            contract.clone(),
+            &ExecuteMsg::Mint {
+                amount: Uint128::from(50u128),
+                recipient: recipient.to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Mint {
+                amount: Uint128::from(50u128),
+                recipient: recipient.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+        #[allow(deprecated)]
+        let balance = app.wrap().query_all_balances(recipient).unwrap();
+        assert_eq!(balance, coins(50, "x/id"));
+
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::ChangeAdmin {
+                new_admin: new_admin.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(config.admin, new_admin.to_string());
+
+        // The old admin has lost its authority once a new one is set.
+        app.execute_contract(
+            owner,
+            contract.clone(),
+            &ExecuteMsg::Burn {
+                amount: Uint128::from(10u128),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+        app.execute_contract(
+            new_admin,
+            contract,
+            &ExecuteMsg::Burn {
+                amount: Uint128::from(10u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use std::ops::Mul;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{coin, Addr, Attribute, Decimal, StdResult, Storage, Uint128, Uint256};
+use cw_storage_plus::{Item, Map};
+use rujira_rs::exchange::{Commitment, SwapError, Swappable};
+
+use crate::ContractError;
+
+pub const AMM_POOL: Item<AmmPool> = Item::new("amm-pool");
+/// Per-owner LP share balances, mirroring the `ACCOUNTS`-style per-owner map used elsewhere.
+pub const LP_SHARES: Map<Addr, Uint128> = Map::new("amm-lp-shares");
+
+/// Continuous constant-product (`x*y=k`) liquidity for the same ask/bid pair as the
+/// premium-ladder pools, participating in the same `Swapper`/`Swappable` interface.
+#[cw_serde]
+#[derive(Default)]
+pub struct AmmPool {
+    /// Reserves of the offered (ask) denom.
+    pub reserve_offer: Uint128,
+    /// Reserves of the bid denom.
+    pub reserve_bid: Uint128,
+    pub total_shares: Uint128,
+    /// Taker fee skimmed from the input before it hits the curve. Set from `Config` at load
+    /// time rather than persisted, since it tracks the contract's current fee setting.
+    #[serde(skip)]
+    fee_taker: Decimal,
+}
+
+impl AmmPool {
+    pub fn load(storage: &dyn Storage, fee_taker: Decimal) -> Self {
+        Self {
+            fee_taker,
+            ..AMM_POOL.load(storage).unwrap_or_default()
+        }
+    }
+
+    pub fn commit_state(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        AMM_POOL.save(storage, self)
+    }
+
+    /// Deposit `offer`/`bid` reserves, minting shares proportional to the growth of
+    /// `sqrt(x*y)`. The pool's first depositor is credited with `sqrt(x*y)` shares directly.
+    pub fn add_liquidity(
+        &mut self,
+        owner: &Addr,
+        storage: &mut dyn Storage,
+        offer: Uint128,
+        bid: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        let shares = if self.total_shares.is_zero() {
+            Uint128::try_from((Uint256::from(offer) * Uint256::from(bid)).isqrt())?
+        } else {
+            let k_before =
+                (Uint256::from(self.reserve_offer) * Uint256::from(self.reserve_bid)).isqrt();
+            let k_after = (Uint256::from(self.reserve_offer + offer)
+                * Uint256::from(self.reserve_bid + bid))
+            .isqrt();
+            Uint128::try_from(Uint256::from(self.total_shares) * (k_after - k_before) / k_before)?
+        };
+
+        self.reserve_offer += offer;
+        self.reserve_bid += bid;
+        self.total_shares += shares;
+
+        let owned = LP_SHARES
+            .may_load(storage, owner.clone())?
+            .unwrap_or_default();
+        LP_SHARES.save(storage, owner.clone(), &(owned + shares))?;
+
+        Ok(shares)
+    }
+
+    /// Burn `shares` owned by `owner`, returning the proportional share of each reserve.
+    pub fn remove_liquidity(
+        &mut self,
+        owner: &Addr,
+        storage: &mut dyn Storage,
+        shares: Uint128,
+    ) -> Result<(Uint128, Uint128), ContractError> {
+        let owned = LP_SHARES
+            .may_load(storage, owner.clone())?
+            .unwrap_or_default();
+        if shares > owned {
+            return Err(ContractError::InsufficientFunds {
+                expected: coin(shares.u128(), "lp-shares"),
+                returned: coin(owned.u128(), "lp-shares"),
+            });
+        }
+
+        let offer = Uint128::try_from(
+            Uint256::from(self.reserve_offer) * Uint256::from(shares)
+                / Uint256::from(self.total_shares),
+        )?;
+        let bid = Uint128::try_from(
+            Uint256::from(self.reserve_bid) * Uint256::from(shares)
+                / Uint256::from(self.total_shares),
+        )?;
+
+        self.reserve_offer -= offer;
+        self.reserve_bid -= bid;
+        self.total_shares -= shares;
+
+        let remaining = owned - shares;
+        if remaining.is_zero() {
+            LP_SHARES.remove(storage, owner.clone());
+        } else {
+            LP_SHARES.save(storage, owner.clone(), &remaining)?;
+        }
+
+        Ok((offer, bid))
+    }
+}
+
+impl Swappable for AmmPool {
+    fn swap(&mut self, offer: Uint128) -> Result<(Uint128, Uint128), SwapError> {
+        if offer.is_zero() || self.reserve_offer.is_zero() || self.reserve_bid.is_zero() {
+            return Ok((Uint128::zero(), Uint128::zero()));
+        }
+
+        let offer_after_fee = Decimal::from_ratio(offer, 1u128)
+            .mul(Decimal::one() - self.fee_taker)
+            .to_uint_floor();
+
+        let k = Uint256::from(self.reserve_offer) * Uint256::from(self.reserve_bid);
+        let reserve_offer_after_fee = self.reserve_offer + offer_after_fee;
+        let reserve_bid_after: Uint128 =
+            (k / Uint256::from(reserve_offer_after_fee)).try_into()?;
+        let returned = self.reserve_bid - reserve_bid_after;
+
+        self.reserve_offer += offer;
+        self.reserve_bid = reserve_bid_after;
+
+        Ok((offer, returned))
+    }
+
+    fn commit(&self, storage: &mut dyn Storage) -> Result<Commitment, SwapError> {
+        AMM_POOL.save(storage, self)?;
+        Ok(Commitment::default())
+    }
+
+    fn attributes(&self) -> Vec<Attribute> {
+        vec![Attribute::new("pool", "amm")]
+    }
+
+    fn rate(&self) -> Decimal {
+        if self.reserve_offer.is_zero() {
+            Decimal::zero()
+        } else {
+            Decimal::from_ratio(self.reserve_bid, self.reserve_offer)
+        }
+    }
+
+    fn total(&self) -> Uint128 {
+        self.reserve_bid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn add_remove_liquidity_and_swap() {
+        let mut store = MockStorage::new();
+        let owner = Addr::unchecked("owner");
+        let mut amm = AmmPool::load(&store, Decimal::permille(3));
+
+        let shares = amm
+            .add_liquidity(
+                &owner,
+                &mut store,
+                Uint128::from(1_000_000u128),
+                Uint128::from(1_000_000u128),
+            )
+            .unwrap();
+        assert_eq!(shares, Uint128::from(1_000_000u128));
+        assert_eq!(amm.total_shares, Uint128::from(1_000_000u128));
+
+        let (out_offer, out_bid) = amm.swap(Uint128::from(10_000u128)).unwrap();
+        assert_eq!(out_offer, Uint128::from(10_000u128));
+        // Taker fee is skimmed before the curve, so the return is a little below the naive x*y=k quote
+        assert_eq!(out_bid, Uint128::from(9_872u128));
+        amm.commit_state(&mut store).unwrap();
+
+        let reserve_offer = amm.reserve_offer;
+        let reserve_bid = amm.reserve_bid;
+        let (offer, bid) = amm.remove_liquidity(&owner, &mut store, shares).unwrap();
+        assert_eq!(offer, reserve_offer);
+        assert_eq!(bid, reserve_bid);
+        assert!(amm.total_shares.is_zero());
+        assert!(LP_SHARES.may_load(&store, owner).unwrap().is_none());
+    }
+}
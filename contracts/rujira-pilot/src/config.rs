@@ -1,7 +1,11 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, Decimal, StdResult, Storage};
+use cosmwasm_std::{Addr, Api, Decimal, Storage, Uint128};
 use cw_storage_plus::Item;
+use rujira_rs::exchange::{FeeSchedule, SwapError};
 use rujira_rs::pilot::{ConfigResponse, Denoms, InstantiateMsg};
+use std::ops::Mul;
+
+use crate::error::ContractError;
 
 pub static CONFIG: Item<Config> = Item::new("config");
 
@@ -12,26 +16,74 @@ pub struct Config {
     pub max_premium: u8,
     pub fee_maker: Decimal,
     pub fee_taker: Decimal,
-    pub fee_address: Addr,
+    /// Recipients of the accumulated taker/maker fees, paired with their weight.
+    /// Weights must sum to exactly `Decimal::one()`.
+    pub fee_recipients: Vec<(Addr, Decimal)>,
+    /// Maximum age, in seconds, of an oracle price before it is rejected as stale.
+    pub max_staleness_seconds: u64,
+    /// Maximum allowed `conf / price` ratio before the price is rejected as uncertain.
+    pub max_confidence_ratio: Option<Decimal>,
+    /// Price pools against the EMA price rather than the spot price.
+    pub use_ema_price: bool,
 }
 
 impl Config {
-    pub fn new(api: &dyn Api, value: InstantiateMsg) -> StdResult<Self> {
+    pub fn new(api: &dyn Api, value: InstantiateMsg) -> Result<Self, ContractError> {
         Ok(Self {
             denoms: value.denoms.clone(),
             max_premium: value.max_premium,
             executor: api.addr_validate(&value.executor)?,
             fee_taker: value.fee_taker,
             fee_maker: value.fee_maker,
-            fee_address: api.addr_validate(value.fee_address.as_str())?,
+            fee_recipients: validate_fee_recipients(api, value.fee_recipients)?,
+            max_staleness_seconds: value.max_staleness_seconds,
+            max_confidence_ratio: value.max_confidence_ratio,
+            use_ema_price: value.use_ema_price,
         })
     }
 
-    pub fn validate(&self) -> StdResult<()> {
-        Ok(())
+    pub fn validate(&self) -> Result<(), ContractError> {
+        validate_weights(&self.fee_recipients)
+    }
+
+    /// Builds the `FeeSchedule` a swap's taker fee should be split across, weighting each
+    /// `fee_recipients` entry by `fee_taker` so the combined rate matches the old scalar fee
+    /// exactly. Returns `SwapError`, the error type `FeeSchedule::new` reports.
+    pub fn fee_schedule(&self) -> Result<FeeSchedule, SwapError> {
+        FeeSchedule::new(
+            self.fee_recipients
+                .iter()
+                .map(|(addr, weight)| (addr.clone(), *weight * self.fee_taker))
+                .collect(),
+        )
+    }
+
+    /// Splits `amount` across `fee_recipients` by weight. Each recipient's share is floored
+    /// from `amount * weight`; the leftover dust is credited to the first recipient so the
+    /// shares always sum to exactly `amount` with no tokens lost or created.
+    pub fn split(&self, amount: Uint128) -> Vec<(Addr, Uint128)> {
+        let mut shares: Vec<(Addr, Uint128)> = self
+            .fee_recipients
+            .iter()
+            .map(|(addr, weight)| {
+                (
+                    addr.clone(),
+                    Decimal::from_ratio(amount, 1u128)
+                        .mul(*weight)
+                        .to_uint_floor(),
+                )
+            })
+            .collect();
+
+        let allocated: Uint128 = shares.iter().map(|(_, amount)| *amount).sum();
+        if let Some((_, first)) = shares.first_mut() {
+            *first += amount - allocated;
+        }
+
+        shares
     }
 
-    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+    pub fn save(&self, storage: &mut dyn Storage) -> cosmwasm_std::StdResult<()> {
         CONFIG.save(storage, self)
     }
 
@@ -39,18 +91,45 @@ impl Config {
         &mut self,
         fee_taker: Option<Decimal>,
         fee_maker: Option<Decimal>,
-        fee_address: Option<Addr>,
-    ) {
+        fee_recipients: Option<Vec<(Addr, Decimal)>>,
+    ) -> Result<(), ContractError> {
         if let Some(fee_taker) = fee_taker {
             self.fee_taker = fee_taker;
         }
         if let Some(fee_maker) = fee_maker {
             self.fee_maker = fee_maker;
         }
-        if let Some(fee_address) = fee_address {
-            self.fee_address = fee_address;
+        if let Some(fee_recipients) = fee_recipients {
+            validate_weights(&fee_recipients)?;
+            self.fee_recipients = fee_recipients;
         }
+        Ok(())
+    }
+}
+
+fn validate_fee_recipients(
+    api: &dyn Api,
+    value: Vec<(String, Decimal)>,
+) -> Result<Vec<(Addr, Decimal)>, ContractError> {
+    let recipients = value
+        .into_iter()
+        .map(|(addr, weight)| Ok((api.addr_validate(&addr)?, weight)))
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    validate_weights(&recipients)?;
+    Ok(recipients)
+}
+
+fn validate_weights(recipients: &[(Addr, Decimal)]) -> Result<(), ContractError> {
+    if recipients.is_empty() {
+        return Err(ContractError::InvalidFeeWeights {});
+    }
+    let total = recipients
+        .iter()
+        .fold(Decimal::zero(), |acc, (_, weight)| acc + *weight);
+    if total != Decimal::one() {
+        return Err(ContractError::InvalidFeeWeights {});
     }
+    Ok(())
 }
 
 impl From<Config> for ConfigResponse {
@@ -60,7 +139,67 @@ impl From<Config> for ConfigResponse {
             executor: value.executor.to_string(),
             fee_maker: value.fee_maker,
             fee_taker: value.fee_taker,
-            fee_address: value.fee_address.to_string(),
+            fee_recipients: value
+                .fee_recipients
+                .into_iter()
+                .map(|(addr, weight)| (addr.to_string(), weight))
+                .collect(),
+            max_staleness_seconds: value.max_staleness_seconds,
+            max_confidence_ratio: value.max_confidence_ratio,
+            use_ema_price: value.use_ema_price,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Addr {
+        Addr::unchecked(s)
+    }
+
+    fn config_with_recipients(recipients: Vec<(Addr, Decimal)>) -> Config {
+        Config {
+            denoms: rujira_rs::pilot::Denoms::new("ask", "bid"),
+            executor: addr("executor"),
+            max_premium: 10,
+            fee_maker: Decimal::permille(1),
+            fee_taker: Decimal::permille(1),
+            fee_recipients: recipients,
+            max_staleness_seconds: 60,
+            max_confidence_ratio: None,
+            use_ema_price: false,
         }
     }
+
+    #[test]
+    fn split_reconciles_exactly_with_dust_to_first() {
+        let config = config_with_recipients(vec![
+            (addr("treasury"), Decimal::percent(60)),
+            (addr("burn"), Decimal::percent(30)),
+            (addr("affiliate"), Decimal::percent(10)),
+        ]);
+
+        let shares = config.split(Uint128::from(1001u128));
+        let allocated: Uint128 = shares.iter().map(|(_, amount)| *amount).sum();
+        assert_eq!(allocated, Uint128::from(1001u128));
+        assert_eq!(
+            shares,
+            vec![
+                (addr("treasury"), Uint128::from(601u128)),
+                (addr("burn"), Uint128::from(300u128)),
+                (addr("affiliate"), Uint128::from(100u128)),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_single_recipient_keeps_everything() {
+        let config = config_with_recipients(vec![(addr("treasury"), Decimal::one())]);
+        assert_eq!(
+            config.split(Uint128::from(12345u128)),
+            vec![(addr("treasury"), Uint128::from(12345u128))]
+        );
+    }
 }
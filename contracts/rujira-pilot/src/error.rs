@@ -48,6 +48,45 @@ pub enum ContractError {
 
     #[error("Invalid Premium: {premium}")]
     InvalidPremium { premium: u8 },
+
+    #[error("StalePrice published {published} now {now}")]
+    StalePrice { published: u64, now: u64 },
+
+    #[error("PriceUncertain")]
+    PriceUncertain {},
+
+    #[error("InvalidFeeWeights: weights must be non-empty and sum to 1")]
+    InvalidFeeWeights {},
+
+    #[error("FillOrKillUnfillable: order cannot be fully consumed against current pool depth")]
+    FillOrKillUnfillable {},
+
+    #[error("DeadlineExceeded")]
+    DeadlineExceeded {},
+
+    #[error("InvalidRange: start {start} must not be greater than end {end}")]
+    InvalidRange { start: u8, end: u8 },
+
+    #[error("InvalidLaunchWindow: start must precede deadline within the max launch horizon")]
+    InvalidLaunchWindow {},
+
+    #[error("LaunchNotOpen: outside the contribution window")]
+    LaunchNotOpen {},
+
+    #[error("LaunchStillOpen: deadline has not yet passed")]
+    LaunchStillOpen {},
+
+    #[error("SoftCapNotMet: cannot settle a launch that missed its soft cap")]
+    SoftCapNotMet {},
+
+    #[error("SoftCapMet: launch settled instead of refunding, soft cap was reached")]
+    SoftCapMet {},
+
+    #[error("AlreadySettled")]
+    AlreadySettled {},
+
+    #[error("NoContribution: nothing left to refund")]
+    NoContribution {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
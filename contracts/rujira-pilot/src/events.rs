@@ -1,4 +1,4 @@
-use cosmwasm_std::{Event, Uint128};
+use cosmwasm_std::{Addr, Event, Uint128};
 
 use crate::{order::Order, pool::Pool};
 
@@ -7,13 +7,21 @@ pub fn event_create_order(pool: &Pool, order: &Order) -> Event {
         .add_attribute("owner", order.owner.clone())
         .add_attribute("premium", pool.premium.to_string())
         .add_attribute("offer", order.offer)
+        .add_attribute("client_id", client_id_attr(order))
 }
 
-pub fn event_withdraw_order(pool: &Pool, order: &Order, amount: &Uint128) -> Event {
+pub fn event_withdraw_order(
+    pool: &Pool,
+    order: &Order,
+    amount: &Uint128,
+    fee_bonus: &Uint128,
+) -> Event {
     Event::new("rujira-orca/order.withdraw")
         .add_attribute("owner", order.owner.clone())
         .add_attribute("premium", pool.premium.to_string())
         .add_attribute("amount", amount.to_string())
+        .add_attribute("fee_bonus", fee_bonus.to_string())
+        .add_attribute("client_id", client_id_attr(order))
 }
 
 pub fn event_increase_order(pool: &Pool, order: &Order, amount: &Uint128) -> Event {
@@ -21,6 +29,7 @@ pub fn event_increase_order(pool: &Pool, order: &Order, amount: &Uint128) -> Eve
         .add_attribute("owner", order.owner.clone())
         .add_attribute("premium", pool.premium.to_string())
         .add_attribute("amount", amount.to_string())
+        .add_attribute("client_id", client_id_attr(order))
 }
 
 pub fn event_retract_order(pool: &Pool, order: &Order, amount: &Uint128) -> Event {
@@ -28,4 +37,61 @@ pub fn event_retract_order(pool: &Pool, order: &Order, amount: &Uint128) -> Even
         .add_attribute("owner", order.owner.clone())
         .add_attribute("premium", pool.premium.to_string())
         .add_attribute("amount", amount.to_string())
+        .add_attribute("client_id", client_id_attr(order))
+}
+
+/// Renders `order.client_id` for an event attribute - empty string when the order wasn't
+/// tagged with one, so existing off-chain consumers see a stable attribute count either way.
+fn client_id_attr(order: &Order) -> String {
+    order.client_id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+/// Emitted when `OrderManager::prune_expired` auto-retracts a `GoodTilTime` order whose
+/// `valid_to` has passed, rather than the owner retracting it themselves.
+pub fn event_expire_order(pool: &Pool, order: &Order, amount: &Uint128) -> Event {
+    Event::new("rujira-orca/order.expire")
+        .add_attribute("owner", order.owner.clone())
+        .add_attribute("premium", pool.premium.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+/// Emitted when `OrderManager::execute_peg` migrates an oracle-pegged order from one concrete
+/// tier to another because the oracle moved since it was last resynced.
+pub fn event_repeg_order(
+    order: &Order,
+    from_premium: u8,
+    to_premium: u8,
+    amount: &Uint128,
+) -> Event {
+    Event::new("rujira-orca/order.repeg")
+        .add_attribute("owner", order.owner.clone())
+        .add_attribute("from_premium", from_premium.to_string())
+        .add_attribute("to_premium", to_premium.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_fill(pool: &Pool, ask_consumed: &Uint128, bid_remaining: &Uint128) -> Event {
+    Event::new("rujira-orca/order.fill")
+        .add_attribute("premium", pool.premium.to_string())
+        .add_attribute("ask_consumed", ask_consumed.to_string())
+        .add_attribute("bid_remaining", bid_remaining.to_string())
+}
+
+pub fn event_launch_contribute(owner: Addr, amount: Uint128) -> Event {
+    Event::new("rujira-orca/launch.contribute")
+        .add_attribute("owner", owner)
+        .add_attribute("amount", amount)
+}
+
+/// Emitted once, by whichever `Claim` call first settles a launch that reached its soft cap -
+/// `raised` is the full amount split across `Config::split`'s fee recipients, not a payout to
+/// the caller.
+pub fn event_launch_settle(raised: Uint128) -> Event {
+    Event::new("rujira-orca/launch.settle").add_attribute("raised", raised)
+}
+
+pub fn event_launch_refund(owner: Addr, amount: Uint128) -> Event {
+    Event::new("rujira-orca/launch.refund")
+        .add_attribute("owner", owner)
+        .add_attribute("amount", amount)
 }
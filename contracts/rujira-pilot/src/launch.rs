@@ -0,0 +1,210 @@
+//! Goal-or-refund accounting for a time-bounded launch window: while `now` is within
+//! `[start, deadline]` contributions accrue; once `now` passes `deadline`, the launch either
+//! settles (raised >= `soft_cap`) or every contributor can reclaim their exact deposit.
+//!
+//! `instantiate` seeds the single [`LAUNCH`] from `InstantiateMsg`'s `start`/`deadline`/
+//! `soft_cap` fields, and `contract.rs`'s `execute()` calls [`Launch::contribute`] from
+//! `ExecuteMsg::Contribute {}` and [`Launch::settle`]/[`Launch::refund`] (whichever applies)
+//! from `ExecuteMsg::Claim {}`.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map};
+
+use crate::error::ContractError;
+
+/// Longest allowed `deadline - start`, so a launch can't be configured to run (and lock
+/// contributor funds) indefinitely.
+pub const MAX_WINDOW_SECONDS: u64 = 90 * 24 * 60 * 60;
+
+pub static LAUNCH: Item<Launch> = Item::new("launch");
+static CONTRIBUTIONS: Map<&Addr, Uint128> = Map::new("launch_contributions");
+
+#[cw_serde]
+pub struct Launch {
+    pub start: Timestamp,
+    pub deadline: Timestamp,
+    pub soft_cap: Uint128,
+    pub raised: Uint128,
+    pub settled: bool,
+}
+
+impl Launch {
+    pub fn new(
+        start: Timestamp,
+        deadline: Timestamp,
+        soft_cap: Uint128,
+    ) -> Result<Self, ContractError> {
+        if start >= deadline {
+            return Err(ContractError::InvalidLaunchWindow {});
+        }
+        if deadline.seconds() - start.seconds() > MAX_WINDOW_SECONDS {
+            return Err(ContractError::InvalidLaunchWindow {});
+        }
+        Ok(Self {
+            start,
+            deadline,
+            soft_cap,
+            raised: Uint128::zero(),
+            settled: false,
+        })
+    }
+
+    pub fn is_open(&self, now: &Timestamp) -> bool {
+        *now >= self.start && *now <= self.deadline
+    }
+
+    pub fn soft_cap_met(&self) -> bool {
+        self.raised >= self.soft_cap
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        LAUNCH.save(storage, self)
+    }
+
+    /// Records `amount` from `contributor` against the launch, failing outside
+    /// `[start, deadline]`.
+    pub fn contribute(
+        &mut self,
+        storage: &mut dyn Storage,
+        now: &Timestamp,
+        contributor: &Addr,
+        amount: Uint128,
+    ) -> Result<(), ContractError> {
+        if !self.is_open(now) {
+            return Err(ContractError::LaunchNotOpen {});
+        }
+        let existing = CONTRIBUTIONS
+            .may_load(storage, contributor)?
+            .unwrap_or_default();
+        CONTRIBUTIONS.save(storage, contributor, &(existing + amount))?;
+        self.raised += amount;
+        self.save(storage)?;
+        Ok(())
+    }
+
+    /// Settles the launch once, after `deadline`, provided `soft_cap` was reached. Returns the
+    /// full raised amount, left for the caller to split across fee recipients (e.g. via
+    /// `Config::split`).
+    pub fn settle(
+        &mut self,
+        storage: &mut dyn Storage,
+        now: &Timestamp,
+    ) -> Result<Uint128, ContractError> {
+        if *now <= self.deadline {
+            return Err(ContractError::LaunchStillOpen {});
+        }
+        if self.settled {
+            return Err(ContractError::AlreadySettled {});
+        }
+        if !self.soft_cap_met() {
+            return Err(ContractError::SoftCapNotMet {});
+        }
+        self.settled = true;
+        self.save(storage)?;
+        Ok(self.raised)
+    }
+
+    /// Refunds `contributor`'s exact deposit once, after `deadline`, provided `soft_cap` was
+    /// not reached.
+    pub fn refund(
+        &self,
+        storage: &mut dyn Storage,
+        now: &Timestamp,
+        contributor: &Addr,
+    ) -> Result<Uint128, ContractError> {
+        if *now <= self.deadline {
+            return Err(ContractError::LaunchStillOpen {});
+        }
+        if self.soft_cap_met() {
+            return Err(ContractError::SoftCapMet {});
+        }
+        let amount = CONTRIBUTIONS
+            .may_load(storage, contributor)?
+            .unwrap_or_default();
+        if amount.is_zero() {
+            return Err(ContractError::NoContribution {});
+        }
+        CONTRIBUTIONS.remove(storage, contributor);
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    fn ts(seconds: u64) -> Timestamp {
+        Timestamp::from_seconds(seconds)
+    }
+
+    fn addr(s: &str) -> Addr {
+        Addr::unchecked(s)
+    }
+
+    #[test]
+    fn rejects_inverted_or_oversized_window() {
+        assert!(Launch::new(ts(100), ts(100), Uint128::zero()).is_err());
+        assert!(Launch::new(ts(100), ts(50), Uint128::zero()).is_err());
+        assert!(Launch::new(ts(0), ts(MAX_WINDOW_SECONDS + 1), Uint128::zero()).is_err());
+        assert!(Launch::new(ts(0), ts(MAX_WINDOW_SECONDS), Uint128::zero()).is_ok());
+    }
+
+    #[test]
+    fn contribute_rejected_outside_window() {
+        let mut storage = MockStorage::new();
+        let mut launch = Launch::new(ts(100), ts(200), Uint128::from(1000u128)).unwrap();
+
+        assert!(launch
+            .contribute(&mut storage, &ts(50), &addr("alice"), Uint128::from(10u128))
+            .is_err());
+        assert!(launch
+            .contribute(&mut storage, &ts(201), &addr("alice"), Uint128::from(10u128))
+            .is_err());
+        launch
+            .contribute(&mut storage, &ts(150), &addr("alice"), Uint128::from(10u128))
+            .unwrap();
+        assert_eq!(launch.raised, Uint128::from(10u128));
+    }
+
+    #[test]
+    fn settles_when_soft_cap_met() {
+        let mut storage = MockStorage::new();
+        let mut launch = Launch::new(ts(0), ts(100), Uint128::from(1000u128)).unwrap();
+        launch
+            .contribute(&mut storage, &ts(10), &addr("alice"), Uint128::from(1000u128))
+            .unwrap();
+
+        assert!(launch.settle(&mut storage, &ts(50)).is_err());
+        let raised = launch.settle(&mut storage, &ts(101)).unwrap();
+        assert_eq!(raised, Uint128::from(1000u128));
+        assert!(launch.settle(&mut storage, &ts(101)).is_err());
+    }
+
+    #[test]
+    fn refunds_exact_deposit_when_soft_cap_missed() {
+        let mut storage = MockStorage::new();
+        let mut launch = Launch::new(ts(0), ts(100), Uint128::from(1000u128)).unwrap();
+        launch
+            .contribute(&mut storage, &ts(10), &addr("alice"), Uint128::from(400u128))
+            .unwrap();
+        launch
+            .contribute(&mut storage, &ts(20), &addr("bob"), Uint128::from(200u128))
+            .unwrap();
+
+        assert!(launch.settle(&mut storage, &ts(101)).is_err());
+
+        let refund = launch
+            .refund(&mut storage, &ts(101), &addr("alice"))
+            .unwrap();
+        assert_eq!(refund, Uint128::from(400u128));
+        // A second claim has nothing left to refund.
+        assert!(launch
+            .refund(&mut storage, &ts(101), &addr("alice"))
+            .is_err());
+
+        let refund = launch.refund(&mut storage, &ts(101), &addr("bob")).unwrap();
+        assert_eq!(refund, Uint128::from(200u128));
+    }
+}
@@ -1,10 +1,15 @@
+pub mod amm;
 pub mod config;
 pub mod contract;
 mod error;
 pub mod events;
+pub mod launch;
+pub mod oracle;
 pub mod order;
 pub mod order_manager;
 pub mod pool;
 pub mod premium;
+pub mod stable_amm;
+pub mod target_rate;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,52 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Env, QuerierWrapper};
+
+use crate::config::Config;
+use crate::error::ContractError;
+
+/// Query message understood by `config.executor`, a Pyth-style price pusher.
+#[cw_serde]
+pub enum ExecutorQueryMsg {
+    Price {},
+}
+
+/// Pyth-style price response: a spot price, its EMA counterpart, a confidence
+/// interval around the spot price, and the time it was published.
+#[cw_serde]
+pub struct PriceResponse {
+    pub price: Decimal,
+    pub ema_price: Decimal,
+    pub conf: Decimal,
+    pub publish_time: u64,
+}
+
+pub fn load_oracle_price(
+    querier: QuerierWrapper,
+    env: &Env,
+    config: &Config,
+) -> Result<Decimal, ContractError> {
+    let res: PriceResponse =
+        querier.query_wasm_smart(config.executor.to_string(), &ExecutorQueryMsg::Price {})?;
+
+    let now = env.block.time.seconds();
+    if now.saturating_sub(res.publish_time) > config.max_staleness_seconds {
+        return Err(ContractError::StalePrice {
+            published: res.publish_time,
+            now,
+        });
+    }
+
+    let price = if config.use_ema_price {
+        res.ema_price
+    } else {
+        res.price
+    };
+
+    if let Some(max_confidence_ratio) = config.max_confidence_ratio {
+        if price.is_zero() || res.conf.checked_div(price)? > max_confidence_ratio {
+            return Err(ContractError::PriceUncertain {});
+        }
+    }
+
+    Ok(price)
+}
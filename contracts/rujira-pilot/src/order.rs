@@ -1,157 +1,446 @@
-use std::cmp::min;
-
-use crate::{error::ContractError, pool::Pool};
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, StdResult, Storage, Timestamp, Uint128};
-use cw_storage_plus::Map;
-use rujira_rs::bid_pool;
-
-pub const ORDERS: Map<(Addr, u8), (Timestamp, Uint128, bid_pool::Bid)> = Map::new("orders");
-const MAX_LIMIT: u8 = 31;
-const DEFAULT_LIMIT: u8 = 10;
-
-#[cw_serde]
-pub struct Order {
-    pub owner: Addr,
-    pub updated_at: Timestamp,
-    /// Original offer amount, as it was at `updated_at` time
-    pub offer: Uint128,
-    pub bid: bid_pool::Bid,
-}
-
-impl Order {
-    pub fn load(storage: &dyn Storage, owner: &Addr, premium: &u8) -> Result<Self, ContractError> {
-        let (updated_at, offer, bid) = ORDERS
-            .load(storage, (owner.clone(), *premium))
-            .map_err(|_| ContractError::NotFound {})?;
-        Ok(Self {
-            owner: owner.clone(),
-            updated_at,
-            offer,
-            bid,
-        })
-    }
-
-    pub fn by_owner(
-        storage: &dyn Storage,
-        owner: &Addr,
-        offset: Option<u8>,
-        limit: Option<u8>,
-    ) -> StdResult<Vec<(u8, Self)>> {
-        let limit = min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT) as usize;
-        let offset = offset.unwrap_or(0) as usize;
-        ORDERS
-            .prefix(owner.clone())
-            .range(storage, None, None, cosmwasm_std::Order::Ascending)
-            .skip(offset)
-            .take(limit)
-            .map(|x| {
-                x.map(|(k, (updated_at, offer, bid))| {
-                    (
-                        k,
-                        Self {
-                            owner: owner.clone(),
-                            updated_at,
-                            offer,
-                            bid,
-                        },
-                    )
-                })
-            })
-            .collect()
-    }
-
-    pub fn amount(&self) -> Uint128 {
-        self.bid.amount().try_into().unwrap()
-    }
-
-    pub fn save(&self, storage: &mut dyn Storage, pool: &Pool) -> StdResult<()> {
-        if self.bid.is_empty() {
-            self.remove(storage, pool);
-            return Ok(());
-        }
-        ORDERS.save(
-            storage,
-            (self.owner.clone(), pool.premium),
-            &(self.updated_at, self.offer, self.bid.clone()),
-        )?;
-        Ok(())
-    }
-
-    fn remove(&self, storage: &mut dyn Storage, pool: &Pool) {
-        ORDERS.remove(storage, (self.owner.clone(), pool.premium))
-    }
-}
-
-#[cfg(test)]
-mod tests {
-
-    use super::*;
-    use cosmwasm_std::{testing::MockStorage, Addr, Decimal, Timestamp, Uint128};
-    use rujira_rs::exchange::Swappable;
-
-    use crate::pool::Pool;
-
-    #[test]
-    fn query_order() {
-        let mut store = MockStorage::new();
-        let timestamp = Timestamp::default();
-        let owner = Addr::unchecked("owner");
-        let offer = Uint128::from(100u128);
-        let mut pool = Pool::load(&store, &0, &Decimal::one());
-        pool.create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-
-        pool.commit(&mut store).unwrap();
-
-        let order = Order::load(&store, &owner, &0).unwrap();
-        assert_eq!(order.owner, owner);
-        assert_eq!(order.offer, offer);
-    }
-
-    #[test]
-    fn query_orders_by_owner() {
-        let mut store = MockStorage::new();
-        let timestamp = Timestamp::default();
-        let owner = Addr::unchecked("owner");
-        let owner2 = Addr::unchecked("owner2");
-        let offer = Uint128::from(100u128);
-        let oracle = Decimal::one();
-        let mut pool1 = Pool::load(&store, &0, &oracle);
-        let mut pool2 = Pool::load(&store, &1, &oracle);
-        let mut pool3 = Pool::load(&store, &2, &oracle);
-        let mut pool4 = Pool::load(&store, &10, &oracle);
-        let mut pool5 = Pool::load(&store, &11, &oracle);
-        let mut pool6 = Pool::load(&store, &12, &oracle);
-
-        pool1
-            .create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-        pool2
-            .create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-        pool3
-            .create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-        pool4
-            .create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-        pool5
-            .create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-        pool6
-            .create_order(&mut store, &timestamp, &owner, offer)
-            .unwrap();
-
-        pool1
-            .create_order(&mut store, &timestamp, &owner2, offer)
-            .unwrap();
-
-        pool1.commit(&mut store).unwrap();
-
-        let orders = Order::by_owner(&store, &owner, None, None).unwrap();
-        assert_eq!(orders.len(), 6);
-        assert_eq!(orders[0].1.owner, owner);
-        assert_eq!(orders[0].1.offer, offer);
-    }
-}
+use std::cmp::min;
+
+use crate::{error::ContractError, pool::Pool};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal256, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::Map;
+use rujira_rs::bid_pool;
+
+#[allow(clippy::type_complexity)]
+pub const ORDERS: Map<
+    (Addr, u8),
+    (
+        Timestamp,
+        Uint128,
+        bid_pool::Bid,
+        Option<Timestamp>,
+        Uint128,
+        Decimal256,
+        Uint128,
+        Option<i8>,
+        Option<u64>,
+    ),
+> = Map::new("orders");
+/// Secondary index so expired orders can be pruned by premium pool without scanning every owner.
+pub const ORDERS_BY_PREMIUM: Map<(u8, Addr), ()> = Map::new("orders-by-premium");
+/// Secondary index from an integrator-supplied `client_id` to the tier an order currently rests
+/// at, following the `client_order_id` pattern Serum/DeepBook-style CLOBs use so an integrator
+/// can track an order's lifecycle by their own identifier instead of our premium tier.
+pub const CLIENT_ORDER_IDS: Map<(Addr, u64), u8> = Map::new("client-order-ids");
+const MAX_LIMIT: u8 = 31;
+const DEFAULT_LIMIT: u8 = 10;
+
+#[cw_serde]
+pub struct Order {
+    pub owner: Addr,
+    pub updated_at: Timestamp,
+    /// Original offer amount, as it was at `updated_at` time
+    pub offer: Uint128,
+    pub bid: bid_pool::Bid,
+    /// Block time after which the order is pruned and its escrow refunded, for
+    /// `TimeInForce::GoodTilTime` orders. `None` means `GoodTilCancelled`.
+    pub valid_to: Option<Timestamp>,
+    /// The smallest amount a crank is allowed to fill this order for. A fill that would consume
+    /// less than `min_fill` of it is skipped rather than ground down into dust. Zero means no
+    /// minimum.
+    pub min_fill: Uint128,
+    /// The pool's fee index as of this order's last fee settlement (see `Pool::sync_order`),
+    /// so only growth since then is folded into `claimable_fee`.
+    pub fee_index: Decimal256,
+    /// Bid-denom swap fee settled from `fee_index` but not yet claimed via `claim_order`.
+    pub claimable_fee: Uint128,
+    /// For an oracle-pegged order, the discount (in premium points) the owner wants to hold
+    /// relative to the live oracle price rather than a fixed tier - see
+    /// `premium::peg_tier` for how this is turned back into a concrete tier, and
+    /// `OrderManager::execute_peg` for the retract-then-recreate migration that keeps it there
+    /// as the oracle drifts. `None` for an ordinary fixed-tier order.
+    pub peg_offset: Option<i8>,
+    /// An integrator-supplied identifier set once at creation, unrelated to our own premium-tier
+    /// key, so off-chain systems can track this order's full lifecycle without having to watch
+    /// which tier it happens to rest at (which can itself change - see `peg_offset`). Looked up
+    /// via the `CLIENT_ORDER_IDS` secondary index in `Order::by_client_id`.
+    pub client_id: Option<u64>,
+}
+
+impl Order {
+    pub fn load(storage: &dyn Storage, owner: &Addr, premium: &u8) -> Result<Self, ContractError> {
+        let (
+            updated_at,
+            offer,
+            bid,
+            valid_to,
+            min_fill,
+            fee_index,
+            claimable_fee,
+            peg_offset,
+            client_id,
+        ) = ORDERS
+            .load(storage, (owner.clone(), *premium))
+            .map_err(|_| ContractError::NotFound {})?;
+        Ok(Self {
+            owner: owner.clone(),
+            updated_at,
+            offer,
+            bid,
+            valid_to,
+            min_fill,
+            fee_index,
+            claimable_fee,
+            peg_offset,
+            client_id,
+        })
+    }
+
+    /// Direct lookup of an owner's order by the `client_id` they supplied at creation, via the
+    /// `CLIENT_ORDER_IDS` index - avoids a linear scan of every tier the owner might rest at.
+    pub fn by_client_id(
+        storage: &dyn Storage,
+        owner: &Addr,
+        client_id: u64,
+    ) -> Result<Self, ContractError> {
+        let premium = CLIENT_ORDER_IDS
+            .load(storage, (owner.clone(), client_id))
+            .map_err(|_| ContractError::NotFound {})?;
+        Self::load(storage, owner, &premium)
+    }
+
+    /// The owner's resting pegged order, if any - an oracle-pegged order can live at a
+    /// different concrete tier every time the oracle moves, so unlike a fixed-tier order it
+    /// can't be looked up by `(owner, premium)` alone and has to be found by scanning. A given
+    /// owner is only expected to hold one pegged position at a time.
+    pub fn find_pegged(storage: &dyn Storage, owner: &Addr) -> StdResult<Option<(u8, Self)>> {
+        ORDERS
+            .prefix(owner.clone())
+            .range(storage, None, None, cosmwasm_std::Order::Ascending)
+            .map(|x| {
+                x.map(
+                    |(
+                        k,
+                        (
+                            updated_at,
+                            offer,
+                            bid,
+                            valid_to,
+                            min_fill,
+                            fee_index,
+                            claimable_fee,
+                            peg_offset,
+                            client_id,
+                        ),
+                    )| {
+                        (
+                            k,
+                            Self {
+                                owner: owner.clone(),
+                                updated_at,
+                                offer,
+                                bid,
+                                valid_to,
+                                min_fill,
+                                fee_index,
+                                claimable_fee,
+                                peg_offset,
+                                client_id,
+                            },
+                        )
+                    },
+                )
+            })
+            .find_map(|x| match x {
+                Ok((premium, order)) if order.peg_offset.is_some() => Some(Ok((premium, order))),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .transpose()
+    }
+
+    /// The page size `by_owner`/`retract_all` actually use for a given `limit` request, bounded
+    /// by `MAX_LIMIT` so a caller can't force an unbounded-gas scan of one owner's orders.
+    pub(crate) fn clamp_limit(limit: Option<u8>) -> u8 {
+        min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT)
+    }
+
+    /// Orders resting for `owner`, skipping any that have passed their `valid_to` - an expired
+    /// order is logically withdrawn already, even though it isn't pruned from storage (and its
+    /// escrow refunded) until the next `Pool` commit touches its premium.
+    pub fn by_owner(
+        storage: &dyn Storage,
+        owner: &Addr,
+        now: &Timestamp,
+        offset: Option<u8>,
+        limit: Option<u8>,
+    ) -> StdResult<Vec<(u8, Self)>> {
+        let limit = Self::clamp_limit(limit) as usize;
+        let offset = offset.unwrap_or(0) as usize;
+        ORDERS
+            .prefix(owner.clone())
+            .range(storage, None, None, cosmwasm_std::Order::Ascending)
+            .map(|x| {
+                x.map(
+                    |(
+                        k,
+                        (
+                            updated_at,
+                            offer,
+                            bid,
+                            valid_to,
+                            min_fill,
+                            fee_index,
+                            claimable_fee,
+                            peg_offset,
+                            client_id,
+                        ),
+                    )| {
+                        (
+                            k,
+                            Self {
+                                owner: owner.clone(),
+                                updated_at,
+                                offer,
+                                bid,
+                                valid_to,
+                                min_fill,
+                                fee_index,
+                                claimable_fee,
+                                peg_offset,
+                                client_id,
+                            },
+                        )
+                    },
+                )
+            })
+            .filter(|x| x.as_ref().map(|(_, order)| !order.expired(now)).unwrap_or(true))
+            .skip(offset)
+            .take(limit)
+            .collect()
+    }
+
+    /// Owners with a resting order in the given premium pool, for expiry pruning.
+    pub fn owners_by_premium(storage: &dyn Storage, premium: u8) -> StdResult<Vec<Addr>> {
+        ORDERS_BY_PREMIUM
+            .prefix(premium)
+            .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+            .collect()
+    }
+
+    pub fn amount(&self) -> Uint128 {
+        self.bid.amount().try_into().unwrap()
+    }
+
+    pub fn expired(&self, now: &Timestamp) -> bool {
+        self.valid_to.map(|valid_to| now > &valid_to).unwrap_or(false)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage, pool: &Pool) -> StdResult<()> {
+        // An empty bid with no outstanding fee is done for good; keep the record around
+        // otherwise, or an unclaimed fee bonus would be wiped out along with it.
+        if self.bid.is_empty() && self.claimable_fee.is_zero() {
+            self.remove(storage, pool);
+            return Ok(());
+        }
+        ORDERS.save(
+            storage,
+            (self.owner.clone(), pool.premium),
+            &(
+                self.updated_at,
+                self.offer,
+                self.bid.clone(),
+                self.valid_to,
+                self.min_fill,
+                self.fee_index,
+                self.claimable_fee,
+                self.peg_offset,
+                self.client_id,
+            ),
+        )?;
+        ORDERS_BY_PREMIUM.save(storage, (pool.premium, self.owner.clone()), &())?;
+        if let Some(client_id) = self.client_id {
+            CLIENT_ORDER_IDS.save(storage, (self.owner.clone(), client_id), &pool.premium)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, storage: &mut dyn Storage, pool: &Pool) {
+        ORDERS.remove(storage, (self.owner.clone(), pool.premium));
+        ORDERS_BY_PREMIUM.remove(storage, (pool.premium, self.owner.clone()));
+        if let Some(client_id) = self.client_id {
+            CLIENT_ORDER_IDS.remove(storage, (self.owner.clone(), client_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use cosmwasm_std::{testing::MockStorage, Addr, Decimal, Timestamp, Uint128};
+    use rujira_rs::exchange::Swappable;
+
+    use crate::pool::Pool;
+
+    #[test]
+    fn query_order() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let offer = Uint128::from(100u128);
+        let mut pool = Pool::load(&store, &0, &Decimal::one());
+        pool.create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+
+        pool.commit(&mut store).unwrap();
+
+        let order = Order::load(&store, &owner, &0).unwrap();
+        assert_eq!(order.owner, owner);
+        assert_eq!(order.offer, offer);
+    }
+
+    #[test]
+    fn query_orders_by_owner() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let owner2 = Addr::unchecked("owner2");
+        let offer = Uint128::from(100u128);
+        let oracle = Decimal::one();
+        let mut pool1 = Pool::load(&store, &0, &oracle);
+        let mut pool2 = Pool::load(&store, &1, &oracle);
+        let mut pool3 = Pool::load(&store, &2, &oracle);
+        let mut pool4 = Pool::load(&store, &10, &oracle);
+        let mut pool5 = Pool::load(&store, &11, &oracle);
+        let mut pool6 = Pool::load(&store, &12, &oracle);
+
+        pool1
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool2
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool3
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool4
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool5
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool6
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+
+        pool1
+            .create_order(&mut store, &timestamp, &owner2, offer)
+            .unwrap();
+
+        pool1.commit(&mut store).unwrap();
+
+        let orders = Order::by_owner(&store, &owner, &timestamp, None, None).unwrap();
+        assert_eq!(orders.len(), 6);
+        assert_eq!(orders[0].1.owner, owner);
+        assert_eq!(orders[0].1.offer, offer);
+    }
+
+    #[test]
+    fn by_owner_excludes_orders_past_their_valid_to() {
+        let mut store = MockStorage::new();
+        let owner = Addr::unchecked("owner");
+        let offer = Uint128::from(100u128);
+        let oracle = Decimal::one();
+
+        let mut pool0 = Pool::load(&store, &0, &oracle);
+        let mut pool1 = Pool::load(&store, &1, &oracle);
+        pool0
+            .create_order_with_expiry(
+                &mut store,
+                &Timestamp::from_seconds(0),
+                &owner,
+                offer,
+                Some(Timestamp::from_seconds(100)),
+                Uint128::zero(),
+            )
+            .unwrap();
+        pool1
+            .create_order_with_expiry(
+                &mut store,
+                &Timestamp::from_seconds(0),
+                &owner,
+                offer,
+                None,
+                Uint128::zero(),
+            )
+            .unwrap();
+        pool0.commit(&mut store).unwrap();
+        pool1.commit(&mut store).unwrap();
+
+        // Before expiry, both orders are visible.
+        let orders = Order::by_owner(&store, &owner, &Timestamp::from_seconds(50), None, None)
+            .unwrap();
+        assert_eq!(orders.len(), 2);
+
+        // Past valid_to, the premium-0 order drops out even though it hasn't been pruned yet.
+        let orders = Order::by_owner(&store, &owner, &Timestamp::from_seconds(200), None, None)
+            .unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].0, 1);
+    }
+
+    #[test]
+    fn find_pegged_ignores_ordinary_orders_and_returns_the_pegged_one() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let oracle = Decimal::one();
+
+        let mut pool0 = Pool::load(&store, &0, &oracle);
+        pool0
+            .create_order(&mut store, &timestamp, &owner, Uint128::from(100u128))
+            .unwrap();
+        assert!(Order::find_pegged(&store, &owner).unwrap().is_none());
+
+        let mut pool5 = Pool::load(&store, &5, &oracle);
+        pool5
+            .create_order_with_peg(
+                &mut store,
+                &timestamp,
+                &owner,
+                Uint128::from(50u128),
+                None,
+                Uint128::zero(),
+                Some(5),
+                None,
+            )
+            .unwrap();
+
+        let (premium, order) = Order::find_pegged(&store, &owner).unwrap().unwrap();
+        assert_eq!(premium, 5);
+        assert_eq!(order.peg_offset, Some(5));
+    }
+
+    #[test]
+    fn by_client_id_finds_the_order_without_knowing_its_tier() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let oracle = Decimal::one();
+
+        let mut pool = Pool::load(&store, &7, &oracle);
+        pool.create_order_with_peg(
+            &mut store,
+            &timestamp,
+            &owner,
+            Uint128::from(100u128),
+            None,
+            Uint128::zero(),
+            None,
+            Some(42),
+        )
+        .unwrap();
+
+        let order = Order::by_client_id(&store, &owner, 42).unwrap();
+        assert_eq!(order.client_id, Some(42));
+        assert_eq!(order.offer, Uint128::from(100u128));
+
+        assert!(Order::by_client_id(&store, &owner, 43).is_err());
+    }
+}
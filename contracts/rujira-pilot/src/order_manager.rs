@@ -1,445 +1,1169 @@
-use cosmwasm_std::{coin, Addr, Decimal, Event, Storage, Timestamp, Uint128};
-use cw_utils::NativeBalance;
-use rujira_rs::pilot::Denoms;
-use std::cmp::Ordering;
-use std::ops::{Mul, Sub};
-
-use crate::{
-    events::{event_create_order, event_increase_order, event_retract_order, event_withdraw_order},
-    order::Order,
-    pool::Pool,
-    ContractError,
-};
-
-pub struct OrderManager {
-    denoms: Denoms,
-    fee: Decimal,
-    owner: Addr,
-    timestamp: Timestamp,
-    oracle: Decimal,
-    max_premium: u8,
-    // NativeBalance can't be negative. Store in and out separately and we'll validate
-    // no negative balances at the end
-    // What we receive from the user and withdrawn and retracted orders
-    receive: NativeBalance,
-    // What we spend creating and increasing orders
-    send: NativeBalance,
-    fees: NativeBalance,
-    events: Vec<Event>,
-}
-
-impl OrderManager {
-    pub fn new(
-        denoms: Denoms,
-        fee: Decimal,
-        max_premium: u8,
-        owner: Addr,
-        timestamp: Timestamp,
-        oracle: Decimal,
-        funds: NativeBalance,
-    ) -> Self {
-        Self {
-            denoms,
-            fee,
-            max_premium,
-            owner,
-            timestamp,
-            oracle,
-            receive: funds,
-            send: NativeBalance::default(),
-            fees: NativeBalance::default(),
-            events: vec![],
-        }
-    }
-
-    pub fn execute_orders(
-        &mut self,
-        storage: &mut dyn Storage,
-        o: Vec<(u8, Uint128)>,
-    ) -> Result<ExecutionResult, ContractError> {
-        for (premium, target) in o {
-            if premium > self.max_premium {
-                return Err(ContractError::InvalidPremium { premium });
-            }
-            let mut pool = Pool::load(storage, &premium, &self.oracle);
-            match pool.load_order(storage, &self.owner) {
-                Ok(mut order) => {
-                    self.execute_existing_order(storage, &mut pool, &mut order, target)?
-                }
-                Err(ContractError::NotFound {}) => {
-                    self.execute_new_order(storage, &mut pool, target)?
-                }
-                Err(err) => return Err(err),
-            }
-        }
-
-        for x in self.send.clone().into_vec() {
-            self.receive = (self.receive.clone() - x)?;
-        }
-
-        Ok(self.into())
-    }
-
-    fn execute_existing_order(
-        &mut self,
-        storage: &mut dyn Storage,
-        pool: &mut Pool,
-        order: &mut Order,
-        target: Uint128,
-    ) -> Result<(), ContractError> {
-        self.maybe_withdraw(storage, pool, order)?;
-        let amount = Uint128::try_from(order.bid.amount()).unwrap();
-        match amount.cmp(&target) {
-            Ordering::Less => {
-                let diff = target - amount;
-
-                let amount = pool.increase_order(storage, order, &self.timestamp, diff)?;
-                let coins = coin(amount.u128(), self.denoms.bid());
-                self.send += coins;
-                self.events.push(event_increase_order(pool, order, &diff));
-            }
-            Ordering::Greater => {
-                let diff = amount - target;
-                let amount = pool.retract_order(storage, order, &self.timestamp, Some(diff))?;
-                let coins = coin(amount.u128(), self.denoms.bid());
-                self.receive += coins;
-                self.events.push(event_retract_order(pool, order, &diff));
-            }
-            Ordering::Equal => {}
-        }
-        Ok(())
-    }
-
-    fn execute_new_order(
-        &mut self,
-        storage: &mut dyn Storage,
-        pool: &mut Pool,
-        target: Uint128,
-    ) -> Result<(), ContractError> {
-        let order = pool.create_order(storage, &self.timestamp, &self.owner, target)?;
-        let coins = coin(order.amount().u128(), self.denoms.bid());
-        self.send += coins;
-        self.events.push(event_create_order(pool, &order));
-        Ok(())
-    }
-
-    fn maybe_withdraw(
-        &mut self,
-        storage: &mut dyn Storage,
-        pool: &mut Pool,
-        order: &mut Order,
-    ) -> Result<(), ContractError> {
-        if order.bid.filled().is_zero() {
-            return Ok(());
-        }
-        let amount = pool.claim_order(storage, order)?;
-        let fees = Decimal::from_ratio(amount, 1u128)
-            .mul(self.fee)
-            .to_uint_ceil();
-
-        let receive = coin(amount.sub(fees).u128(), self.denoms.ask());
-        let fees = coin(fees.u128(), self.denoms.ask());
-
-        self.receive += receive;
-        self.fees += fees;
-        self.events.push(event_withdraw_order(pool, order, &amount));
-        Ok(())
-    }
-}
-
-impl From<&mut OrderManager> for ExecutionResult {
-    fn from(e: &mut OrderManager) -> Self {
-        e.fees.normalize();
-        e.receive.normalize();
-        Self {
-            withdraw: e.receive.clone(),
-            fees: e.fees.clone(),
-            events: e.events.clone(),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct ExecutionResult {
-    pub withdraw: NativeBalance,
-    pub fees: NativeBalance,
-    pub events: Vec<Event>,
-}
-
-#[cfg(test)]
-
-mod tests {
-    use super::*;
-    use cosmwasm_std::{
-        coins,
-        testing::{message_info, mock_dependencies, mock_env},
-    };
-    use std::str::FromStr;
-
-    #[test]
-    fn test_simple_success() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = message_info(&Addr::unchecked("addr0000"), &[]);
-        let oracle = Decimal::from_str("1.0").unwrap();
-        let mut funds = NativeBalance::default();
-        funds += coin(1000, "usdc");
-        let fee = Decimal::from_str("0.001").unwrap();
-
-        let mut e = OrderManager::new(
-            Denoms::new("ruji", "usdc"),
-            fee,
-            30,
-            info.sender,
-            env.block.time,
-            oracle,
-            funds,
-        );
-
-        let res = e
-            .execute_orders(&mut deps.storage, vec![(1, Uint128::from(1000u128))])
-            .unwrap();
-
-        assert_eq!(res.withdraw, NativeBalance::default());
-        let event = res.events[0].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "1");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "1000");
-    }
-
-    #[test]
-    fn test_multiple_orders() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = message_info(&Addr::unchecked("addr0000"), &[]);
-        let fee = Decimal::from_str("0.001").unwrap();
-
-        let oracle = Decimal::from_str("1.0").unwrap();
-        let mut funds = NativeBalance::default();
-        funds += coin(10000, "usdc");
-        funds += coin(10000, "ruji");
-
-        let mut e = OrderManager::new(
-            Denoms::new("ruji", "usdc"),
-            fee,
-            30,
-            info.sender,
-            env.block.time,
-            oracle,
-            funds,
-        );
-
-        let res = e
-            .execute_orders(
-                &mut deps.storage,
-                vec![
-                    (0, Uint128::from(2000u128)),
-                    (1, Uint128::from(1000u128)),
-                    (2, Uint128::from(1200u128)),
-                    (14, Uint128::from(1300u128)),
-                ],
-            )
-            .unwrap();
-        let returned = NativeBalance(vec![coin(10000, "ruji"), coin(4500, "usdc")]);
-        assert_eq!(res.withdraw, returned);
-        let event = res.events[0].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "0");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "2000");
-
-        let event = res.events[1].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "1");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "1000");
-
-        let event = res.events[2].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "2");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "1200");
-
-        let event = res.events[3].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "14");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "1300");
-    }
-
-    #[test]
-    fn test_out_of_funds() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = message_info(&Addr::unchecked("addr0000"), &[]);
-        let fee = Decimal::from_str("0.001").unwrap();
-
-        let oracle = Decimal::from_str("1.0").unwrap();
-        let funds = NativeBalance::default();
-        let mut e = OrderManager::new(
-            Denoms::new("ruji", "usdc"),
-            fee,
-            30,
-            info.sender,
-            env.block.time,
-            oracle,
-            funds,
-        );
-
-        e.execute_orders(&mut deps.storage, vec![(0, Uint128::from(1000u128))])
-            .unwrap_err();
-    }
-
-    #[test]
-    fn test_moving_orders() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = message_info(&Addr::unchecked("addr0000"), &[]);
-        let fee = Decimal::from_str("0.001").unwrap();
-
-        let oracle = Decimal::from_str("1.0").unwrap();
-        let mut funds = NativeBalance::default();
-        funds += coin(10000, "usdc");
-        funds += coin(10000, "ruji");
-        let mut e = OrderManager::new(
-            Denoms::new("ruji", "usdc"),
-            fee,
-            30,
-            info.sender.clone(),
-            env.block.time,
-            oracle,
-            funds,
-        );
-
-        // Same as above
-        e.execute_orders(
-            &mut deps.storage,
-            vec![
-                (0, Uint128::from(1000u128)),
-                (1, Uint128::from(2000u128)),
-                (2, Uint128::from(1200u128)),
-                (10, Uint128::from(1300u128)),
-            ],
-        )
-        .unwrap();
-
-        let mut e = OrderManager::new(
-            Denoms::new("ruji", "usdc"),
-            fee,
-            30,
-            info.sender.clone(),
-            env.block.time,
-            oracle,
-            NativeBalance::default(),
-        );
-
-        let res = e
-            .execute_orders(
-                &mut deps.storage,
-                vec![
-                    (0, Uint128::from(1000u128)),
-                    // Split 1200 ito 2 x 600
-                    (2, Uint128::from(600u128)),
-                    (3, Uint128::from(600u128)),
-                    (9, Uint128::from(1300u128)),
-                    (10, Uint128::zero()),
-                ],
-            )
-            .unwrap();
-
-        let returned = NativeBalance::default();
-        assert_eq!(res.withdraw, returned);
-        assert_eq!(res.events.len(), 4);
-
-        let event = res.events[0].clone();
-        assert_eq!(event.ty, "rujira-orca/order.retract");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "2");
-        assert_eq!(event.attributes[2].key, "amount");
-        assert_eq!(event.attributes[2].value, "600");
-
-        let event = res.events[1].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "3");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "600");
-
-        let event = res.events[2].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "9");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "1300");
-
-        let event = res.events[3].clone();
-        assert_eq!(event.ty, "rujira-orca/order.retract");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "10");
-        assert_eq!(event.attributes[2].key, "amount");
-        assert_eq!(event.attributes[2].value, "1300");
-
-        let mut e = OrderManager::new(
-            Denoms::new("ruji", "usdc"),
-            fee,
-            30,
-            info.sender.clone(),
-            env.block.time,
-            oracle,
-            NativeBalance(coins(300, "usdc")),
-        );
-
-        let res = e
-            .execute_orders(
-                &mut deps.storage,
-                vec![(1, Uint128::from(300u128)), (10, Uint128::from(2000u128))],
-            )
-            .unwrap();
-
-        let returned = NativeBalance::default();
-        assert_eq!(res.withdraw, returned);
-        assert_eq!(res.events.len(), 2);
-
-        let event = res.events[0].clone();
-        assert_eq!(event.ty, "rujira-orca/order.retract");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "1");
-        assert_eq!(event.attributes[2].key, "amount");
-        assert_eq!(event.attributes[2].value, "1700");
-
-        let event = res.events[1].clone();
-        assert_eq!(event.ty, "rujira-orca/order.create");
-        assert_eq!(event.attributes[0].key, "owner");
-        assert_eq!(event.attributes[0].value, "addr0000");
-        assert_eq!(event.attributes[1].key, "premium");
-        assert_eq!(event.attributes[1].value, "10");
-        assert_eq!(event.attributes[2].key, "offer");
-        assert_eq!(event.attributes[2].value, "2000");
-    }
-}
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{coin, Addr, BankMsg, CosmosMsg, Decimal, Event, Storage, Timestamp, Uint128};
+use cw_utils::NativeBalance;
+use rujira_rs::pilot::Denoms;
+use std::cmp::Ordering;
+use std::ops::{Mul, Sub};
+
+use crate::{
+    events::{
+        event_create_order, event_expire_order, event_increase_order, event_repeg_order,
+        event_retract_order, event_withdraw_order,
+    },
+    order::Order,
+    pool::Pool,
+    premium::peg_tier,
+    target_rate::TargetRate,
+    ContractError,
+};
+
+/// How long a resting order should be honoured for.
+#[cw_serde]
+pub enum TimeInForce {
+    /// Rests until explicitly cancelled or retracted - the historical behavior.
+    GoodTilCancelled,
+    /// Pruned, with escrow refunded to the owner, once `env.block.time` passes this value.
+    GoodTilTime(Timestamp),
+    /// The order must be fully satisfied by resting liquidity right now, or not placed at all.
+    /// The premium ladder is maker-only - there is no resting counter-liquidity to instantly
+    /// cross at order-placement time - so a `FillOrKill` request is only ever valid when it
+    /// doesn't grow the order (i.e. it retracts or leaves the existing resting amount alone).
+    FillOrKill,
+}
+
+/// One entry of an `ExecuteMsg::Order` batch: the target resting amount for a premium tier,
+/// plus the time-in-force governing how long that target should be honoured.
+#[cw_serde]
+pub struct OrderRequest {
+    pub premium: u8,
+    pub amount: Uint128,
+    pub time_in_force: TimeInForce,
+    /// The smallest amount a liquidation crank may fill this order for. Zero means no minimum.
+    pub min_fill: Uint128,
+    /// An integrator-supplied identifier, set once at creation - see `Order::client_id`.
+    pub client_id: Option<u64>,
+}
+
+pub struct OrderManager {
+    denoms: Denoms,
+    fee: Decimal,
+    owner: Addr,
+    timestamp: Timestamp,
+    oracle: TargetRate,
+    max_premium: u8,
+    // NativeBalance can't be negative. Store in and out separately and we'll validate
+    // no negative balances at the end
+    // What we receive from the user and withdrawn and retracted orders
+    receive: NativeBalance,
+    // What we spend creating and increasing orders
+    send: NativeBalance,
+    fees: NativeBalance,
+    events: Vec<Event>,
+}
+
+impl OrderManager {
+    pub fn new(
+        denoms: Denoms,
+        fee: Decimal,
+        max_premium: u8,
+        owner: Addr,
+        timestamp: Timestamp,
+        oracle: TargetRate,
+        funds: NativeBalance,
+    ) -> Self {
+        Self {
+            denoms,
+            fee,
+            max_premium,
+            owner,
+            timestamp,
+            oracle,
+            receive: funds,
+            send: NativeBalance::default(),
+            fees: NativeBalance::default(),
+            events: vec![],
+        }
+    }
+
+    /// Automatically ladder `self.receive`'s bid-denom budget across the premium buckets in
+    /// `range` (inclusive), weighted by `weights`, and feed the resulting per-premium targets
+    /// into the same `execute_existing_order`/`execute_new_order` logic `execute_orders` uses.
+    pub fn execute_budget(
+        &mut self,
+        storage: &mut dyn Storage,
+        range: (u8, u8),
+        weights: Distribution,
+    ) -> Result<ExecutionResult, ContractError> {
+        let budget = self
+            .receive
+            .0
+            .iter()
+            .find(|c| c.denom == self.denoms.bid())
+            .map(|c| c.amount)
+            .unwrap_or_default();
+
+        let orders = allocate(range, weights, budget)?
+            .into_iter()
+            .map(|(premium, amount)| OrderRequest {
+                premium,
+                amount,
+                time_in_force: TimeInForce::GoodTilCancelled,
+                min_fill: Uint128::zero(),
+                client_id: None,
+            })
+            .collect();
+
+        self.execute_orders(storage, orders)
+    }
+
+    pub fn execute_orders(
+        &mut self,
+        storage: &mut dyn Storage,
+        o: Vec<OrderRequest>,
+    ) -> Result<ExecutionResult, ContractError> {
+        for req in o {
+            if req.premium > self.max_premium {
+                return Err(ContractError::InvalidPremium {
+                    premium: req.premium,
+                });
+            }
+            let oracle = self.oracle.resolve(self.timestamp);
+            let mut pool = Pool::load(storage, &req.premium, &oracle);
+            match pool.load_order(storage, &self.owner) {
+                Ok(mut order) => self.execute_existing_order(
+                    storage,
+                    &mut pool,
+                    &mut order,
+                    req.amount,
+                    req.time_in_force,
+                    req.min_fill,
+                )?,
+                Err(ContractError::NotFound {}) => self.execute_new_order(
+                    storage,
+                    &mut pool,
+                    req.amount,
+                    req.time_in_force,
+                    req.min_fill,
+                    None,
+                    req.client_id,
+                )?,
+                Err(err) => return Err(err),
+            }
+        }
+
+        for x in self.send.clone().into_vec() {
+            self.receive = (self.receive.clone() - x)?;
+        }
+
+        Ok(self.into())
+    }
+
+    fn execute_existing_order(
+        &mut self,
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        order: &mut Order,
+        target: Uint128,
+        time_in_force: TimeInForce,
+        min_fill: Uint128,
+    ) -> Result<(), ContractError> {
+        self.maybe_withdraw(storage, pool, order)?;
+        let amount = Uint128::try_from(order.bid.amount())?;
+
+        if matches!(time_in_force, TimeInForce::FillOrKill) && target > amount {
+            return Err(ContractError::FillOrKillUnfillable {});
+        }
+
+        match amount.cmp(&target) {
+            Ordering::Less => {
+                let diff = target - amount;
+
+                let amount = pool.increase_order(storage, order, &self.timestamp, diff)?;
+                let coins = coin(amount.u128(), self.denoms.bid());
+                self.send += coins;
+                self.events.push(event_increase_order(pool, order, &diff));
+            }
+            Ordering::Greater => {
+                let diff = amount - target;
+                let amount = pool.retract_order(storage, order, &self.timestamp, Some(diff))?;
+                let coins = coin(amount.u128(), self.denoms.bid());
+                self.receive += coins;
+                self.events.push(event_retract_order(pool, order, &diff));
+            }
+            Ordering::Equal => {}
+        }
+
+        order.valid_to = valid_to(&time_in_force);
+        order.min_fill = min_fill;
+        order.save(storage, pool)?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_new_order(
+        &mut self,
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        target: Uint128,
+        time_in_force: TimeInForce,
+        min_fill: Uint128,
+        peg_offset: Option<i8>,
+        client_id: Option<u64>,
+    ) -> Result<(), ContractError> {
+        if matches!(time_in_force, TimeInForce::FillOrKill) && !target.is_zero() {
+            return Err(ContractError::FillOrKillUnfillable {});
+        }
+
+        let order = pool.create_order_with_peg(
+            storage,
+            &self.timestamp,
+            &self.owner,
+            target,
+            valid_to(&time_in_force),
+            min_fill,
+            peg_offset,
+            client_id,
+        )?;
+        let coins = coin(order.amount().u128(), self.denoms.bid());
+        self.send += coins;
+        self.events.push(event_create_order(pool, &order));
+        Ok(())
+    }
+
+    /// Places or re-targets an oracle-pegged order: `peg_offset` premium points relative to the
+    /// live `oracle_price` (the Pyth-style feed `oracle::load_oracle_price` resolves), rather
+    /// than a fixed tier. The concrete tier is recomputed from `oracle_price` and the book's own
+    /// `TargetRate` basis every call via `premium::peg_tier`; if that tier has moved since the
+    /// order was last touched, the resting amount is fully retracted from the stale tier and
+    /// re-committed into the new one (emitting `order.repeg`) before `target`/`time_in_force`/
+    /// `min_fill` are applied, so the ladder's matching logic never has to know a bid is pegged.
+    /// `client_id` is applied when a new order is created, or when the owner's untagged
+    /// fixed-tier order at that tier is first pegged; an order that is already pegged keeps the
+    /// `client_id` it has (or none), across a migration as well as a re-target.
+    ///
+    /// Driven by `contract.rs`'s `ExecuteMsg::Peg { peg_offset, amount, time_in_force, min_fill,
+    /// client_id }`, with `oracle_price` the same `load_oracle_price` feed that arm's `oracle`
+    /// resolves.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_peg(
+        &mut self,
+        storage: &mut dyn Storage,
+        peg_offset: i8,
+        oracle_price: Decimal,
+        target: Uint128,
+        time_in_force: TimeInForce,
+        min_fill: Uint128,
+        client_id: Option<u64>,
+    ) -> Result<ExecutionResult, ContractError> {
+        let target_rate = self.oracle.resolve(self.timestamp);
+        let tier = peg_tier(peg_offset, oracle_price, target_rate);
+        if tier > self.max_premium {
+            return Err(ContractError::InvalidPremium { premium: tier });
+        }
+
+        match Order::find_pegged(storage, &self.owner)? {
+            Some((stale_tier, mut order)) if stale_tier != tier => {
+                let mut stale_pool = Pool::load(storage, &stale_tier, &target_rate);
+                self.maybe_withdraw(storage, &mut stale_pool, &mut order)?;
+                let resting = order.amount();
+                let refunded = stale_pool.retract_order(
+                    storage,
+                    &mut order,
+                    &self.timestamp,
+                    Some(resting),
+                )?;
+                self.receive += coin(refunded.u128(), self.denoms.bid());
+                self.events
+                    .push(event_repeg_order(&order, stale_tier, tier, &refunded));
+
+                // The order's own `client_id` travels with it across the migration - it
+                // identifies the owner's logical order, not the tier it happens to rest at.
+                let preserved_client_id = order.client_id;
+                let mut new_pool = Pool::load(storage, &tier, &target_rate);
+                self.execute_new_order(
+                    storage,
+                    &mut new_pool,
+                    target,
+                    time_in_force,
+                    min_fill,
+                    Some(peg_offset),
+                    preserved_client_id,
+                )?;
+            }
+            Some((_, mut order)) => {
+                let mut pool = Pool::load(storage, &tier, &target_rate);
+                self.execute_existing_order(
+                    storage,
+                    &mut pool,
+                    &mut order,
+                    target,
+                    time_in_force,
+                    min_fill,
+                )?;
+            }
+            None => {
+                let mut pool = Pool::load(storage, &tier, &target_rate);
+                match pool.load_order(storage, &self.owner) {
+                    Ok(mut order) => {
+                        self.execute_existing_order(
+                            storage,
+                            &mut pool,
+                            &mut order,
+                            target,
+                            time_in_force,
+                            min_fill,
+                        )?;
+                        order.peg_offset = Some(peg_offset);
+                        if order.client_id.is_none() {
+                            order.client_id = client_id;
+                        }
+                        order.save(storage, &pool)?;
+                    }
+                    Err(ContractError::NotFound {}) => {
+                        self.execute_new_order(
+                            storage,
+                            &mut pool,
+                            target,
+                            time_in_force,
+                            min_fill,
+                            Some(peg_offset),
+                            client_id,
+                        )?;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        // Net internally-escrowed spend out of what's refunded/received, so `withdraw` only
+        // reflects the owner's actual external balance change - mirroring `execute_orders`'
+        // reconciliation, since a repeg's retract-then-recreate otherwise looks like a full
+        // external refund followed by a full external spend.
+        for x in self.send.clone().into_vec() {
+            self.receive = (self.receive.clone() - x)?;
+        }
+
+        Ok(self.into())
+    }
+
+    /// Closes every resting order for `self.owner` across premium tiers in one call, instead of
+    /// one retract per tier, by discovering them via `Order::by_owner` (the same bounded
+    /// offset/limit pagination a query uses) and driving each through `execute_existing_order`
+    /// with a zero target - identical to what sending an explicit `OrderRequest { amount: 0, .. }`
+    /// for that premium would do, including the `order.retract` event it already emits.
+    ///
+    /// Returns the cursor to pass as `offset` on a follow-up call if this page's length hit the
+    /// bound (i.e. more orders may remain), or `None` once nothing is left. Every order on the
+    /// page is retracted in full and so leaves storage, which makes that cursor the same
+    /// `offset` this call was given.
+    ///
+    /// Driven by `contract.rs`'s `ExecuteMsg::RetractAll { offset, limit }`, which surfaces the
+    /// cursor back to the caller as a `cursor` attribute when one is returned.
+    pub fn retract_all(
+        &mut self,
+        storage: &mut dyn Storage,
+        offset: Option<u8>,
+        limit: Option<u8>,
+    ) -> Result<(ExecutionResult, Option<u8>), ContractError> {
+        let page = Order::by_owner(storage, &self.owner, &self.timestamp, offset, limit)?;
+        let page_len = page.len() as u8;
+
+        for (premium, mut order) in page {
+            let oracle = self.oracle.resolve(self.timestamp);
+            let mut pool = Pool::load(storage, &premium, &oracle);
+            self.execute_existing_order(
+                storage,
+                &mut pool,
+                &mut order,
+                Uint128::zero(),
+                TimeInForce::GoodTilCancelled,
+                Uint128::zero(),
+            )?;
+        }
+
+        let cursor =
+            (page_len == Order::clamp_limit(limit)).then(|| offset.unwrap_or(0));
+
+        Ok((self.into(), cursor))
+    }
+
+    /// Prune expired (`GoodTilTime`) orders resting in `premium`, refunding each owner's escrow
+    /// and emitting an `order.expire` event per owner so off-chain systems can tell an auto-prune
+    /// apart from a self-initiated retract.
+    pub fn prune_expired(
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        denom: &str,
+        now: &Timestamp,
+    ) -> Result<(Vec<CosmosMsg>, Vec<Event>), ContractError> {
+        let mut messages = vec![];
+        let mut events = vec![];
+        for owner in Order::owners_by_premium(storage, pool.premium)? {
+            let mut order = pool.load_order(storage, &owner)?;
+            if !order.expired(now) {
+                continue;
+            }
+            let amount = pool.retract_order(storage, &mut order, now, None)?;
+            events.push(event_expire_order(pool, &order, &amount));
+            if !amount.is_zero() {
+                messages.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: owner.to_string(),
+                    amount: vec![coin(amount.u128(), denom)],
+                }));
+            }
+        }
+        Ok((messages, events))
+    }
+
+    fn maybe_withdraw(
+        &mut self,
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        order: &mut Order,
+    ) -> Result<(), ContractError> {
+        if order.bid.filled().is_zero() && order.claimable_fee.is_zero() {
+            return Ok(());
+        }
+        let (amount, fee_bonus) = pool.claim_order(storage, order)?;
+        let fees = Decimal::from_ratio(amount, 1u128)
+            .mul(self.fee)
+            .to_uint_ceil();
+
+        let receive = coin(amount.sub(fees).u128(), self.denoms.ask());
+        let fees = coin(fees.u128(), self.denoms.ask());
+
+        self.receive += receive;
+        self.fees += fees;
+        if !fee_bonus.is_zero() {
+            self.receive += coin(fee_bonus.u128(), self.denoms.bid());
+        }
+        self.events
+            .push(event_withdraw_order(pool, order, &amount, &fee_bonus));
+        Ok(())
+    }
+}
+
+impl From<&mut OrderManager> for ExecutionResult {
+    fn from(e: &mut OrderManager) -> Self {
+        e.fees.normalize();
+        e.receive.normalize();
+        Self {
+            withdraw: e.receive.clone(),
+            fees: e.fees.clone(),
+            events: e.events.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecutionResult {
+    pub withdraw: NativeBalance,
+    pub fees: NativeBalance,
+    pub events: Vec<Event>,
+}
+
+fn valid_to(time_in_force: &TimeInForce) -> Option<Timestamp> {
+    match time_in_force {
+        TimeInForce::GoodTilTime(valid_to) => Some(*valid_to),
+        TimeInForce::GoodTilCancelled | TimeInForce::FillOrKill => None,
+    }
+}
+
+/// Weighting scheme for `OrderManager::execute_budget`'s automatic premium-bucket sizing.
+#[cw_serde]
+pub enum Distribution {
+    /// Every bucket in the range gets an equal share of the budget
+    Uniform,
+    /// Bucket `i`, 0-indexed from the start of the range, gets weight `i + 1`
+    Linear,
+    /// Bucket `i` gets weight `base^i`, skewing allocation toward the end of the range
+    Exponential { base: Decimal },
+}
+
+/// Split `budget` across every premium in `range` (inclusive) per `weights`, normalizing
+/// weights to sum to 1 and flooring each bucket's share, with the rounding remainder assigned
+/// to the last bucket so the full budget is always allocated, with no dust left over.
+fn allocate(
+    range: (u8, u8),
+    weights: Distribution,
+    budget: Uint128,
+) -> Result<Vec<(u8, Uint128)>, ContractError> {
+    let (start, end) = range;
+    if start > end {
+        return Err(ContractError::InvalidRange { start, end });
+    }
+    let premiums: Vec<u8> = (start..=end).collect();
+
+    let raw_weights: Vec<Decimal> = match weights {
+        Distribution::Uniform => premiums.iter().map(|_| Decimal::one()).collect(),
+        Distribution::Linear => (1..=premiums.len())
+            .map(|i| Decimal::from_ratio(i as u128, 1u128))
+            .collect(),
+        Distribution::Exponential { base } => (0..premiums.len())
+            .map(|i| {
+                (0..i).fold(Decimal::one(), |acc, _| {
+                    acc.checked_mul(base).unwrap_or(Decimal::MAX)
+                })
+            })
+            .collect(),
+    };
+    let total: Decimal = raw_weights.iter().copied().sum();
+
+    let mut allocated = Uint128::zero();
+    let mut targets: Vec<(u8, Uint128)> = premiums
+        .into_iter()
+        .zip(raw_weights)
+        .map(|(premium, weight)| {
+            let share = weight.checked_div(total).unwrap_or_default();
+            let amount = Decimal::from_ratio(budget, 1u128)
+                .checked_mul(share)
+                .unwrap_or_default()
+                .to_uint_floor();
+            allocated += amount;
+            (premium, amount)
+        })
+        .collect();
+
+    if let Some(last) = targets.last_mut() {
+        last.1 += budget - allocated;
+    }
+
+    Ok(targets)
+}
+
+#[cfg(test)]
+
+mod tests {
+    use super::*;
+    use cosmwasm_std::{
+        coins,
+        testing::{message_info, mock_dependencies, mock_env},
+    };
+    use std::str::FromStr;
+
+    fn gtc(premium: u8, amount: u128) -> OrderRequest {
+        OrderRequest {
+            premium,
+            amount: Uint128::from(amount),
+            time_in_force: TimeInForce::GoodTilCancelled,
+            min_fill: Uint128::zero(),
+            client_id: None,
+        }
+    }
+
+    #[test]
+    fn test_simple_success() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let oracle = TargetRate::Fixed(Decimal::from_str("1.0").unwrap());
+        let mut funds = NativeBalance::default();
+        funds += coin(1000, "usdc");
+        let fee = Decimal::from_str("0.001").unwrap();
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            oracle,
+            funds,
+        );
+
+        let res = e
+            .execute_orders(&mut deps.storage, vec![gtc(1, 1000)])
+            .unwrap();
+
+        assert_eq!(res.withdraw, NativeBalance::default());
+        let event = res.events[0].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "1");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "1000");
+    }
+
+    #[test]
+    fn prune_expired_refunds_and_emits_one_expire_event_per_owner() {
+        let mut deps = mock_dependencies();
+        let oracle = Decimal::from_str("1.0").unwrap();
+
+        let mut pool = Pool::load(&deps.storage, &1, &oracle);
+        pool.create_order_with_expiry(
+            &mut deps.storage,
+            &Timestamp::from_seconds(0),
+            &Addr::unchecked("expired"),
+            Uint128::from(1000u128),
+            Some(Timestamp::from_seconds(100)),
+            Uint128::zero(),
+        )
+        .unwrap();
+        pool.create_order_with_expiry(
+            &mut deps.storage,
+            &Timestamp::from_seconds(0),
+            &Addr::unchecked("still_good"),
+            Uint128::from(500u128),
+            None,
+            Uint128::zero(),
+        )
+        .unwrap();
+
+        let (messages, events) = OrderManager::prune_expired(
+            &mut deps.storage,
+            &mut pool,
+            "usdc",
+            &Timestamp::from_seconds(200),
+        )
+        .unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].ty, "rujira-orca/order.expire");
+        assert_eq!(events[0].attributes[0].value, "expired");
+        assert_eq!(events[0].attributes[2].value, "1000");
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0],
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "expired".to_string(),
+                amount: coins(1000, "usdc"),
+            })
+        );
+
+        // The still-good GoodTilCancelled order is untouched by the prune.
+        assert!(Order::by_owner(
+            &deps.storage,
+            &Addr::unchecked("still_good"),
+            &Timestamp::from_seconds(200),
+            None,
+            None
+        )
+        .unwrap()
+        .iter()
+        .any(|(_, order)| order.offer == Uint128::from(500u128)));
+    }
+
+    #[test]
+    fn test_multiple_orders() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+
+        let oracle = TargetRate::Fixed(Decimal::from_str("1.0").unwrap());
+        let mut funds = NativeBalance::default();
+        funds += coin(10000, "usdc");
+        funds += coin(10000, "ruji");
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            oracle,
+            funds,
+        );
+
+        let res = e
+            .execute_orders(
+                &mut deps.storage,
+                vec![gtc(0, 2000), gtc(1, 1000), gtc(2, 1200), gtc(14, 1300)],
+            )
+            .unwrap();
+        let returned = NativeBalance(vec![coin(10000, "ruji"), coin(4500, "usdc")]);
+        assert_eq!(res.withdraw, returned);
+        let event = res.events[0].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "0");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "2000");
+
+        let event = res.events[1].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "1");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "1000");
+
+        let event = res.events[2].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "2");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "1200");
+
+        let event = res.events[3].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "14");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "1300");
+    }
+
+    #[test]
+    fn test_out_of_funds() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+
+        let oracle = TargetRate::Fixed(Decimal::from_str("1.0").unwrap());
+        let funds = NativeBalance::default();
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            oracle,
+            funds,
+        );
+
+        e.execute_orders(&mut deps.storage, vec![gtc(0, 1000)])
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_moving_orders() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+
+        let oracle = TargetRate::Fixed(Decimal::from_str("1.0").unwrap());
+        let mut funds = NativeBalance::default();
+        funds += coin(10000, "usdc");
+        funds += coin(10000, "ruji");
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            oracle,
+            funds,
+        );
+
+        // Same as above
+        e.execute_orders(
+            &mut deps.storage,
+            vec![gtc(0, 1000), gtc(1, 2000), gtc(2, 1200), gtc(10, 1300)],
+        )
+        .unwrap();
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            oracle.clone(),
+            NativeBalance::default(),
+        );
+
+        let res = e
+            .execute_orders(
+                &mut deps.storage,
+                vec![
+                    gtc(0, 1000),
+                    // Split 1200 ito 2 x 600
+                    gtc(2, 600),
+                    gtc(3, 600),
+                    gtc(9, 1300),
+                    gtc(10, 0),
+                ],
+            )
+            .unwrap();
+
+        let returned = NativeBalance::default();
+        assert_eq!(res.withdraw, returned);
+        assert_eq!(res.events.len(), 4);
+
+        let event = res.events[0].clone();
+        assert_eq!(event.ty, "rujira-orca/order.retract");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "2");
+        assert_eq!(event.attributes[2].key, "amount");
+        assert_eq!(event.attributes[2].value, "600");
+
+        let event = res.events[1].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "3");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "600");
+
+        let event = res.events[2].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "9");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "1300");
+
+        let event = res.events[3].clone();
+        assert_eq!(event.ty, "rujira-orca/order.retract");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "10");
+        assert_eq!(event.attributes[2].key, "amount");
+        assert_eq!(event.attributes[2].value, "1300");
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            oracle.clone(),
+            NativeBalance(coins(300, "usdc")),
+        );
+
+        let res = e
+            .execute_orders(
+                &mut deps.storage,
+                vec![gtc(1, 300), gtc(10, 2000)],
+            )
+            .unwrap();
+
+        let returned = NativeBalance::default();
+        assert_eq!(res.withdraw, returned);
+        assert_eq!(res.events.len(), 2);
+
+        let event = res.events[0].clone();
+        assert_eq!(event.ty, "rujira-orca/order.retract");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "1");
+        assert_eq!(event.attributes[2].key, "amount");
+        assert_eq!(event.attributes[2].value, "1700");
+
+        let event = res.events[1].clone();
+        assert_eq!(event.ty, "rujira-orca/order.create");
+        assert_eq!(event.attributes[0].key, "owner");
+        assert_eq!(event.attributes[0].value, "addr0000");
+        assert_eq!(event.attributes[1].key, "premium");
+        assert_eq!(event.attributes[1].value, "10");
+        assert_eq!(event.attributes[2].key, "offer");
+        assert_eq!(event.attributes[2].value, "2000");
+    }
+
+    #[test]
+    fn retract_all_closes_every_premium_in_one_call_and_emits_one_retract_each() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+        let oracle = TargetRate::Fixed(Decimal::from_str("1.0").unwrap());
+
+        let mut funds = NativeBalance::default();
+        funds += coin(10000, "usdc");
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            oracle.clone(),
+            funds,
+        );
+        e.execute_orders(
+            &mut deps.storage,
+            vec![gtc(0, 1000), gtc(1, 2000), gtc(2, 1200)],
+        )
+        .unwrap();
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            oracle,
+            NativeBalance::default(),
+        );
+        let (res, cursor) = e.retract_all(&mut deps.storage, None, None).unwrap();
+
+        assert_eq!(res.withdraw, NativeBalance(coins(4200, "usdc")));
+        assert_eq!(res.events.len(), 3);
+        for (event, premium, amount) in [
+            (&res.events[0], "0", "1000"),
+            (&res.events[1], "1", "2000"),
+            (&res.events[2], "2", "1200"),
+        ] {
+            assert_eq!(event.ty, "rujira-orca/order.retract");
+            assert_eq!(event.attributes[1].key, "premium");
+            assert_eq!(event.attributes[1].value, premium);
+            assert_eq!(event.attributes[2].key, "amount");
+            assert_eq!(event.attributes[2].value, amount);
+        }
+        // Only 3 orders existed - well under the default limit - so there's nothing left to page
+        // through on a follow-up call.
+        assert_eq!(cursor, None);
+
+        assert!(Order::by_owner(
+            &deps.storage,
+            &Addr::unchecked("addr0000"),
+            &env.block.time,
+            None,
+            None
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn retract_all_returns_a_cursor_when_more_orders_remain() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+        let oracle = TargetRate::Fixed(Decimal::from_str("1.0").unwrap());
+
+        let mut funds = NativeBalance::default();
+        funds += coin(10000, "usdc");
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            oracle.clone(),
+            funds,
+        );
+        e.execute_orders(
+            &mut deps.storage,
+            vec![gtc(0, 1000), gtc(1, 1000), gtc(2, 1000)],
+        )
+        .unwrap();
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            oracle.clone(),
+            NativeBalance::default(),
+        );
+        let (res, cursor) = e
+            .retract_all(&mut deps.storage, None, Some(2))
+            .unwrap();
+        assert_eq!(res.events.len(), 2);
+        assert_eq!(cursor, Some(0));
+
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            oracle,
+            NativeBalance::default(),
+        );
+        let (res, cursor) = e
+            .retract_all(&mut deps.storage, cursor, Some(2))
+            .unwrap();
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(cursor, None);
+    }
+
+    #[test]
+    fn execute_peg_places_a_new_order_at_the_tier_the_offset_currently_maps_to() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+        let rate = Decimal::from_str("1.0").unwrap();
+
+        let mut funds = NativeBalance::default();
+        funds += coin(1000, "usdc");
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            TargetRate::Fixed(rate),
+            funds,
+        );
+
+        // Oracle price equal to the book's target rate, so a 10-point offset maps to tier 10.
+        let res = e
+            .execute_peg(
+                &mut deps.storage,
+                10,
+                rate,
+                Uint128::from(1000u128),
+                TimeInForce::GoodTilCancelled,
+                Uint128::zero(),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "rujira-orca/order.create");
+        assert_eq!(res.events[0].attributes[1].value, "10");
+
+        let order = Order::find_pegged(&deps.storage, &Addr::unchecked("addr0000"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.0, 10);
+        assert_eq!(order.1.peg_offset, Some(10));
+    }
+
+    #[test]
+    fn execute_peg_migrates_a_resting_order_when_the_oracle_drifts_to_a_new_tier() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+        let rate = Decimal::from_str("1.0").unwrap();
+
+        let mut funds = NativeBalance::default();
+        funds += coin(2000, "usdc");
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            TargetRate::Fixed(rate),
+            funds,
+        );
+        e.execute_peg(
+            &mut deps.storage,
+            10,
+            rate,
+            Uint128::from(1000u128),
+            TimeInForce::GoodTilCancelled,
+            Uint128::zero(),
+            None,
+        )
+        .unwrap();
+
+        // The live oracle price now sits below the book's target rate, so the same 10-point
+        // offset maps to a deeper tier - the order should migrate there automatically.
+        let drifted_price = Decimal::from_str("0.9").unwrap();
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            TargetRate::Fixed(rate),
+            NativeBalance::default(),
+        );
+        let res = e
+            .execute_peg(
+                &mut deps.storage,
+                10,
+                drifted_price,
+                Uint128::from(1000u128),
+                TimeInForce::GoodTilCancelled,
+                Uint128::zero(),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(res.events.len(), 2);
+        assert_eq!(res.events[0].ty, "rujira-orca/order.repeg");
+        assert_eq!(res.events[0].attributes[1].value, "10");
+        assert_eq!(res.events[0].attributes[2].key, "to_premium");
+        let new_tier = res.events[0].attributes[2].value.parse::<u8>().unwrap();
+        assert!(new_tier > 10);
+        assert_eq!(res.events[1].ty, "rujira-orca/order.create");
+        assert_eq!(res.events[1].attributes[1].value, new_tier.to_string());
+
+        // Nothing is left resting at the stale tier.
+        assert!(Order::load(&deps.storage, &Addr::unchecked("addr0000"), &10).is_err());
+
+        let order = Order::find_pegged(&deps.storage, &Addr::unchecked("addr0000"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.0, new_tier);
+    }
+
+    #[test]
+    fn client_id_is_tagged_on_create_and_survives_a_peg_migration() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let fee = Decimal::from_str("0.001").unwrap();
+        let rate = Decimal::from_str("1.0").unwrap();
+
+        let mut funds = NativeBalance::default();
+        funds += coin(1000, "usdc");
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender.clone(),
+            env.block.time,
+            TargetRate::Fixed(rate),
+            funds,
+        );
+        let res = e
+            .execute_peg(
+                &mut deps.storage,
+                10,
+                rate,
+                Uint128::from(1000u128),
+                TimeInForce::GoodTilCancelled,
+                Uint128::zero(),
+                Some(7),
+            )
+            .unwrap();
+        assert_eq!(res.events[0].attributes[3].key, "client_id");
+        assert_eq!(res.events[0].attributes[3].value, "7");
+
+        let order = Order::by_client_id(&deps.storage, &Addr::unchecked("addr0000"), 7).unwrap();
+        assert_eq!(order.peg_offset, Some(10));
+
+        // The oracle drifts to a new tier - the order migrates, but keeps the same client_id.
+        let drifted_price = Decimal::from_str("0.9").unwrap();
+        let mut e = OrderManager::new(
+            Denoms::new("ruji", "usdc"),
+            fee,
+            30,
+            info.sender,
+            env.block.time,
+            TargetRate::Fixed(rate),
+            NativeBalance::default(),
+        );
+        e.execute_peg(
+            &mut deps.storage,
+            10,
+            drifted_price,
+            Uint128::from(1000u128),
+            TimeInForce::GoodTilCancelled,
+            Uint128::zero(),
+            None,
+        )
+        .unwrap();
+
+        let order = Order::by_client_id(&deps.storage, &Addr::unchecked("addr0000"), 7).unwrap();
+        assert_eq!(order.offer, Uint128::from(1000u128));
+    }
+}
@@ -1,6 +1,6 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Addr, Attribute, Decimal, Decimal256, StdResult, Storage, Timestamp, Uint128, Uint256,
+    Addr, Attribute, Decimal, Decimal256, Event, StdResult, Storage, Timestamp, Uint128, Uint256,
 };
 use cw_storage_plus::Map;
 use rujira_rs::{
@@ -9,12 +9,19 @@ use rujira_rs::{
     DecimalScaled,
 };
 
-use crate::{order::Order, premium::Premium, ContractError};
+use crate::{amm::AmmPool, events::event_fill, order::Order, premium::Premium, ContractError};
 const SNAPSHOTS: Map<(u8, bid_pool::SumSnapshotKey), DecimalScaled> = Map::new("snapshots");
 // The POOLS Map is used simply as an indicator that there is a non-zero BidPool at this key
 // The BID_POOLS Map is used to store the BidPool itself, and the Key is used to populate the Pool values
 const POOLS: Map<u8, ()> = Map::new("pools");
 const BID_POOLS: Map<u8, bid_pool::Pool> = Map::new("bid-pools");
+const FEE_RATES: Map<u8, Decimal> = Map::new("fee-rates");
+/// Running fee-per-bid-unit index, credited on every fill by `consumed_bids * fee_rate / total`
+/// and consulted by `sync_order` to settle each order's proportional share - the fee-accrual
+/// counterpart to `SNAPSHOTS` above, but a live running total rather than a per-era snapshot,
+/// since a fee share only needs "how much accrued since I last looked", not which era a bid was
+/// created in.
+const FEE_INDEX: Map<u8, Decimal256> = Map::new("fee-index");
 
 /// A wrapper around a BidPool to provide a side & price, used for keying orders and
 /// storing pools for iterating during execution
@@ -23,8 +30,14 @@ pub struct Pool {
     pub premium: u8,
     pub rate: Decimal,
     pub pool: bid_pool::Pool,
+    /// Share of every fill's `consumed_bids` retained for resting bidders instead of paid out
+    /// to the taker, as a reward for providing this premium bucket's liquidity.
+    pub fee_rate: Decimal,
+    fee_index: Decimal256,
     #[serde(skip)]
     pending_sum_snapshots: Vec<SumSnapshot>,
+    #[serde(skip)]
+    pending_fee_accrued: Decimal256,
 }
 
 impl Pool {
@@ -42,7 +55,10 @@ impl Pool {
                         // The presence of the key indicates a BidPool should be present,
                         // so we should panic if this is incorrect
                         pool: BID_POOLS.load(storage, premium).unwrap(),
+                        fee_rate: FEE_RATES.load(storage, premium).unwrap_or_default(),
+                        fee_index: FEE_INDEX.load(storage, premium).unwrap_or_default(),
                         pending_sum_snapshots: vec![],
+                        pending_fee_accrued: Decimal256::zero(),
                     }),
                     Err(_) => None,
                 }
@@ -56,22 +72,151 @@ impl Pool {
             premium: *premium,
             rate: premium.to_rate(oracle),
             pool: BID_POOLS.load(storage, *premium).unwrap_or_default(),
+            fee_rate: FEE_RATES.load(storage, *premium).unwrap_or_default(),
+            fee_index: FEE_INDEX.load(storage, *premium).unwrap_or_default(),
             pending_sum_snapshots: vec![],
+            pending_fee_accrued: Decimal256::zero(),
         }
     }
 
+    /// Updates this premium bucket's fee rate. A fill's accrued delta is held on the in-memory
+    /// `Pool` only until `commit` flushes it into `FEE_INDEX`, in the same message as the swap,
+    /// so fees earned under the old rate are already frozen in the index by the time this runs -
+    /// there's nothing outstanding left to snapshot, only the rate future fills will use changes.
+    pub fn set_fee_rate(
+        storage: &mut dyn Storage,
+        premium: u8,
+        new_rate: Decimal,
+    ) -> StdResult<()> {
+        FEE_RATES.save(storage, premium, &new_rate)
+    }
+
+    /// Consume `offered_ask` against resting bid liquidity across every active premium bucket,
+    /// walking from the highest premium down (best discount for the protocol filled first)
+    /// until either the book is exhausted or the offer is. `POOLS` already tracks non-empty
+    /// buckets and drops a key as soon as a bucket empties in `commit`, so ranging it in
+    /// descending order doubles as the sorted index a liquidation crank needs - no separate
+    /// heap required. Returns the ask amount left unfilled, plus one `order.fill` event per
+    /// bucket actually touched, so the caller can route the remainder elsewhere.
+    pub fn fill_liquidation(
+        storage: &mut dyn Storage,
+        oracle: &Decimal,
+        now: &Timestamp,
+        offered_ask: Uint128,
+    ) -> Result<(Uint128, Vec<Event>), ContractError> {
+        let premiums = POOLS
+            .keys(storage, None, None, cosmwasm_std::Order::Descending)
+            .collect::<StdResult<Vec<u8>>>()?;
+
+        let mut remaining = offered_ask;
+        let mut events = vec![];
+
+        for premium in premiums {
+            if remaining.is_zero() {
+                break;
+            }
+
+            let mut pool = Self::load(storage, &premium, oracle);
+            let total_bid = pool.total();
+            if total_bid.is_zero() {
+                continue;
+            }
+
+            // The bucket's pro-rata distribution below has no notion of a per-order minimum, so
+            // pull out any order whose estimated share of this fill would land under its own
+            // `min_fill` before the distribution ever touches it - a retract-then-recreate at
+            // the same size, invisible to a pro-rata pool where there is no time priority to lose.
+            let bid_needed = remaining.mul_floor(pool.rate);
+            let ratio = Decimal::from_ratio(bid_needed.min(total_bid), total_bid);
+
+            let mut excluded = vec![];
+            if ratio < Decimal::one() {
+                for owner in Order::owners_by_premium(storage, premium)? {
+                    let mut order = pool.load_order(storage, &owner)?;
+                    if order.min_fill.is_zero() {
+                        continue;
+                    }
+                    let resting = order.amount();
+                    if resting.mul_floor(ratio) < order.min_fill {
+                        pool.retract_order(storage, &mut order, now, Some(resting))?;
+                        excluded.push((order, resting));
+                    }
+                }
+            }
+
+            let (ask_consumed, _bid_consumed) = pool.swap(remaining)?;
+            pool.commit(storage)?;
+
+            for (order, resting) in excluded {
+                pool.create_order_with_peg(
+                    storage,
+                    now,
+                    &order.owner,
+                    resting,
+                    order.valid_to,
+                    order.min_fill,
+                    order.peg_offset,
+                    order.client_id,
+                )?;
+            }
+
+            remaining -= ask_consumed;
+            events.push(event_fill(&pool, &ask_consumed, &pool.total()));
+        }
+
+        Ok((remaining, events))
+    }
+
     pub fn create_order(
         &mut self,
         storage: &mut dyn Storage,
         timestamp: &Timestamp,
         owner: &Addr,
         offer: Uint128,
+    ) -> Result<Order, ContractError> {
+        self.create_order_with_expiry(storage, timestamp, owner, offer, None, Uint128::zero())
+    }
+
+    pub fn create_order_with_expiry(
+        &mut self,
+        storage: &mut dyn Storage,
+        timestamp: &Timestamp,
+        owner: &Addr,
+        offer: Uint128,
+        valid_to: Option<Timestamp>,
+        min_fill: Uint128,
+    ) -> Result<Order, ContractError> {
+        self.create_order_with_peg(
+            storage, timestamp, owner, offer, valid_to, min_fill, None, None,
+        )
+    }
+
+    /// As `create_order_with_expiry`, but also tags the order as oracle-pegged at `peg_offset`
+    /// premium points, for `OrderManager::execute_peg` to migrate between tiers later, and/or
+    /// with an integrator-supplied `client_id` for `Order::by_client_id` lookup.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_order_with_peg(
+        &mut self,
+        storage: &mut dyn Storage,
+        timestamp: &Timestamp,
+        owner: &Addr,
+        offer: Uint128,
+        valid_to: Option<Timestamp>,
+        min_fill: Uint128,
+        peg_offset: Option<i8>,
+        client_id: Option<u64>,
     ) -> Result<Order, ContractError> {
         let order = Order {
             owner: owner.clone(),
             offer,
             updated_at: *timestamp,
             bid: self.pool.new_bid(offer.into()),
+            valid_to,
+            min_fill,
+            fee_index: self.fee_index,
+            claimable_fee: Uint128::zero(),
+            peg_offset,
+            client_id,
         };
         self.commit(storage)?;
         order.save(storage, self)?;
@@ -115,14 +260,18 @@ impl Pool {
         Ok(Uint128::try_from(refund_amount)?)
     }
 
+    /// Claims the order's filled ask-side amount plus any settled bid-side fee bonus, returned
+    /// as `(filled, fee_bonus)` since they're paid out in different denoms.
     pub fn claim_order(
         &mut self,
         storage: &mut dyn Storage,
         order: &mut Order,
-    ) -> Result<Uint128, ContractError> {
+    ) -> Result<(Uint128, Uint128), ContractError> {
         let claimed = order.bid.claim_filled();
+        let fee_bonus = order.claimable_fee;
+        order.claimable_fee = Uint128::zero();
         order.save(storage, self)?;
-        Ok(Uint128::try_from(claimed)?)
+        Ok((Uint128::try_from(claimed)?, fee_bonus))
     }
 
     pub fn sync_order(
@@ -131,7 +280,24 @@ impl Pool {
         order: &mut Order,
     ) -> Result<(), ContractError> {
         let sum_snapshot = self.sum_snapshot(storage, &order.bid).ok();
-        Ok(self.pool.sync_bid(&mut order.bid, sum_snapshot)?)
+        self.pool.sync_bid(&mut order.bid, sum_snapshot)?;
+        self.sync_fee(order);
+        Ok(())
+    }
+
+    /// Settles `order`'s share of `fee_index` growth since its last settlement into
+    /// `claimable_fee`, proportional to its resting bid size at the time - the fee counterpart
+    /// to `sync_bid` folding newly-closed sum snapshots into the bid's filled amount above.
+    fn sync_fee(&self, order: &mut Order) {
+        let delta = self
+            .fee_index
+            .checked_sub(order.fee_index)
+            .unwrap_or_default();
+        if !delta.is_zero() {
+            let accrued = delta * Decimal256::from_ratio(order.amount(), 1u128);
+            order.claimable_fee += Uint128::try_from(accrued.to_uint_floor()).unwrap();
+        }
+        order.fee_index = self.fee_index;
     }
 
     fn sum_snapshot(&self, storage: &dyn Storage, bid: &bid_pool::Bid) -> StdResult<DecimalScaled> {
@@ -142,22 +308,34 @@ impl Pool {
 
 impl Swappable for Pool {
     fn swap(&mut self, offer: Uint128) -> Result<(Uint128, Uint128), SwapError> {
+        let total_bid = self.total();
         let res = self
             .pool
             .distribute(offer.into(), &Decimal256::from(self.rate))?;
 
         self.pending_sum_snapshots = res.snapshots;
 
-        Ok((
-            res.consumed_offer.try_into()?,
-            res.consumed_bids.try_into()?,
-        ))
+        let consumed_bids: Uint128 = res.consumed_bids.try_into()?;
+        let fee = consumed_bids.mul_floor(self.fee_rate);
+        if !fee.is_zero() && !total_bid.is_zero() {
+            self.pending_fee_accrued +=
+                Decimal256::from_ratio(fee, 1u128) / Decimal256::from_ratio(total_bid, 1u128);
+        }
+
+        Ok((res.consumed_offer.try_into()?, consumed_bids - fee))
     }
 
     fn commit(&self, storage: &mut dyn Storage) -> Result<Commitment, SwapError> {
         for s in self.pending_sum_snapshots.clone() {
             SNAPSHOTS.save(storage, (self.premium, s.key()), &s.sum)?;
         }
+        if !self.pending_fee_accrued.is_zero() {
+            FEE_INDEX.save(
+                storage,
+                self.premium,
+                &(self.fee_index + self.pending_fee_accrued),
+            )?;
+        }
 
         BID_POOLS.save(storage, self.premium, &self.pool)?;
         // Clear empty pools so they're not iterated over during a swap
@@ -183,6 +361,71 @@ impl Swappable for Pool {
     }
 }
 
+/// A venue of liquidity touched during a swap: either a discrete premium-ladder rung or the
+/// continuous constant-product AMM pool. Lets `Swapper` walk both in a single pass, consuming
+/// whichever offers the better marginal price at each step.
+#[derive(Debug)]
+pub enum Liquidity {
+    Ladder(Pool),
+    Amm(AmmPool),
+}
+
+impl Liquidity {
+    /// Every ladder pool plus the AMM pool (if it holds liquidity), ordered best rate first.
+    pub fn iter(
+        storage: &dyn Storage,
+        oracle: &Decimal,
+        fee_taker: Decimal,
+    ) -> Box<dyn Iterator<Item = Self>> {
+        let mut venues: Vec<Self> = Pool::iter(storage, oracle).map(Liquidity::Ladder).collect();
+
+        let amm = AmmPool::load(storage, fee_taker);
+        if !amm.total_shares.is_zero() {
+            venues.push(Liquidity::Amm(amm));
+        }
+
+        venues.sort_by(|a, b| b.rate().cmp(&a.rate()));
+        Box::new(venues.into_iter())
+    }
+}
+
+impl Swappable for Liquidity {
+    fn swap(&mut self, offer: Uint128) -> Result<(Uint128, Uint128), SwapError> {
+        match self {
+            Liquidity::Ladder(pool) => pool.swap(offer),
+            Liquidity::Amm(amm) => amm.swap(offer),
+        }
+    }
+
+    fn commit(&self, storage: &mut dyn Storage) -> Result<Commitment, SwapError> {
+        match self {
+            Liquidity::Ladder(pool) => pool.commit(storage),
+            Liquidity::Amm(amm) => amm.commit(storage),
+        }
+    }
+
+    fn attributes(&self) -> Vec<Attribute> {
+        match self {
+            Liquidity::Ladder(pool) => pool.attributes(),
+            Liquidity::Amm(amm) => amm.attributes(),
+        }
+    }
+
+    fn rate(&self) -> Decimal {
+        match self {
+            Liquidity::Ladder(pool) => pool.rate(),
+            Liquidity::Amm(amm) => amm.rate(),
+        }
+    }
+
+    fn total(&self) -> Uint128 {
+        match self {
+            Liquidity::Ladder(pool) => pool.total(),
+            Liquidity::Amm(amm) => amm.total(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -220,4 +463,94 @@ mod tests {
         let pool = Pool::load(&store, &premium, &oracle);
         assert_eq!(pool.pool, bp);
     }
+
+    #[test]
+    fn fill_liquidation_skips_orders_below_min_fill() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let oracle = Decimal::one();
+        let premium = 0;
+
+        let dust_bidder = Addr::unchecked("dust_bidder");
+        let patient_bidder = Addr::unchecked("patient_bidder");
+
+        let mut pool = Pool::load(&store, &premium, &oracle);
+        // Would receive a ~10 fill from a 100-unit ask against 1000 resting bid - below min_fill.
+        pool.create_order_with_expiry(
+            &mut store,
+            &timestamp,
+            &dust_bidder,
+            Uint128::from(100u128),
+            None,
+            Uint128::from(50u128),
+        )
+        .unwrap();
+        pool.create_order(&mut store, &timestamp, &patient_bidder, Uint128::from(900u128))
+            .unwrap();
+        pool.commit(&mut store).unwrap();
+
+        let (unfilled, events) =
+            Pool::fill_liquidation(&mut store, &oracle, &timestamp, Uint128::from(100u128))
+                .unwrap();
+
+        assert!(unfilled.is_zero());
+        assert_eq!(events.len(), 1);
+
+        let dust_order = Order::load(&store, &dust_bidder, &premium).unwrap();
+        assert_eq!(dust_order.amount(), Uint128::from(100u128));
+    }
+
+    #[test]
+    fn swap_skims_fee_rate_and_settles_it_proportionally_on_sync() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let premium = 0;
+        let oracle = Decimal::one();
+
+        Pool::set_fee_rate(&mut store, premium, Decimal::percent(10)).unwrap();
+
+        let mut pool = Pool::load(&store, &premium, &oracle);
+        pool.create_order(&mut store, &timestamp, &owner, Uint128::from(1000u128))
+            .unwrap();
+
+        let mut pool = Pool::load(&store, &premium, &oracle);
+        let (consumed_offer, consumed_bids) = pool.swap(Uint128::from(100u128)).unwrap();
+        // 10% of the 100 consumed bids is skimmed off before it's returned to the taker.
+        assert_eq!(consumed_offer, Uint128::from(100u128));
+        assert_eq!(consumed_bids, Uint128::from(90u128));
+        pool.commit(&mut store).unwrap();
+
+        let pool = Pool::load(&store, &premium, &oracle);
+        let order = pool.load_order(&store, &owner).unwrap();
+        // The 10 fee is indexed over the 1000 resting before the fill (0.01 a unit) and settled
+        // against the 900 still resting after it, all owned by `owner`.
+        assert_eq!(order.claimable_fee, Uint128::from(9u128));
+    }
+
+    #[test]
+    fn set_fee_rate_does_not_reprice_fees_already_accrued() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let premium = 0;
+        let oracle = Decimal::one();
+
+        Pool::set_fee_rate(&mut store, premium, Decimal::percent(10)).unwrap();
+
+        let mut pool = Pool::load(&store, &premium, &oracle);
+        pool.create_order(&mut store, &timestamp, &owner, Uint128::from(1000u128))
+            .unwrap();
+
+        let mut pool = Pool::load(&store, &premium, &oracle);
+        pool.swap(Uint128::from(100u128)).unwrap();
+        pool.commit(&mut store).unwrap();
+
+        // Raising the rate must not touch what's already been credited to the index.
+        Pool::set_fee_rate(&mut store, premium, Decimal::percent(50)).unwrap();
+
+        let pool = Pool::load(&store, &premium, &oracle);
+        let order = pool.load_order(&store, &owner).unwrap();
+        assert_eq!(order.claimable_fee, Uint128::from(9u128));
+    }
 }
@@ -9,3 +9,67 @@ impl Premium for u8 {
         oracle * Decimal::from_ratio(100 - self, 100u16)
     }
 }
+
+/// Inverts `Premium::to_rate`: the concrete tier whose `to_rate(target_rate)` comes closest to
+/// holding a constant `peg_offset`-point discount off the live `oracle_price`, rather than off
+/// `target_rate` itself. The two only coincide when `oracle_price == target_rate`; as they
+/// diverge (e.g. a liquid-staking redemption rate drifting away from the spot market price),
+/// the returned tier shifts to compensate, which is what lets an oracle-pegged order keep its
+/// real-terms discount constant through a volatile move instead of sitting at a fixed tier.
+///
+/// `peg_offset` is clamped to `[-100, 100]` and the result to `[0, 100]`, matching the range
+/// `Premium::to_rate` itself assumes (it panics on a `premium` above 100).
+pub fn peg_tier(peg_offset: i8, oracle_price: Decimal, target_rate: Decimal) -> u8 {
+    if target_rate.is_zero() {
+        return 100;
+    }
+    let scale = (100 - peg_offset.clamp(-100, 100) as i32).clamp(0, 200) as u128;
+    let desired_rate = oracle_price
+        .checked_mul(Decimal::from_ratio(scale, 100u128))
+        .unwrap_or(Decimal::MAX);
+    let ratio_pct = desired_rate
+        .checked_div(target_rate)
+        .unwrap_or(Decimal::MAX)
+        .checked_mul(Decimal::from_ratio(100u128, 1u128))
+        .unwrap_or(Decimal::MAX);
+
+    let hundred = Decimal::from_ratio(100u128, 1u128);
+    if ratio_pct >= hundred {
+        0
+    } else {
+        (hundred - ratio_pct).to_uint_floor().u128().min(100) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn matches_an_ordinary_tier_when_oracle_price_equals_target_rate() {
+        let rate = Decimal::from_str("1.0").unwrap();
+        assert_eq!(peg_tier(10, rate, rate), 10);
+        assert_eq!(peg_tier(0, rate, rate), 0);
+    }
+
+    #[test]
+    fn deepens_the_tier_as_the_target_rate_drifts_above_the_oracle_price() {
+        let oracle_price = Decimal::from_str("0.95").unwrap();
+        let target_rate = Decimal::one();
+        // A real discount of 10 points off the live price needs a deeper book-basis tier once
+        // the basis itself has drifted 5% above the live price.
+        assert!(peg_tier(10, oracle_price, target_rate) > 10);
+    }
+
+    #[test]
+    fn negative_offsets_and_a_zero_target_rate_both_clamp_to_the_valid_tier_range() {
+        let rate = Decimal::from_str("1.0").unwrap();
+        // An aggressive negative offset asks for a rate above the oracle price - the tightest
+        // tier (0) is as close as the ladder can get.
+        assert_eq!(peg_tier(-50, rate, rate), 0);
+        // A zero target rate has no tier that could match any positive desired rate; treat it
+        // as maximally discounted rather than dividing by zero.
+        assert_eq!(peg_tier(10, rate, Decimal::zero()), 100);
+    }
+}
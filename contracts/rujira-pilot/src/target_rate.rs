@@ -0,0 +1,114 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Timestamp};
+
+/// The oracle rate premium buckets are priced against, either a frozen constant or one that
+/// drifts linearly between two sampled `(rate, timestamp)` observations - the latter tracks a
+/// liquid-staking-derivative's redemption rate, which moves every block rather than sitting at
+/// a single spot reading.
+#[cw_serde]
+pub enum TargetRate {
+    /// A constant rate, for ask/bid pairs with no meaningful drift
+    Fixed(Decimal),
+    /// Interpolates between `from` and `to` (`to` sampled later), clamped so the resolved rate
+    /// can move at most `max_deviation_per_second` away from `from`'s rate for every second
+    /// elapsed since `from`'s timestamp - this bounds how far a stale or manipulated sample
+    /// pair can skew pricing.
+    Drifting {
+        from: (Decimal, Timestamp),
+        to: (Decimal, Timestamp),
+        max_deviation_per_second: Decimal,
+    },
+}
+
+impl TargetRate {
+    /// The effective rate at `at`, per the variant's rule
+    pub fn resolve(&self, at: Timestamp) -> Decimal {
+        match self {
+            TargetRate::Fixed(rate) => *rate,
+            TargetRate::Drifting {
+                from,
+                to,
+                max_deviation_per_second,
+            } => {
+                let (from_rate, from_ts) = *from;
+                let (to_rate, to_ts) = *to;
+                // At least 1s, so a same-timestamp sample pair doesn't divide by zero
+                let span = to_ts.seconds().saturating_sub(from_ts.seconds()).max(1);
+                let elapsed = at.seconds().saturating_sub(from_ts.seconds());
+                let elapsed = Decimal::from_ratio(elapsed, 1u128);
+
+                let (diff, increasing) = if to_rate >= from_rate {
+                    (to_rate - from_rate, true)
+                } else {
+                    (from_rate - to_rate, false)
+                };
+                let slope = diff / Decimal::from_ratio(span as u128, 1u128);
+
+                let raw_drift = slope.checked_mul(elapsed).unwrap_or(Decimal::MAX);
+                let max_drift = max_deviation_per_second
+                    .checked_mul(elapsed)
+                    .unwrap_or(Decimal::MAX);
+                let drift = raw_drift.min(max_drift);
+
+                if increasing {
+                    from_rate + drift
+                } else if drift > from_rate {
+                    Decimal::zero()
+                } else {
+                    from_rate - drift
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_env;
+    use std::str::FromStr;
+
+    #[test]
+    fn fixed_ignores_timestamp() {
+        let rate = TargetRate::Fixed(Decimal::from_str("1.05").unwrap());
+        assert_eq!(rate.resolve(mock_env().block.time), Decimal::from_str("1.05").unwrap());
+        assert_eq!(
+            rate.resolve(mock_env().block.time.plus_seconds(100)),
+            Decimal::from_str("1.05").unwrap()
+        );
+    }
+
+    #[test]
+    fn drifting_interpolates_and_clamps() {
+        let from_ts = mock_env().block.time;
+        let rate = TargetRate::Drifting {
+            from: (Decimal::from_str("1.0").unwrap(), from_ts),
+            to: (Decimal::from_str("1.1").unwrap(), from_ts.plus_seconds(100)),
+            max_deviation_per_second: Decimal::from_str("0.01").unwrap(),
+        };
+
+        // Halfway between the samples, within the cap
+        assert_eq!(
+            rate.resolve(from_ts.plus_seconds(50)),
+            Decimal::from_str("1.05").unwrap()
+        );
+
+        // Far beyond `to`'s timestamp the 0.001/s slope keeps extrapolating; the 0.01/s cap
+        // (10 after 1000s) is looser than the raw drift of 1.0, so it does not bind
+        assert_eq!(
+            rate.resolve(from_ts.plus_seconds(1000)),
+            Decimal::from_str("1.0").unwrap() + Decimal::from_str("0.001").unwrap() * Decimal::from_ratio(1000u128, 1u128)
+        );
+
+        // A cap tighter than the slope binds: 0.0005/s over 50s allows 0.025 of the raw 0.05
+        let capped = TargetRate::Drifting {
+            from: (Decimal::from_str("1.0").unwrap(), from_ts),
+            to: (Decimal::from_str("1.1").unwrap(), from_ts.plus_seconds(100)),
+            max_deviation_per_second: Decimal::from_str("0.0005").unwrap(),
+        };
+        assert_eq!(
+            capped.resolve(from_ts.plus_seconds(50)),
+            Decimal::from_str("1.025").unwrap()
+        );
+    }
+}
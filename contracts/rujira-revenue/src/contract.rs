@@ -1,18 +1,30 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_json_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env,
-    MessageInfo, QuerierWrapper, Reply, Response, StdResult, Storage, SubMsg, WasmMsg,
+    coin, coins, to_json_binary, Addr, Api, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, QuerierWrapper, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128,
+    Uint256, WasmMsg,
 };
+use cw_storage_plus::{Item, Map};
 
+use crate::cw20::{Cw20BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
+use crate::cw4::{Cw4Member, Cw4MemberListResponse, Cw4QueryMsg, Cw4TotalWeightResponse};
 use crate::error::ContractError;
-use crate::events::event_run;
-use crate::state::{Action, Config};
+use crate::events::{event_claim, event_run};
+use crate::state::{Action, AssetInfo, Config, WeightSource};
 use rujira_rs::revenue::{
-    ActionResponse, ActionsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
-    StatusResponse, SudoMsg,
+    ActionResponse, ActionsResponse, ClaimableResponse, ConfigResponse, ExecuteMsg,
+    InstantiateMsg, QueryMsg, StatusResponse, SudoMsg, TargetSource,
 };
 
+/// Per-`(recipient, denom)` balances accrued by pull-mode targets, drained on `ExecuteMsg::Claim`.
+pub static CLAIMABLE: Map<(Addr, String), Uint128> = Map::new("claimable");
+
+/// Number of a batch `Run`'s action replies still outstanding. Distribution only fires once
+/// this reaches zero, so a batch of N actions distributes once after the Nth reply rather than
+/// once per action.
+pub static IN_FLIGHT: Item<u64> = Item::new("in_flight");
+
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -42,24 +54,61 @@ pub fn execute(
             if info.sender != config.executor {
                 return Err(ContractError::Unauthorized {});
             }
-            let action_msg = get_action_msg(deps.storage, deps.querier, &env.contract.address)?;
-
-            match action_msg {
-                Some((action, msg)) => Ok(Response::default()
-                    .add_event(event_run(action.denom))
-                    .add_submessage(SubMsg::reply_always(msg, 0))),
-                // If there's no compatible action, skip to the reply
-                None => {
-                    let mut sends: Vec<CosmosMsg> = vec![];
-                    for target in config.target_denoms() {
-                        distribute_denom(deps.as_ref(), &env, &config, &mut sends, target)?;
+
+            let mut response = Response::default();
+            let mut submsgs = vec![];
+            for _ in 0..config.batch_size.max(1) {
+                match get_action_msg(deps.storage, deps.querier, &env.contract.address)? {
+                    Some((action, msg)) => {
+                        response = response.add_event(event_run(action.denom));
+                        submsgs.push(SubMsg::reply_always(msg, 0));
                     }
+                    // No more compatible actions in this batch; skip straight to distribution.
+                    None => break,
+                }
+            }
 
-                    Ok(Response::default().add_messages(sends))
+            if submsgs.is_empty() {
+                let mut sends: Vec<CosmosMsg> = vec![];
+                for target in config.target_assets() {
+                    distribute_denom(deps.storage, deps.querier, &env, &config, &mut sends, target)?;
                 }
+                return Ok(response.add_messages(sends));
             }
+
+            IN_FLIGHT.save(deps.storage, &(submsgs.len() as u64))?;
+            Ok(response.add_submessages(submsgs))
         }
+        ExecuteMsg::Claim {} => claim(deps, &config, info.sender),
+        ExecuteMsg::ClaimFor { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            claim(deps, &config, addr)
+        }
+    }
+}
+
+/// Drains `claimant`'s accrued pull-mode balances across every `target_assets` entry into one
+/// payout message per asset (a CW20 transfer can't be folded into a native `BankMsg::Send`),
+/// emitting a `revenue/claim` event regardless of whether anything was owed so callers can
+/// distinguish "claimed zero" from "the message never ran".
+fn claim(deps: DepsMut, config: &Config, claimant: Addr) -> Result<Response, ContractError> {
+    let mut claimed: Vec<(String, Uint128)> = vec![];
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for asset in config.target_assets() {
+        let key = (claimant.clone(), asset_key(&asset));
+        let claimable = CLAIMABLE.may_load(deps.storage, key.clone())?.unwrap_or_default();
+        if claimable.is_zero() {
+            continue;
+        }
+        CLAIMABLE.remove(deps.storage, key.clone());
+        claimed.push((key.1, claimable));
+        msgs.push(payout_msg(&asset, &claimant, claimable)?);
     }
+
+    let response = Response::default()
+        .add_event(event_claim(&claimant, &claimed))
+        .add_messages(msgs);
+    Ok(response)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -76,6 +125,7 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
             contract,
             limit,
             msg,
+            min_balance,
         } => {
             Action::set(
                 deps.storage,
@@ -83,6 +133,7 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
                 deps.api.addr_validate(&contract)?,
                 limit,
                 msg,
+                min_balance,
             )?;
             Ok(Response::default())
         }
@@ -90,6 +141,23 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
             Action::unset(deps.storage, denom);
             Ok(Response::default())
         }
+        SudoMsg::SetCw20Action {
+            token,
+            contract,
+            limit,
+            msg,
+            min_balance,
+        } => {
+            Action::set_cw20(
+                deps.storage,
+                deps.api.addr_validate(&token)?,
+                deps.api.addr_validate(&contract)?,
+                limit,
+                msg,
+                min_balance,
+            )?;
+            Ok(Response::default())
+        }
         SudoMsg::SetExecutor(executor) => {
             config.executor = deps.api.addr_validate(&executor)?;
             config.save(deps.storage)?;
@@ -100,6 +168,42 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::AddTargetCw20(token) => {
+            config.add_target_asset(AssetInfo::Cw20(deps.api.addr_validate(&token)?));
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetWeightSource(source) => {
+            config.weight_source = validate_weight_source(deps.api, source)?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetBatchSize(batch_size) => {
+            config.batch_size = batch_size;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetMinDistribution { asset, min_balance } => {
+            config.min_distribution.insert(asset_key(&asset), min_balance);
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+    }
+}
+
+/// Validates a wire-level `TargetSource` into the `Addr`-based `WeightSource` `Config` stores,
+/// the same validate-on-entry pattern as the other `Sudo*` address fields.
+fn validate_weight_source(api: &dyn Api, source: TargetSource) -> Result<WeightSource, ContractError> {
+    match source {
+        TargetSource::Fixed(weights) => Ok(WeightSource::Fixed(
+            weights
+                .into_iter()
+                .map(|(addr, weight)| Ok((api.addr_validate(&addr)?, weight)))
+                .collect::<Result<Vec<_>, ContractError>>()?,
+        )),
+        TargetSource::Group { contract } => Ok(WeightSource::Group {
+            contract: api.addr_validate(&contract)?,
+        }),
     }
 }
 
@@ -110,25 +214,111 @@ fn get_action_msg(
 ) -> StdResult<Option<(Action, WasmMsg)>> {
     // Fetch the next action in the iterator
     if let Some(action) = Action::next(storage)? {
-        let balance = querier.query_balance(contract, action.denom.to_string())?;
+        let amount = query_asset_balance(querier, contract, &action.asset)?;
+        // Below the action's dust threshold - skip the crank rather than spending gas on it.
+        if amount < action.min_balance {
+            return Ok(None);
+        }
+        let balance = coin(amount.u128(), asset_key(&action.asset));
         return match action.execute(balance)? {
             None => Ok(None),
-            Some(msg) => Ok(Some((action, msg))),
+            Some(msg) => Ok(Some((action.clone(), wrap_action_msg(&action.asset, &action.contract, msg)?))),
         };
     }
     Ok(None)
 }
 
+/// Reads the balance of `asset` held by `holder`, dispatching to a native bank query or a
+/// CW20 `Balance` smart query depending on asset kind.
+fn query_asset_balance(
+    querier: QuerierWrapper,
+    holder: &Addr,
+    asset: &AssetInfo,
+) -> StdResult<Uint128> {
+    match asset {
+        AssetInfo::Native(denom) => Ok(querier.query_balance(holder, denom)?.amount),
+        AssetInfo::Cw20(contract) => {
+            let res: Cw20BalanceResponse = querier
+                .query_wasm_smart(contract, &Cw20QueryMsg::Balance { address: holder.to_string() })?;
+            Ok(res.balance)
+        }
+    }
+}
+
+/// The string key `CLAIMABLE` and the balance-check `Coin` passed into `Action::execute` use to
+/// identify an asset uniformly: the native denom, or the CW20 token's contract address.
+fn asset_key(asset: &AssetInfo) -> String {
+    match asset {
+        AssetInfo::Native(denom) => denom.clone(),
+        AssetInfo::Cw20(contract) => contract.to_string(),
+    }
+}
+
+/// `Action::execute`'s returned `WasmMsg` attaches the capped amount as native `funds`, which
+/// only makes sense for native assets. For a CW20 action, re-wrap it as a `Cw20ExecuteMsg::Send`
+/// to the token contract instead, carrying the same capped amount and the configured payload.
+fn wrap_action_msg(asset: &AssetInfo, action_contract: &Addr, msg: WasmMsg) -> StdResult<WasmMsg> {
+    let AssetInfo::Cw20(token) = asset else {
+        return Ok(msg);
+    };
+    let WasmMsg::Execute { funds, msg: payload, .. } = msg else {
+        return Err(StdError::generic_err(
+            "expected Action::execute to return a WasmMsg::Execute",
+        ));
+    };
+    let amount = funds.first().map(|c| c.amount).unwrap_or_default();
+    Ok(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Send {
+            contract: action_contract.to_string(),
+            amount,
+            msg: payload,
+        })?,
+        funds: vec![],
+    })
+}
+
+/// Builds the payout message for crediting `amount` of `asset` to `to`, dispatching to a native
+/// `BankMsg::Send` or a CW20 `Transfer` depending on asset kind.
+fn payout_msg(asset: &AssetInfo, to: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(match asset {
+        AssetInfo::Native(denom) => BankMsg::Send {
+            to_address: to.to_string(),
+            amount: coins(amount.u128(), denom.clone()),
+        }
+        .into(),
+        AssetInfo::Cw20(contract) => WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    })
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, env: Env, _msg: Reply) -> Result<Response, ContractError> {
-    execute_reply(deps.as_ref(), env)
+    execute_reply(deps, env)
 }
 
-pub fn execute_reply(deps: Deps, env: Env) -> Result<Response, ContractError> {
+pub fn execute_reply(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    // No in-flight counter means this reply wasn't part of a batch run - distribute right away,
+    // preserving the pre-batching single-action behavior.
+    let remaining = IN_FLIGHT.may_load(deps.storage)?.unwrap_or(1).saturating_sub(1);
+
+    if remaining > 0 {
+        IN_FLIGHT.save(deps.storage, &remaining)?;
+        return Ok(Response::default());
+    }
+    IN_FLIGHT.remove(deps.storage);
+
     let config = Config::load(deps.storage)?;
     let mut sends: Vec<CosmosMsg> = vec![];
-    for target in config.target_denoms().clone() {
-        distribute_denom(deps, &env, &config, &mut sends, target)?;
+    for target in config.target_assets() {
+        distribute_denom(deps.storage, deps.querier, &env, &config, &mut sends, target)?;
     }
 
     Ok(Response::default().add_messages(sends))
@@ -147,57 +337,176 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Status {} => to_json_binary(&StatusResponse {
             last: Action::last(deps.storage)?,
         }),
+        QueryMsg::Claimable { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            let config = Config::load(deps.storage)?;
+            let claimable = config
+                .target_assets()
+                .into_iter()
+                .filter_map(|asset| {
+                    let key = asset_key(&asset);
+                    let amount = CLAIMABLE
+                        .may_load(deps.storage, (addr.clone(), key.clone()))
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    if amount.is_zero() {
+                        None
+                    } else {
+                        Some(coin(amount.u128(), key))
+                    }
+                })
+                .collect();
+            to_json_binary(&ClaimableResponse { claimable })
+        }
     }
 }
 
 fn distribute_denom(
-    deps: Deps,
+    storage: &mut dyn Storage,
+    querier: QuerierWrapper,
     env: &Env,
     config: &Config,
     sends: &mut Vec<CosmosMsg>,
-    denom: String,
+    asset: AssetInfo,
 ) -> StdResult<()> {
-    let balance = deps
-        .querier
-        .query_balance(env.contract.address.clone(), denom.to_string())?;
-
-    let total_weight = config.target_addresses.iter().fold(0, |a, e| e.1 + a);
-    if !balance.amount.is_zero() {
-        let mut remaining = balance.amount;
-        let mut targets = config.target_addresses.iter().peekable();
-
-        while let Some((addr, weight)) = targets.next() {
-            let amount = if targets.peek().is_none() {
-                remaining
-            } else {
-                let ratio = Decimal::from_ratio(*weight, total_weight);
-                balance.amount.mul_floor(ratio)
-            };
+    let balance = query_asset_balance(querier, &env.contract.address, &asset)?;
 
-            if amount.is_zero() {
-                continue;
-            }
-            remaining -= amount;
-            sends.push(
-                BankMsg::Send {
-                    to_address: addr.to_string(),
-                    amount: coins(amount.u128(), denom.clone()),
-                }
-                .into(),
-            )
+    let min_balance = config
+        .min_distribution
+        .get(&asset_key(&asset))
+        .copied()
+        .unwrap_or_default();
+    if balance.is_zero() || balance < min_balance {
+        // Below threshold: leave the balance untouched so it accumulates for a later Run
+        // rather than paying swap/transfer gas on a dust amount.
+        return Ok(());
+    }
+
+    let weights = resolve_weights(querier, &config.weight_source)?;
+    for (addr, amount) in apportion(balance, &weights) {
+        if amount.is_zero() {
+            continue;
         }
-    };
+
+        // Pull-mode targets accrue a claimable balance instead of being sent to directly, so
+        // contracts or time-locked recipients aren't forced to receive an unsolicited payout.
+        if config.pull_targets.contains(&addr) {
+            let key = (addr.clone(), asset_key(&asset));
+            let accrued = CLAIMABLE.may_load(storage, key.clone())?.unwrap_or_default();
+            CLAIMABLE.save(storage, key, &(accrued + amount))?;
+            continue;
+        }
+
+        sends.push(payout_msg(&asset, &addr, amount)?);
+    }
     Ok(())
 }
 
+/// Resolves the live weights revenue should be apportioned across. `WeightSource::Fixed`
+/// returns its captured weights as-is; `WeightSource::Group` pages through the group
+/// contract's full membership via cw4-style queries and cross-checks the sum against
+/// `TotalWeight`, so a membership change mid-pagination is caught rather than silently
+/// under-counting.
+fn resolve_weights(
+    querier: QuerierWrapper,
+    weight_source: &WeightSource,
+) -> StdResult<Vec<(Addr, u128)>> {
+    match weight_source {
+        WeightSource::Fixed(weights) => Ok(weights.clone()),
+        WeightSource::Group { contract } => {
+            let total: Cw4TotalWeightResponse =
+                querier.query_wasm_smart(contract, &Cw4QueryMsg::TotalWeight {})?;
+
+            let mut members: Vec<Cw4Member> = vec![];
+            let mut start_after = None;
+            loop {
+                let page: Cw4MemberListResponse = querier.query_wasm_smart(
+                    contract,
+                    &Cw4QueryMsg::ListMembers {
+                        start_after: start_after.take(),
+                        limit: None,
+                    },
+                )?;
+                if page.members.is_empty() {
+                    break;
+                }
+                start_after = page.members.last().map(|m| m.addr.clone());
+                members.extend(page.members);
+            }
+
+            let weights: Vec<(Addr, u128)> = members
+                .into_iter()
+                .map(|m| (Addr::unchecked(m.addr), m.weight as u128))
+                .collect();
+
+            let sum: u128 = weights.iter().map(|(_, w)| w).sum();
+            if sum != total.weight as u128 {
+                return Err(StdError::generic_err(
+                    "group membership changed mid-distribution, retry",
+                ));
+            }
+
+            Ok(weights)
+        }
+    }
+}
+
+/// Apportions `total` across `weights` via the largest-remainder (Hamilton) method: every
+/// target first receives `floor(total * weight / total_weight)`, then the leftover units left
+/// by flooring are handed out one at a time to the targets with the largest fractional
+/// remainder `(total * weight) mod total_weight`, breaking ties by ascending index. This keeps
+/// the payouts summing exactly to `total` regardless of iteration order, unlike crediting all
+/// rounding dust to whichever target happens to be visited last.
+fn apportion(total: Uint128, weights: &[(Addr, u128)]) -> Vec<(Addr, Uint128)> {
+    let total_weight: u128 = weights.iter().fold(0, |acc, (_, w)| acc + w);
+    if total_weight == 0 {
+        return weights
+            .iter()
+            .map(|(addr, _)| (addr.clone(), Uint128::zero()))
+            .collect();
+    }
+
+    let total_256 = Uint256::from(total);
+    let total_weight_256 = Uint256::from(total_weight);
+
+    let mut amounts: Vec<Uint128> = Vec::with_capacity(weights.len());
+    let mut remainders: Vec<(usize, Uint256)> = Vec::with_capacity(weights.len());
+
+    for (i, (_, weight)) in weights.iter().enumerate() {
+        let scaled = total_256 * Uint256::from(*weight);
+        let floor = scaled / total_weight_256;
+        remainders.push((i, scaled - floor * total_weight_256));
+        amounts.push(Uint128::try_from(floor).unwrap());
+    }
+
+    let allocated: u128 = amounts.iter().map(|a| a.u128()).sum();
+    let mut leftover = total.u128() - allocated;
+
+    remainders.sort_by(|a, b| b.1.cmp(&a.1));
+    for (i, _) in remainders {
+        if leftover == 0 {
+            break;
+        }
+        amounts[i] += Uint128::one();
+        leftover -= 1;
+    }
+
+    weights
+        .iter()
+        .zip(amounts)
+        .map(|((addr, _), amount)| (addr.clone(), amount))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use cosmwasm_std::{
         coin, from_json,
-        testing::{message_info, mock_dependencies, mock_env},
-        Uint128,
+        testing::{message_info, mock_dependencies, mock_env, MockQuerier},
+        ContractResult, SystemError, SystemResult, Uint128, WasmQuery,
     };
     use cw_multi_test::{BasicApp, ContractWrapper, Executor};
 
@@ -213,6 +522,7 @@ mod tests {
             owner: owner.to_string(),
             target_denoms: vec!["uruji".to_string(), "another".to_string()],
             target_addresses: vec![(fees.to_string(), 1)],
+            pull_targets: vec![],
             executor: executor.to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -243,6 +553,7 @@ mod tests {
             owner: owner.to_string(),
             target_denoms: vec!["uruji".to_string(), "another".to_string()],
             target_addresses: vec![(fees.to_string(), 1)],
+            pull_targets: vec![],
             executor: executor.to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -260,6 +571,8 @@ mod tests {
             contract,
             limit: Uint128::MAX,
             msg: Binary::new(vec![0]),
+            asset: AssetInfo::Native("uatom".to_string()),
+            min_balance: Uint128::zero(),
         };
 
         let a = action.clone();
@@ -272,6 +585,7 @@ mod tests {
                 contract: a.contract.to_string(),
                 limit: a.limit,
                 msg: a.msg,
+                min_balance: a.min_balance,
             },
         )
         .unwrap();
@@ -284,7 +598,8 @@ mod tests {
                 denom: action.denom.clone(),
                 contract: action.contract.to_string(),
                 limit: action.limit,
-                msg: action.msg
+                msg: action.msg,
+                min_balance: action.min_balance,
             }]
         );
 
@@ -355,6 +670,7 @@ mod tests {
                     owner: owner.to_string(),
                     target_denoms: vec!["uruji".to_string(), "another".to_string()],
                     target_addresses: vec![(fees.to_string(), 1)],
+                    pull_targets: vec![],
                     executor: owner.to_string(),
                 },
                 &[],
@@ -448,6 +764,7 @@ mod tests {
                 contract: app.api().addr_make(target).to_string(),
                 limit,
                 msg: Binary::new(vec![0]),
+                min_balance: Uint128::zero(),
             },
         )
         .unwrap();
@@ -489,6 +806,7 @@ mod tests {
                         (another.to_string(), 3),
                         (app.api().addr_make("nope").to_string(), 0),
                     ],
+                    pull_targets: vec![],
                     executor: owner.to_string(),
                 },
                 &[],
@@ -574,4 +892,513 @@ mod tests {
             Uint128::from(1500u128)
         );
     }
+
+    #[test]
+    fn batch_cranking_processes_multiple_actions_per_run() {
+        let mut app = BasicApp::default();
+        let owner = app.api().addr_make("owner");
+        let fees = app.api().addr_make("fees");
+
+        let funds = vec![
+            coin(1000u128, "token-a"),
+            coin(1000u128, "token-b"),
+            coin(1000u128, "token-c"),
+            coin(1000u128, "token-d"),
+            coin(1000u128, "token-e"),
+            coin(1000u128, "uruji"),
+        ];
+
+        app.init_modules(|router, _, storage| {
+            router.bank.init_balance(storage, &owner, funds.clone())
+        })
+        .unwrap();
+        let code = Box::new(
+            ContractWrapper::new(execute, instantiate, query)
+                .with_reply(reply)
+                .with_sudo(sudo),
+        );
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    owner: owner.to_string(),
+                    target_denoms: vec!["uruji".to_string()],
+                    target_addresses: vec![(fees.to_string(), 1)],
+                    pull_targets: vec![],
+                    executor: owner.to_string(),
+                },
+                &[],
+                "revenue",
+                None,
+            )
+            .unwrap();
+
+        app.send_tokens(owner.clone(), contract.clone(), &funds)
+            .unwrap();
+
+        set_action(&mut app, &contract, "token-a", "contract-a", Uint128::MAX);
+        set_action(&mut app, &contract, "token-b", "contract-b", Uint128::MAX);
+        set_action(&mut app, &contract, "token-c", "contract-c", Uint128::MAX);
+        set_action(&mut app, &contract, "token-d", "contract-d", Uint128::MAX);
+        set_action(&mut app, &contract, "token-e", "contract-e", Uint128::MAX);
+
+        app.wasm_sudo(contract.clone(), &SudoMsg::SetBatchSize(5))
+            .unwrap();
+
+        app.execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Run {}, &[])
+            .unwrap();
+
+        // A single Run call should have advanced the iterator across all five actions...
+        let status: StatusResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Status {})
+            .unwrap();
+        assert_eq!(status.last, Some("token-e".to_string()));
+
+        // ...and distribution should only have fired once, after the final action's reply.
+        assert_eq!(
+            app.wrap().query_balance(fees, "uruji").unwrap().amount,
+            Uint128::from(1000u128)
+        );
+    }
+
+    #[test]
+    fn pull_mode_target_accrues_and_is_claimable() {
+        let mut app = BasicApp::default();
+        let owner = app.api().addr_make("owner");
+        let vesting = app.api().addr_make("vesting");
+
+        let funds = vec![coin(1000u128, "uruji")];
+
+        app.init_modules(|router, _, storage| {
+            router.bank.init_balance(storage, &owner, funds.clone())
+        })
+        .unwrap();
+
+        let code = Box::new(
+            ContractWrapper::new(execute, instantiate, query)
+                .with_reply(reply)
+                .with_sudo(sudo),
+        );
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    owner: owner.to_string(),
+                    target_denoms: vec!["uruji".to_string()],
+                    target_addresses: vec![(vesting.to_string(), 1)],
+                    pull_targets: vec![vesting.clone()],
+                    executor: owner.to_string(),
+                },
+                &[],
+                "revenue",
+                None,
+            )
+            .unwrap();
+
+        app.send_tokens(owner.clone(), contract.clone(), &funds)
+            .unwrap();
+
+        set_action(&mut app, &contract, "token-a", "contract-a", Uint128::MAX);
+        app.execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Run {}, &[])
+            .unwrap();
+
+        // Nothing is pushed directly to a pull-mode target...
+        assert_eq!(
+            app.wrap()
+                .query_balance(vesting.clone(), "uruji")
+                .unwrap()
+                .amount,
+            Uint128::zero()
+        );
+
+        // ...instead it accrues as a claimable balance.
+        let claimable: ClaimableResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Claimable {
+                    addr: vesting.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(claimable.claimable, vec![coin(1000u128, "uruji")]);
+
+        let res = app
+            .execute_contract(
+                vesting.clone(),
+                contract.clone(),
+                &ExecuteMsg::Claim {},
+                &[],
+            )
+            .unwrap();
+        assert!(res
+            .events
+            .iter()
+            .any(|e| e.ty == "wasm-rujira-revenue/claim"));
+
+        assert_eq!(
+            app.wrap()
+                .query_balance(vesting.clone(), "uruji")
+                .unwrap()
+                .amount,
+            Uint128::from(1000u128)
+        );
+
+        let claimable: ClaimableResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Claimable {
+                    addr: vesting.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(claimable.claimable, vec![]);
+    }
+
+    #[test]
+    fn min_distribution_threshold_defers_dust_balances() {
+        let mut app = BasicApp::default();
+        let owner = app.api().addr_make("owner");
+        let fees = app.api().addr_make("fees");
+
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(200u128, "uruji"))
+        })
+        .unwrap();
+
+        let code = Box::new(
+            ContractWrapper::new(execute, instantiate, query)
+                .with_reply(reply)
+                .with_sudo(sudo),
+        );
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    owner: owner.to_string(),
+                    target_denoms: vec!["uruji".to_string()],
+                    target_addresses: vec![(fees.to_string(), 1)],
+                    pull_targets: vec![],
+                    executor: owner.to_string(),
+                },
+                &[],
+                "revenue",
+                None,
+            )
+            .unwrap();
+
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetMinDistribution {
+                asset: AssetInfo::Native("uruji".to_string()),
+                min_balance: Uint128::from(100u128),
+            },
+        )
+        .unwrap();
+
+        // Below the threshold: the run is a no-op, and the dust is left on the contract.
+        app.send_tokens(owner.clone(), contract.clone(), &coins(50u128, "uruji"))
+            .unwrap();
+        app.execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Run {}, &[])
+            .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(fees.clone(), "uruji")
+                .unwrap()
+                .amount,
+            Uint128::zero()
+        );
+
+        // Crossing the threshold lets it distribute as normal.
+        app.send_tokens(owner.clone(), contract.clone(), &coins(50u128, "uruji"))
+            .unwrap();
+        app.execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Run {}, &[])
+            .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(fees, "uruji").unwrap().amount,
+            Uint128::from(100u128)
+        );
+    }
+
+    #[test]
+    fn resolve_weights_fixed_mode_returns_captured_weights() {
+        let deps = mock_dependencies();
+        let weight_source = WeightSource::Fixed(vec![
+            (Addr::unchecked("a"), 1u128),
+            (Addr::unchecked("b"), 3u128),
+        ]);
+
+        let resolved = resolve_weights(deps.as_ref().querier, &weight_source).unwrap();
+        assert_eq!(
+            resolved,
+            vec![(Addr::unchecked("a"), 1u128), (Addr::unchecked("b"), 3u128)]
+        );
+    }
+
+    #[test]
+    fn resolve_weights_group_mode_queries_live_membership() {
+        let mut querier = MockQuerier::default();
+        querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                return SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: "non-smart query".to_string(),
+                });
+            };
+            if contract_addr != "group" {
+                return SystemResult::Err(SystemError::NoSuchContract {
+                    addr: contract_addr.clone(),
+                });
+            }
+            let response = match from_json::<Cw4QueryMsg>(msg).unwrap() {
+                Cw4QueryMsg::TotalWeight {} => {
+                    to_json_binary(&Cw4TotalWeightResponse { weight: 4 }).unwrap()
+                }
+                Cw4QueryMsg::ListMembers { start_after, .. } if start_after.is_none() => {
+                    to_json_binary(&Cw4MemberListResponse {
+                        members: vec![
+                            Cw4Member {
+                                addr: "a".to_string(),
+                                weight: 1,
+                            },
+                            Cw4Member {
+                                addr: "b".to_string(),
+                                weight: 3,
+                            },
+                        ],
+                    })
+                    .unwrap()
+                }
+                Cw4QueryMsg::ListMembers { .. } => {
+                    to_json_binary(&Cw4MemberListResponse { members: vec![] }).unwrap()
+                }
+            };
+            SystemResult::Ok(ContractResult::Ok(response))
+        });
+        let querier = QuerierWrapper::new(&querier);
+
+        let weight_source = WeightSource::Group {
+            contract: Addr::unchecked("group"),
+        };
+        let resolved = resolve_weights(querier, &weight_source).unwrap();
+        assert_eq!(
+            resolved,
+            vec![(Addr::unchecked("a"), 1u128), (Addr::unchecked("b"), 3u128)]
+        );
+    }
+
+    #[test]
+    fn resolve_weights_group_mode_rejects_total_weight_mismatch() {
+        let mut querier = MockQuerier::default();
+        querier.update_wasm(|query| {
+            let WasmQuery::Smart { msg, .. } = query else {
+                return SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: "non-smart query".to_string(),
+                });
+            };
+            let response = match from_json::<Cw4QueryMsg>(msg).unwrap() {
+                // Reports more total weight than ListMembers actually returns.
+                Cw4QueryMsg::TotalWeight {} => {
+                    to_json_binary(&Cw4TotalWeightResponse { weight: 100 }).unwrap()
+                }
+                Cw4QueryMsg::ListMembers { start_after, .. } if start_after.is_none() => {
+                    to_json_binary(&Cw4MemberListResponse {
+                        members: vec![Cw4Member {
+                            addr: "a".to_string(),
+                            weight: 1,
+                        }],
+                    })
+                    .unwrap()
+                }
+                Cw4QueryMsg::ListMembers { .. } => {
+                    to_json_binary(&Cw4MemberListResponse { members: vec![] }).unwrap()
+                }
+            };
+            SystemResult::Ok(ContractResult::Ok(response))
+        });
+        let querier = QuerierWrapper::new(&querier);
+
+        let weight_source = WeightSource::Group {
+            contract: Addr::unchecked("group"),
+        };
+        assert!(resolve_weights(querier, &weight_source).is_err());
+    }
+
+    #[test]
+    fn query_asset_balance_dispatches_native_vs_cw20() {
+        let mut querier = MockQuerier::default();
+        querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                return SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: "non-smart query".to_string(),
+                });
+            };
+            assert_eq!(contract_addr, "token");
+            match from_json::<Cw20QueryMsg>(msg).unwrap() {
+                Cw20QueryMsg::Balance { address } => {
+                    assert_eq!(address, "holder");
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_json_binary(&Cw20BalanceResponse {
+                            balance: Uint128::from(42u128),
+                        })
+                        .unwrap(),
+                    ))
+                }
+            }
+        });
+        querier.update_balance("holder", coins(7u128, "uruji"));
+        let querier = QuerierWrapper::new(&querier);
+        let holder = Addr::unchecked("holder");
+
+        assert_eq!(
+            query_asset_balance(querier, &holder, &AssetInfo::Native("uruji".to_string()))
+                .unwrap(),
+            Uint128::from(7u128)
+        );
+        assert_eq!(
+            query_asset_balance(querier, &holder, &AssetInfo::Cw20(Addr::unchecked("token")))
+                .unwrap(),
+            Uint128::from(42u128)
+        );
+    }
+
+    #[test]
+    fn payout_msg_dispatches_native_vs_cw20() {
+        let to = Addr::unchecked("recipient");
+
+        let native =
+            payout_msg(&AssetInfo::Native("uruji".to_string()), &to, Uint128::from(100u128))
+                .unwrap();
+        assert_eq!(
+            native,
+            BankMsg::Send {
+                to_address: to.to_string(),
+                amount: coins(100u128, "uruji"),
+            }
+            .into()
+        );
+
+        let cw20 = payout_msg(
+            &AssetInfo::Cw20(Addr::unchecked("token")),
+            &to,
+            Uint128::from(100u128),
+        )
+        .unwrap();
+        assert_eq!(
+            cw20,
+            WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: to.to_string(),
+                    amount: Uint128::from(100u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+            .into()
+        );
+    }
+
+    #[test]
+    fn wrap_action_msg_rewraps_cw20_actions_as_send() {
+        let action_contract = Addr::unchecked("fin");
+        let native_msg = WasmMsg::Execute {
+            contract_addr: action_contract.to_string(),
+            msg: Binary::new(vec![1, 2, 3]),
+            funds: coins(500u128, "token"),
+        };
+
+        // Native assets pass the message through untouched.
+        let passthrough = wrap_action_msg(
+            &AssetInfo::Native("token".to_string()),
+            &action_contract,
+            native_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(passthrough, native_msg);
+
+        // CW20 assets get wrapped as a Send to the token contract.
+        let wrapped = wrap_action_msg(
+            &AssetInfo::Cw20(Addr::unchecked("token")),
+            &action_contract,
+            native_msg,
+        )
+        .unwrap();
+        assert_eq!(
+            wrapped,
+            WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                    contract: action_contract.to_string(),
+                    amount: Uint128::from(500u128),
+                    msg: Binary::new(vec![1, 2, 3]),
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn apportion_sums_exactly_with_a_zero_weight_target() {
+        let a = Addr::unchecked("a");
+        let b = Addr::unchecked("b");
+        let c = Addr::unchecked("c");
+        let weights = vec![(a, 1u128), (b, 3u128), (c.clone(), 0u128)];
+
+        let result = apportion(Uint128::from(1000u128), &weights);
+        let sum: u128 = result.iter().map(|(_, amount)| amount.u128()).sum();
+        assert_eq!(sum, 1000);
+        assert_eq!(result.iter().find(|(addr, _)| *addr == c).unwrap().1, Uint128::zero());
+    }
+
+    #[test]
+    fn apportion_breaks_ties_by_ascending_index() {
+        let a = Addr::unchecked("a");
+        let b = Addr::unchecked("b");
+        let c = Addr::unchecked("c");
+        let weights = vec![(a, 1u128), (b, 1u128), (c, 1u128)];
+
+        let result = apportion(Uint128::from(1000u128), &weights);
+        let sum: u128 = result.iter().map(|(_, amount)| amount.u128()).sum();
+        assert_eq!(sum, 1000);
+        // 1000 / 3 floors to 333 each, leaving one unit of leftover; since all remainders
+        // tie, the first target (ascending index) gets it.
+        assert_eq!(result[0].1, Uint128::from(334u128));
+        assert_eq!(result[1].1, Uint128::from(333u128));
+        assert_eq!(result[2].1, Uint128::from(333u128));
+    }
+
+    #[test]
+    fn apportion_gives_leftover_to_largest_remainder_first() {
+        let a = Addr::unchecked("a");
+        let b = Addr::unchecked("b");
+        let c = Addr::unchecked("c");
+        let weights = vec![(a, 1u128), (b, 3u128), (c, 5u128)];
+
+        let result = apportion(Uint128::from(1000u128), &weights);
+        let sum: u128 = result.iter().map(|(_, amount)| amount.u128()).sum();
+        assert_eq!(sum, 1000);
+        assert_eq!(result[0].1, Uint128::from(111u128));
+        assert_eq!(result[1].1, Uint128::from(333u128));
+        assert_eq!(result[2].1, Uint128::from(556u128));
+    }
+
+    #[test]
+    fn apportion_with_zero_total_weight_gives_nothing() {
+        let a = Addr::unchecked("a");
+        let weights = vec![(a, 0u128)];
+
+        let result = apportion(Uint128::from(1000u128), &weights);
+        assert_eq!(result[0].1, Uint128::zero());
+    }
 }
@@ -0,0 +1,27 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, Uint128};
+
+/// Minimal subset of the cw20 execute/query interface this crate needs to read balances of,
+/// and pay out, CW20 assets alongside natives.
+#[cw_serde]
+pub enum Cw20ExecuteMsg {
+    Transfer {
+        recipient: String,
+        amount: Uint128,
+    },
+    Send {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+}
+
+#[cw_serde]
+pub enum Cw20QueryMsg {
+    Balance { address: String },
+}
+
+#[cw_serde]
+pub struct Cw20BalanceResponse {
+    pub balance: Uint128,
+}
@@ -0,0 +1,30 @@
+use cosmwasm_schema::cw_serde;
+
+/// Minimal subset of the cw4 group-contract query interface this crate needs to source live
+/// staking weights for proportional revenue distribution. Deliberately not the full `cw4`
+/// crate's `QueryMsg`/`Member`/`MemberListResponse`/`TotalWeightResponse` types, since this
+/// contract only ever reads membership, never writes it.
+#[cw_serde]
+pub enum Cw4QueryMsg {
+    ListMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    TotalWeight {},
+}
+
+#[cw_serde]
+pub struct Cw4Member {
+    pub addr: String,
+    pub weight: u64,
+}
+
+#[cw_serde]
+pub struct Cw4MemberListResponse {
+    pub members: Vec<Cw4Member>,
+}
+
+#[cw_serde]
+pub struct Cw4TotalWeightResponse {
+    pub weight: u64,
+}
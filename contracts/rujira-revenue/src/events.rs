@@ -1,5 +1,16 @@
-use cosmwasm_std::Event;
+use cosmwasm_std::{Addr, Event, Uint128};
 
 pub fn event_run(denom: String) -> Event {
     Event::new(format!("{}/run", env!("CARGO_PKG_NAME"))).add_attribute("denom", denom)
 }
+
+/// `amounts` pairs an asset key (native denom, or CW20 contract address) with the amount
+/// claimed of it.
+pub fn event_claim(addr: &Addr, amounts: &[(String, Uint128)]) -> Event {
+    let mut event = Event::new(format!("{}/claim", env!("CARGO_PKG_NAME")))
+        .add_attribute("addr", addr.to_string());
+    for (key, amount) in amounts {
+        event = event.add_attribute(key.clone(), amount.to_string());
+    }
+    event
+}
@@ -1,11 +1,14 @@
+use std::collections::BTreeMap;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, from_json, to_json_binary, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo,
-    Reply, Response, StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
+    coin, coins, from_json, to_json_binary, Addr, Api, BankMsg, Binary, Deps, DepsMut, Env,
+    MessageInfo, Reply, Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Timestamp,
+    Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
-use cw_utils::{may_pay, must_pay, nonpayable, NativeBalance};
+use cw_utils::{may_pay, must_pay, nonpayable};
 use rujira_rs::reply::sub_msg_response_to_info;
 use rujira_rs::staking::{
     AccountMsg, ConfigResponse, ExecuteMsg, InstantiateMsg, LiquidMsg, QueryMsg, SudoMsg,
@@ -15,13 +18,18 @@ use rujira_rs::TokenFactory;
 use crate::config::Config;
 use crate::error::ContractError;
 use crate::events::{
-    event_account_bond, event_account_claim, event_account_withdraw, event_liquid_bond,
-    event_liquid_unbond,
+    event_account_bond, event_account_claim, event_account_withdraw, event_denom_rewards,
+    event_external_rewards, event_liquid_bond, event_liquid_unbond, event_unbond_queued,
 };
 use crate::state::{
-    account, distribute, execute_account_bond, execute_account_claim, execute_account_withdraw,
-    execute_liquid_bond, execute_liquid_unbond, increase_pending_swap, init, status,
+    account, account_revenue, add_denom_rewards, add_rewards, beneficiary_or_owner,
+    cancel_pending_swap, distribute, exchange_rate, execute_account_bond, execute_account_claim,
+    execute_account_withdraw, execute_liquid_bond, execute_liquid_unbond, guard_swap_output,
+    health_check, increase_pending_swap, init, liquid_redemption_rate, multi_denom_status,
+    portfolio_value_usd, set_beneficiary, set_swap_batch_config, set_swap_guard, status,
+    status_with_unbonding, twap_rate,
 };
+use crate::unbonding::claim_matured;
 
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -44,7 +52,26 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
+    let stored = cw2::get_contract_version(deps.storage)?;
+    let stored_version: semver::Version = stored.version.parse().map_err(|_| {
+        ContractError::Std(StdError::generic_err("stored contract version is not semver"))
+    })?;
+    let new_version: semver::Version = CONTRACT_VERSION.parse().map_err(|_| {
+        ContractError::Std(StdError::generic_err("CONTRACT_VERSION is not semver"))
+    })?;
+
+    if new_version < stored_version {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "cannot migrate {} down to {}",
+            stored_version, new_version
+        ))));
+    }
+
+    // No state shape has changed across any released version yet - this is where a
+    // version-gated transform would run, e.g. `if stored_version < Version::new(1, 1, 0) { ... }`.
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     Ok(Response::default())
 }
 
@@ -61,11 +88,49 @@ pub fn execute(
         distribute(&env, deps.querier, deps.storage, &config, &bond_amount_sent)?;
     let mut res = match msg {
         ExecuteMsg::Account(account_msg) => {
-            execute_account(deps.storage, info, &config, account_msg)
+            execute_account(deps.storage, deps.api, env.block.time, info, &config, account_msg)
         }
         ExecuteMsg::Liquid(liquid_msg) => {
             execute_liquid(deps.storage, &env, info, &config, liquid_msg)
         }
+        ExecuteMsg::ClaimUnbonded {} => {
+            nonpayable(&info)?;
+            let amount = claim_matured(deps.storage, &info.sender, env.block.time)?;
+            Ok(Response::default().add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: coins(amount.u128(), config.bond_denom.clone()),
+            }))
+        }
+        ExecuteMsg::HealthCheck {
+            address,
+            min_value_usd,
+        } => {
+            health_check(
+                deps.as_ref(),
+                &env,
+                &config,
+                &deps.api.addr_validate(&address)?,
+                min_value_usd,
+            )?;
+            Ok(Response::default())
+        }
+        ExecuteMsg::AddRewards {} => {
+            let amount = must_pay(&info, config.revenue_denom.as_str())?;
+            let (account_allocation, liquid_allocation) = add_rewards(deps.storage, amount)?;
+            Ok(Response::default().add_event(event_external_rewards(
+                info.sender.clone(),
+                amount,
+                account_allocation,
+                liquid_allocation,
+            )))
+        }
+        ExecuteMsg::AddDenomRewards {} => {
+            if info.funds.is_empty() {
+                return Err(ContractError::Invalid("funds".to_string()));
+            }
+            add_denom_rewards(deps.storage, &info.funds)?;
+            Ok(Response::default().add_event(event_denom_rewards(info.sender.clone(), &info.funds)))
+        }
     }?;
     if swap_amount.gt(&Uint128::zero()) {
         let sub_msg = SubMsg::reply_always(
@@ -107,6 +172,25 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::SetSwapGuard { guard } => {
+            set_swap_guard(deps.storage, guard)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetSwapBatch { policy } => {
+            set_swap_batch_config(deps.storage, policy)?;
+            Ok(Response::default())
+        }
+        SudoMsg::CancelPendingSwap { recipient } => {
+            let amount = cancel_pending_swap(deps.storage)?;
+            let mut res = Response::default();
+            if !amount.is_zero() {
+                res = res.add_message(BankMsg::Send {
+                    to_address: deps.api.addr_validate(&recipient)?.to_string(),
+                    amount: coins(amount.u128(), config.revenue_denom.clone()),
+                });
+            }
+            Ok(res)
+        }
     }
 }
 
@@ -115,24 +199,38 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
     // Match on ID for completeness
     match msg.id {
         REPLY_ID => {
+            let swap_amount: Uint128 = from_json(&msg.payload)?;
             match &msg.result {
                 SubMsgResult::Err(_) => {
                     // Swap failed, we need to return all the swap amount
-                    let ongoing_swap = from_json(&msg.payload)?;
-                    increase_pending_swap(deps.storage, ongoing_swap)?;
+                    increase_pending_swap(deps.storage, swap_amount)?;
                 }
                 SubMsgResult::Ok(res) => {
                     // Swap succeeded, we need to check if there were any returned funds
                     let info = sub_msg_response_to_info(res, &deps, &env)?;
                     let config = Config::load(deps.storage)?;
-                    let amount = info
+                    let leftover = info
                         .funds
                         .iter()
                         .find(|c| c.denom == config.revenue_denom)
                         .map(|c| c.amount)
                         .unwrap_or_else(Uint128::zero);
-                    if !amount.is_zero() {
-                        increase_pending_swap(deps.storage, amount)?;
+                    let bond_received = info
+                        .funds
+                        .iter()
+                        .find(|c| c.denom == config.bond_denom)
+                        .map(|c| c.amount)
+                        .unwrap_or_else(Uint128::zero);
+
+                    if guard_swap_output(deps.as_ref(), &config, swap_amount, bond_received)
+                        .is_err()
+                    {
+                        // Realized output fell below the configured slippage guard - treat the
+                        // whole swap as failed and retry the full input on the next distribute,
+                        // rather than crediting a bad fill.
+                        increase_pending_swap(deps.storage, swap_amount)?;
+                    } else if !leftover.is_zero() {
+                        increase_pending_swap(deps.storage, leftover)?;
                     }
                 }
             }
@@ -142,8 +240,33 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
     }
 }
 
+/// Appends a `BankMsg::Send` for `denom_rewards` (the `reward_index` settlement from a
+/// bond/claim/withdraw) to `response`, routed to `owner`'s beneficiary same as the single-denom
+/// `revenue_denom` leg. A no-op when nothing settled.
+fn add_denom_reward_message(
+    storage: &dyn Storage,
+    owner: &Addr,
+    response: Response,
+    denom_rewards: BTreeMap<String, Uint128>,
+) -> Result<Response, ContractError> {
+    if denom_rewards.is_empty() {
+        return Ok(response);
+    }
+    let beneficiary = beneficiary_or_owner(storage, owner)?;
+    let amount = denom_rewards
+        .into_iter()
+        .map(|(denom, amount)| coin(amount.u128(), denom))
+        .collect::<Vec<_>>();
+    Ok(response.add_message(BankMsg::Send {
+        to_address: beneficiary.to_string(),
+        amount,
+    }))
+}
+
 fn execute_account(
     storage: &mut dyn Storage,
+    api: &dyn Api,
+    now: Timestamp,
     info: MessageInfo,
     config: &Config,
     msg: AccountMsg,
@@ -151,51 +274,67 @@ fn execute_account(
     match msg {
         AccountMsg::Bond {} => {
             let amount = must_pay(&info, config.bond_denom.as_str())?;
-            let reward_amount = execute_account_bond(storage, &info.sender, amount)?;
+            let (reward_amount, denom_rewards) = execute_account_bond(storage, &info.sender, amount)?;
             let mut response =
                 Response::default().add_event(event_account_bond(info.sender.clone(), amount));
             if reward_amount.gt(&Uint128::zero()) {
+                let beneficiary = beneficiary_or_owner(storage, &info.sender)?;
                 response = response.add_message(BankMsg::Send {
-                    to_address: info.sender.to_string(),
+                    to_address: beneficiary.to_string(),
                     amount: coins(reward_amount.u128(), config.revenue_denom.clone()),
                 });
             }
-            Ok(response)
+            Ok(add_denom_reward_message(storage, &info.sender, response, denom_rewards)?)
         }
         AccountMsg::Claim {} => {
             nonpayable(&info)?;
-            let reward_amount = execute_account_claim(storage, &info.sender)?;
+            let (reward_amount, denom_rewards) = execute_account_claim(storage, &info.sender)?;
             let mut response = Response::default()
                 .add_event(event_account_claim(info.sender.clone(), reward_amount));
             if reward_amount.gt(&Uint128::zero()) {
+                let beneficiary = beneficiary_or_owner(storage, &info.sender)?;
                 response = response.add_message(BankMsg::Send {
-                    to_address: info.sender.to_string(),
+                    to_address: beneficiary.to_string(),
                     amount: coins(reward_amount.u128(), config.revenue_denom.clone()),
                 });
             }
-            Ok(response)
+            Ok(add_denom_reward_message(storage, &info.sender, response, denom_rewards)?)
         }
         AccountMsg::Withdraw { amount } => {
             nonpayable(&info)?;
-            let (rewards, amount) = execute_account_withdraw(storage, &info.sender, amount)?;
-            let mut send = NativeBalance(vec![
-                Coin::new(rewards, config.revenue_denom.clone()),
-                Coin::new(amount, config.bond_denom.clone()),
-            ]);
-            send.normalize();
-
-            let mut response = Response::default().add_event(event_account_withdraw(
-                info.sender.clone(),
+            let (rewards, denom_rewards, amount, release_at) = execute_account_withdraw(
+                storage,
+                &info.sender,
                 amount,
-                rewards,
-            ));
-            if !send.is_empty() {
+                now,
+                config.unbonding_period,
+            )?;
+            let beneficiary = beneficiary_or_owner(storage, &info.sender)?;
+
+            // The bond principal is never paid out here - it's queued in `unbonding` under the
+            // owner and only leaves the contract once `ExecuteMsg::ClaimUnbonded {}` matures it.
+            // Only the revenue leg, which isn't time-locked, is sent immediately, redirected to
+            // the beneficiary if one is set.
+            let mut response = Response::default()
+                .add_event(event_account_withdraw(info.sender.clone(), amount, rewards))
+                .add_event(event_unbond_queued(
+                    info.sender.clone(),
+                    amount,
+                    release_at.seconds(),
+                ));
+            if !rewards.is_zero() {
                 response = response.add_message(BankMsg::Send {
-                    to_address: info.sender.to_string(),
-                    amount: send.into_vec(),
+                    to_address: beneficiary.to_string(),
+                    amount: coins(rewards.u128(), config.revenue_denom.clone()),
                 });
             }
-            Ok(response)
+            Ok(add_denom_reward_message(storage, &info.sender, response, denom_rewards)?)
+        }
+        AccountMsg::SetBeneficiary { beneficiary } => {
+            nonpayable(&info)?;
+            let beneficiary = beneficiary.map(|b| api.addr_validate(&b)).transpose()?;
+            set_beneficiary(storage, &info.sender, beneficiary)?;
+            Ok(Response::default())
         }
     }
 }
@@ -212,21 +351,28 @@ fn execute_liquid(
     match msg {
         LiquidMsg::Bond {} => {
             let amount = must_pay(&info, config.bond_denom.as_str())?;
-            let shares = execute_liquid_bond(storage, amount)?;
+            let shares = execute_liquid_bond(storage, env.block.time, amount)?;
             Ok(Response::default()
                 .add_event(event_liquid_bond(info.sender.clone(), amount, shares))
                 .add_message(share_denom.mint_msg(shares, info.sender)))
         }
         LiquidMsg::Unbond {} => {
             let shares = must_pay(&info, share_denom.denom().as_str())?;
-            let returned = execute_liquid_unbond(storage, shares)?;
+            let (returned, release_at) = execute_liquid_unbond(
+                storage,
+                &info.sender,
+                env.block.time,
+                shares,
+                config.unbonding_period,
+            )?;
             Ok(Response::default()
                 .add_event(event_liquid_unbond(info.sender.clone(), shares, returned))
-                .add_message(share_denom.burn_msg(shares))
-                .add_message(BankMsg::Send {
-                    to_address: info.sender.to_string(),
-                    amount: coins(returned.u128(), config.bond_denom.clone()),
-                }))
+                .add_event(event_unbond_queued(
+                    info.sender.clone(),
+                    returned,
+                    release_at.seconds(),
+                ))
+                .add_message(share_denom.burn_msg(shares)))
         }
     }
 }
@@ -240,13 +386,52 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Account { addr } => {
             to_json_binary(&account(deps.storage, deps.api.addr_validate(&addr)?)?)
         }
+        QueryMsg::RedemptionRate {} => to_json_binary(
+            &liquid_redemption_rate(deps.storage)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::HealthCheck {
+            address,
+            min_value_usd,
+        } => {
+            let addr = deps.api.addr_validate(&address)?;
+            health_check(deps, &env, &config, &addr, min_value_usd)
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            to_json_binary(
+                &portfolio_value_usd(deps, &env, &config, &addr)
+                    .map_err(|e| StdError::generic_err(e.to_string()))?,
+            )
+        }
+        QueryMsg::ExchangeRate {} => to_json_binary(
+            &exchange_rate(&env, deps.querier, deps.storage, &config)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::TwapRate { window_seconds } => to_json_binary(
+            &twap_rate(deps.storage, env.block.time, window_seconds)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::StatusWithUnbonding {} => {
+            to_json_binary(&status_with_unbonding(env, deps, &config)?)
+        }
+        QueryMsg::MultiDenomStatus {} => to_json_binary(
+            &multi_denom_status(env, deps, &config)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::AccountRevenue { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            let bonded = account(deps.storage, addr.clone())?.bonded;
+            to_json_binary(
+                &account_revenue(deps.storage, addr, bonded)
+                    .map_err(|e| StdError::generic_err(e.to_string()))?,
+            )
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::{coin, Addr, Decimal, Event};
+    use cosmwasm_std::{coin, Decimal, Event};
     use cw_multi_test::{AppResponse, ContractWrapper, Executor};
     use rujira_rs::{
         staking::{AccountResponse, StatusResponse},
@@ -450,6 +635,7 @@ mod tests {
                     uri: None,
                     uri_hash: None,
                 },
+                unbonding_period: 0,
             },
             &[],
             "staking",
@@ -724,11 +910,12 @@ mod tests {
                 .add_attribute("denom", "x/staking-uruji".to_string()),
         );
 
-        // total distribution of 30 across 1000 shares, so withdrawal should be 515 uruji
+        // total distribution of 30 across 1000 shares, so the queued payout should be 515 uruji -
+        // held back until a later `ClaimUnbonded {}`, not sent in this same transaction.
         res.assert_event(
-            &Event::new("transfer")
-                .add_attribute("amount", "515uruji")
-                .add_attribute("recipient", stakers.liquid),
+            &Event::new(format!("{}/unbond.queued", env!("CARGO_PKG_NAME")))
+                .add_attribute("owner", stakers.liquid.clone())
+                .add_attribute("amount", "515"),
         );
 
         /*--- Liquid Unbound 500 ----------------------
@@ -750,6 +937,22 @@ mod tests {
             },
         );
 
+        // stakers.liquid's unbonding_period is 0, so the 515 queued above matured immediately -
+        // claiming it now pays it out.
+        let res = app
+            .execute_contract(
+                stakers.liquid.clone(),
+                contract.clone(),
+                &ExecuteMsg::ClaimUnbonded {},
+                &[],
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("transfer")
+                .add_attribute("amount", "515uruji")
+                .add_attribute("recipient", stakers.liquid.clone()),
+        );
+
         // Test partial withdrawal and that it claims rewards
         // revenue_amount_remaining        split 75:25 account:liquid.
         // revenue_amount_remaining * 0.75 split 2:1 between staker 1 and 2 => revenue_amount_remaining * 75/100 * 2/3
@@ -772,10 +975,32 @@ mod tests {
             .unwrap();
 
         let expected_auto_claim = [50u128, 46u128, 44u128][fees.index];
-        let expected_amount_attribute_value_2 = format!("50uruji,{}uusdc", expected_auto_claim);
+        let expected_amount_attribute_value_2 = format!("{}uusdc", expected_auto_claim);
+        // The revenue leg isn't time-locked, so it's still sent immediately.
+        res.assert_event(
+            &Event::new("transfer")
+                .add_attribute("amount", expected_amount_attribute_value_2) // WITHOUT_FEE: 50uusdc, WITH_FEE: 46uusdc
+                .add_attribute("recipient", stakers.account_1.clone()),
+        );
+        // The 50 uruji bond principal is queued instead of paid out in the same transaction.
+        res.assert_event(
+            &Event::new(format!("{}/unbond.queued", env!("CARGO_PKG_NAME")))
+                .add_attribute("owner", stakers.account_1.clone())
+                .add_attribute("amount", "50"),
+        );
+
+        // unbonding_period is 0, so it's already matured - claim it to get the uruji back.
+        let res = app
+            .execute_contract(
+                stakers.account_1.clone(),
+                contract.clone(),
+                &ExecuteMsg::ClaimUnbonded {},
+                &[],
+            )
+            .unwrap();
         res.assert_event(
             &Event::new("transfer")
-                .add_attribute("amount", expected_amount_attribute_value_2) // WITHOUT_FEE: 50uruji,50uusdc, WITH_FEE: 50uruji,46uusdc
+                .add_attribute("amount", "50uruji")
                 .add_attribute("recipient", stakers.account_1.clone()),
         );
 
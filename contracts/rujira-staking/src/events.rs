@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, Event, Uint128};
+use cosmwasm_std::{Addr, Coin, Event, Uint128};
 
 pub fn event_account_bond(owner: Addr, amount: Uint128) -> Event {
     Event::new(format!("{}/account.bond", env!("CARGO_PKG_NAME")))
@@ -32,3 +32,34 @@ pub fn event_liquid_unbond(owner: Addr, shares: Uint128, returned: Uint128) -> E
         .add_attribute("shares", shares)
         .add_attribute("returned", returned)
 }
+
+pub fn event_unbond_queued(owner: Addr, amount: Uint128, release_at: u64) -> Event {
+    Event::new(format!("{}/unbond.queued", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("amount", amount)
+        .add_attribute("release_at", release_at.to_string())
+}
+
+pub fn event_denom_rewards(sender: Addr, funds: &[Coin]) -> Event {
+    let amount = funds
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    Event::new(format!("{}/denom_rewards", env!("CARGO_PKG_NAME")))
+        .add_attribute("sender", sender)
+        .add_attribute("amount", amount)
+}
+
+pub fn event_external_rewards(
+    sender: Addr,
+    amount: Uint128,
+    account_allocation: Uint128,
+    liquid_allocation: Uint128,
+) -> Event {
+    Event::new(format!("{}/external_rewards", env!("CARGO_PKG_NAME")))
+        .add_attribute("sender", sender)
+        .add_attribute("amount", amount)
+        .add_attribute("account_allocation", account_allocation)
+        .add_attribute("liquid_allocation", liquid_allocation)
+}
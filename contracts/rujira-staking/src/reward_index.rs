@@ -0,0 +1,219 @@
+//! Multi-denom reward-per-share accounting, mirroring the asset-reward-rate model used by the
+//! Alliance hub's `ASSET_REWARD_RATE`/`USER_ASSET_REWARD_RATE`: `reward_index[denom]` is a
+//! global accumulator, scaled by [`SCALE`], that rises by `amount * SCALE / total_bonded`
+//! whenever that denom's revenue is received. Each account keeps a per-denom `user_index`
+//! snapshot; claimable is `bonded * (reward_index[denom] - user_index[addr][denom]) / SCALE`.
+//! Alongside the index, `ASSIGNED_REVENUE` tracks the running total credited to stakers per
+//! denom that hasn't been claimed out yet, for a `StatusResponse`-style per-denom breakdown.
+//!
+//! `state.rs`'s `execute_account_bond`/`execute_account_claim`/`execute_account_withdraw` all call
+//! [`settle`] against an account's bonded amount before it changes, alongside the existing
+//! single-denom `ACCOUNTS`/`POOL_ACCOUNTS` claim, so a denom credited via [`receive`] - through a
+//! top-level `ExecuteMsg::AddDenomRewards {}` - is paid out pro-rata same as `revenue_denom` is.
+//! [`pending`] and [`assigned_revenue`]/[`known_denoms`] back `QueryMsg::AccountRevenue { addr }`
+//! and `QueryMsg::MultiDenomStatus {}` respectively. The liquid side never settles against this
+//! index - only account stakers do.
+
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, StdError, StdResult, Storage, Uint128, Uint256};
+use cw_storage_plus::{Item, Map};
+
+use crate::error::ContractError;
+
+/// Fixed-point scale applied to `reward_index` so a single small revenue distribution against a
+/// large bonded supply doesn't round each account's per-share credit to zero.
+const SCALE: u128 = 1_000_000_000_000_000_000;
+
+static REWARD_INDEX: Map<&str, Uint256> = Map::new("reward_index");
+static USER_INDEX: Map<(&Addr, &str), Uint256> = Map::new("reward_user_index");
+static KNOWN_DENOMS: Item<Vec<String>> = Item::new("reward_denoms");
+/// Running total per denom credited to stakers via [`receive`] that hasn't been paid out by a
+/// [`settle`] yet - the per-denom analogue of `AccountPool::pending` in the single-denom model.
+static ASSIGNED_REVENUE: Map<&str, Uint128> = Map::new("reward_assigned");
+
+/// Every denom ever credited via [`receive`], for a multi-denom `StatusResponse` to iterate.
+pub fn known_denoms(storage: &dyn Storage) -> StdResult<Vec<String>> {
+    Ok(KNOWN_DENOMS.may_load(storage)?.unwrap_or_default())
+}
+
+/// The running total of `denom` credited to stakers and not yet claimed.
+pub fn assigned_revenue(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    Ok(ASSIGNED_REVENUE.may_load(storage, denom)?.unwrap_or_default())
+}
+
+/// Credits `amount` of `denom` to the global index, scaled against `total_bonded`. A no-op when
+/// nothing is bonded yet or `amount` is zero, since there would be no one to credit.
+pub fn receive(
+    storage: &mut dyn Storage,
+    denom: &str,
+    amount: Uint128,
+    total_bonded: Uint128,
+) -> StdResult<()> {
+    if total_bonded.is_zero() || amount.is_zero() {
+        return Ok(());
+    }
+
+    let mut known = KNOWN_DENOMS.may_load(storage)?.unwrap_or_default();
+    if !known.iter().any(|d| d == denom) {
+        known.push(denom.to_string());
+        KNOWN_DENOMS.save(storage, &known)?;
+    }
+
+    let delta = Uint256::from(amount)
+        .checked_mul(Uint256::from(SCALE))?
+        .checked_div(Uint256::from(total_bonded))?;
+    let index = REWARD_INDEX.may_load(storage, denom)?.unwrap_or_default();
+    REWARD_INDEX.save(storage, denom, &(index + delta))?;
+
+    let assigned = ASSIGNED_REVENUE.may_load(storage, denom)?.unwrap_or_default();
+    ASSIGNED_REVENUE.save(storage, denom, &(assigned + amount))
+}
+
+/// `bonded`'s claimable share of `denom`'s index since `addr`'s last snapshot, without
+/// mutating any state - for previewing balances in a status/account query.
+pub fn pending(
+    storage: &dyn Storage,
+    addr: &Addr,
+    bonded: Uint128,
+) -> Result<BTreeMap<String, Uint128>, ContractError> {
+    let denoms = KNOWN_DENOMS.may_load(storage)?.unwrap_or_default();
+    let mut pending = BTreeMap::new();
+    for denom in denoms {
+        let index = REWARD_INDEX.may_load(storage, &denom)?.unwrap_or_default();
+        let user_index = USER_INDEX
+            .may_load(storage, (addr, &denom))?
+            .unwrap_or_default();
+        if let Some(amount) = claimable(bonded, index, user_index)? {
+            pending.insert(denom, amount);
+        }
+    }
+    Ok(pending)
+}
+
+/// Settles `addr`'s pending reward for every denom ever credited via [`receive`], snapshotting
+/// `addr`'s `user_index` to the current `reward_index` for each regardless of whether anything
+/// was claimable, and returns every denom with a non-zero amount. Must be called (and its
+/// result applied) before `bonded` changes, so the settlement reflects the balance the reward
+/// actually accrued against.
+pub fn settle(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    bonded: Uint128,
+) -> Result<BTreeMap<String, Uint128>, ContractError> {
+    let denoms = KNOWN_DENOMS.may_load(storage)?.unwrap_or_default();
+    let mut claimed = BTreeMap::new();
+    for denom in denoms {
+        let index = REWARD_INDEX.may_load(storage, &denom)?.unwrap_or_default();
+        let user_index = USER_INDEX
+            .may_load(storage, (addr, &denom))?
+            .unwrap_or_default();
+        USER_INDEX.save(storage, (addr, &denom), &index)?;
+
+        if let Some(amount) = claimable(bonded, index, user_index)? {
+            let assigned = ASSIGNED_REVENUE.may_load(storage, &denom)?.unwrap_or_default();
+            ASSIGNED_REVENUE.save(storage, &denom, &assigned.checked_sub(amount)?)?;
+            claimed.insert(denom, amount);
+        }
+    }
+    Ok(claimed)
+}
+
+fn claimable(
+    bonded: Uint128,
+    index: Uint256,
+    user_index: Uint256,
+) -> Result<Option<Uint128>, ContractError> {
+    if bonded.is_zero() || index <= user_index {
+        return Ok(None);
+    }
+    let amount = Uint256::from(bonded)
+        .checked_mul(index - user_index)?
+        .checked_div(Uint256::from(SCALE))?;
+    let amount = Uint128::try_from(amount)
+        .map_err(|_| ContractError::Std(StdError::generic_err("reward index overflow")))?;
+    Ok(if amount.is_zero() { None } else { Some(amount) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn accrues_independently_per_denom() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+
+        receive(&mut storage, "uusdc", Uint128::from(300u128), Uint128::from(1000u128)).unwrap();
+        receive(&mut storage, "uruji", Uint128::from(100u128), Uint128::from(1000u128)).unwrap();
+
+        let claimed = settle(&mut storage, &alice, Uint128::from(500u128)).unwrap();
+        assert_eq!(claimed.get("uusdc"), Some(&Uint128::from(150u128)));
+        assert_eq!(claimed.get("uruji"), Some(&Uint128::from(50u128)));
+
+        // A second settle with no new revenue has nothing left to claim.
+        let claimed = settle(&mut storage, &alice, Uint128::from(500u128)).unwrap();
+        assert!(claimed.is_empty());
+    }
+
+    #[test]
+    fn settling_snapshots_so_later_joiners_dont_double_dip() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+
+        receive(&mut storage, "uusdc", Uint128::from(1000u128), Uint128::from(1000u128)).unwrap();
+        // Bob bonds after the first distribution - settling now just snapshots, no back pay.
+        let claimed = settle(&mut storage, &bob, Uint128::from(0u128)).unwrap();
+        assert!(claimed.is_empty());
+
+        let claimed = settle(&mut storage, &alice, Uint128::from(1000u128)).unwrap();
+        assert_eq!(claimed.get("uusdc"), Some(&Uint128::from(1000u128)));
+
+        receive(&mut storage, "uusdc", Uint128::from(500u128), Uint128::from(1500u128)).unwrap();
+        let claimed = settle(&mut storage, &bob, Uint128::from(500u128)).unwrap();
+        assert_eq!(claimed.get("uusdc"), Some(&Uint128::from(166u128)));
+    }
+
+    #[test]
+    fn pending_previews_without_mutating_state() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        receive(&mut storage, "uusdc", Uint128::from(200u128), Uint128::from(1000u128)).unwrap();
+
+        let preview = pending(&storage, &alice, Uint128::from(1000u128)).unwrap();
+        assert_eq!(preview.get("uusdc"), Some(&Uint128::from(200u128)));
+
+        // Previewing twice gives the same answer - it must not have snapshotted anything.
+        let preview = pending(&storage, &alice, Uint128::from(1000u128)).unwrap();
+        assert_eq!(preview.get("uusdc"), Some(&Uint128::from(200u128)));
+
+        let claimed = settle(&mut storage, &alice, Uint128::from(1000u128)).unwrap();
+        assert_eq!(claimed, preview);
+    }
+
+    #[test]
+    fn assigned_revenue_falls_as_it_is_claimed_out() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+
+        receive(&mut storage, "uusdc", Uint128::from(300u128), Uint128::from(1000u128)).unwrap();
+        assert_eq!(
+            assigned_revenue(&storage, "uusdc").unwrap(),
+            Uint128::from(300u128)
+        );
+        assert_eq!(known_denoms(&storage).unwrap(), vec!["uusdc".to_string()]);
+
+        // Alice only owns half the bonded supply, so she only claims out half the assignment.
+        settle(&mut storage, &alice, Uint128::from(500u128)).unwrap();
+        assert_eq!(
+            assigned_revenue(&storage, "uusdc").unwrap(),
+            Uint128::from(150u128)
+        );
+
+        settle(&mut storage, &bob, Uint128::from(500u128)).unwrap();
+        assert_eq!(assigned_revenue(&storage, "uusdc").unwrap(), Uint128::zero());
+    }
+}
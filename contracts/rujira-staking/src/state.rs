@@ -1,340 +1,1517 @@
-use cosmwasm_std::{
-    Addr, Decimal, Deps, Env, QuerierWrapper, StdError, StdResult, Storage, Uint128,
-};
-use cw_storage_plus::{Item, Map};
-use rujira_rs::{
-    staking::{AccountResponse, StatusResponse},
-    AccountPool, AccountPoolAccount, SharePool,
-};
-use std::{cmp::min, ops::Add, ops::Sub};
-
-use crate::{config::Config, ContractError};
-
-static POOL_LIQUID: Item<SharePool> = Item::new("l");
-static ACCOUNTS: Map<Addr, AccountPoolAccount> = Map::new("a");
-static POOL_ACCOUNTS: Item<AccountPool> = Item::new("p");
-static PENDING_SWAP: Item<Uint128> = Item::new("s");
-
-pub fn init(storage: &mut dyn Storage) -> StdResult<()> {
-    POOL_LIQUID.save(storage, &Default::default())?;
-    POOL_ACCOUNTS.save(storage, &Default::default())?;
-    PENDING_SWAP.save(storage, &Default::default())?;
-    Ok(())
-}
-
-pub fn execute_account_bond(
-    storage: &mut dyn Storage,
-    owner: &Addr,
-    amount: Uint128,
-) -> Result<Uint128, ContractError> {
-    let mut pool = POOL_ACCOUNTS.load(storage)?;
-
-    match ACCOUNTS.load(storage, owner.clone()) {
-        Ok(mut account) => {
-            let rewards = pool.claim(&mut account);
-            let account = pool.increase_account(&account, amount);
-            ACCOUNTS.save(storage, owner.clone(), &account)?;
-            POOL_ACCOUNTS.save(storage, &pool)?;
-
-            Ok(rewards)
-        }
-        Err(StdError::NotFound { .. }) => {
-            let account = pool.join(amount);
-            ACCOUNTS.save(storage, owner.clone(), &account)?;
-            POOL_ACCOUNTS.save(storage, &pool)?;
-
-            Ok(Uint128::default())
-        }
-        Err(err) => Err(ContractError::Std(err)),
-    }
-}
-
-pub fn execute_account_claim(storage: &mut dyn Storage, owner: &Addr) -> StdResult<Uint128> {
-    let mut pool = POOL_ACCOUNTS.load(storage)?;
-    let mut account = ACCOUNTS.load(storage, owner.clone())?;
-    let rewards = pool.claim(&mut account);
-    ACCOUNTS.save(storage, owner.clone(), &account)?;
-    POOL_ACCOUNTS.save(storage, &pool)?;
-    Ok(rewards)
-}
-
-pub fn execute_account_withdraw(
-    storage: &mut dyn Storage,
-    owner: &Addr,
-    amount: Option<Uint128>,
-) -> Result<(Uint128, Uint128), ContractError> {
-    let mut pool = POOL_ACCOUNTS.load(storage)?;
-    let mut account = ACCOUNTS.load(storage, owner.clone())?;
-    let rewards = pool.claim(&mut account);
-    let amount = amount.unwrap_or(account.amount);
-    let account = pool.decrease_account(&account, amount)?;
-    ACCOUNTS.save(storage, owner.clone(), &account)?;
-    POOL_ACCOUNTS.save(storage, &pool)?;
-    Ok((rewards, amount))
-}
-
-pub fn execute_liquid_bond(
-    storage: &mut dyn Storage,
-    amount: Uint128,
-) -> Result<Uint128, ContractError> {
-    // Add Bond token to the Compounding pool, mint and return the Compound Share Token
-    let mut pool = POOL_LIQUID.load(storage)?;
-    let shares = pool.join(amount)?;
-    POOL_LIQUID.save(storage, &pool)?;
-    Ok(shares)
-}
-
-pub fn execute_liquid_unbond(
-    storage: &mut dyn Storage,
-    shares: Uint128,
-) -> Result<Uint128, ContractError> {
-    let mut pool = POOL_LIQUID.load(storage)?;
-    let returned = pool.leave(shares)?;
-    POOL_LIQUID.save(storage, &pool)?;
-    Ok(returned)
-}
-
-/// Calculates the amount to be distributed between (account, liquid) pools;
-/// Revenue balance is queried and surplus (ie not allocated to Account stakers) is split pro-rata between ACCOUNT stakers, and LIQUID pool size.
-/// Revenue allocated to LIQUID is transformed to a Wasm Execute msg to swap to the bond token
-/// The surplus of bond_balance - LIQUID.size() - ACCOUNT.total is the return value of the previous swap and can be allocated to the total liquid pool
-pub fn distribute(
-    env: &Env,
-    querier: QuerierWrapper,
-    storage: &mut dyn Storage,
-    config: &Config,
-    bond_amount_sent: &Uint128,
-) -> Result<(Uint128, Uint128), ContractError> {
-    let mut account = POOL_ACCOUNTS.load(storage)?;
-    let mut liquid = POOL_LIQUID.load(storage)?;
-    let swap_pending = PENDING_SWAP.load(storage)?;
-
-    let bond_balance = querier
-        .query_balance(env.contract.address.clone(), config.bond_denom.clone())?
-        .amount;
-
-    let revenue_balance = querier
-        .query_balance(env.contract.address.clone(), config.revenue_denom.clone())?
-        .amount;
-
-    let revenue_surplus_with_fees = revenue_balance
-        .checked_sub(account.pending)?
-        .checked_sub(swap_pending)?;
-
-    let fee_amount = match &config.fee {
-        None => Uint128::zero(),
-        Some(fee) => (Decimal::from_atomics(revenue_surplus_with_fees, 0).unwrap()
-            * fee.percentage)
-            .to_uint_ceil(),
-    };
-    let revenue_surplus = revenue_surplus_with_fees - fee_amount;
-
-    let account_allocation = if account.total.is_zero() {
-        Uint128::zero()
-    } else {
-        Decimal::from_ratio(
-            account.total * revenue_surplus,
-            account.total.add(liquid.size()),
-        )
-        .to_uint_floor()
-    };
-
-    let liquid_allocation = if liquid.size().is_zero() {
-        Uint128::zero()
-    } else {
-        Decimal::from_ratio(
-            liquid.size() * revenue_surplus,
-            account.total.add(liquid.size()),
-        )
-        .to_uint_floor()
-    };
-
-    account.distribute(account_allocation);
-    POOL_ACCOUNTS.save(storage, &account)?;
-
-    let bond_surplus = bond_balance
-        // Discount any bond tokens sent in the tx, so they're not incorrectly allocated to the Share pool size as swap returned funds
-        .checked_sub(*bond_amount_sent)?
-        .checked_sub(liquid.size())?
-        .checked_sub(account.total)?;
-
-    liquid.deposit(bond_surplus)?;
-    POOL_LIQUID.save(storage, &liquid)?;
-
-    // Take pending swaps off the queue, add back any remaining
-    let swap_total = swap_pending.add(liquid_allocation);
-    let swap_amount = min(config.revenue_converter.2, swap_total);
-    let swap_remainder = swap_total.sub(swap_amount);
-    PENDING_SWAP.save(storage, &swap_remainder)?;
-
-    Ok((swap_amount, fee_amount))
-}
-
-pub fn increase_pending_swap(storage: &mut dyn Storage, amount: Uint128) -> StdResult<()> {
-    let swap_pending = PENDING_SWAP.load(storage)?;
-    PENDING_SWAP.save(storage, &(swap_pending + amount))
-}
-
-pub fn status(env: Env, deps: Deps, config: &Config) -> StdResult<StatusResponse> {
-    let liquid = POOL_LIQUID.load(deps.storage)?;
-    let account = POOL_ACCOUNTS.load(deps.storage)?;
-    let swap_pending = PENDING_SWAP.load(deps.storage)?;
-
-    let revenue_balance = deps
-        .querier
-        .query_balance(env.contract.address.clone(), config.revenue_denom.clone())?
-        .amount;
-
-    let revenue_surplus = revenue_balance
-        .checked_sub(account.pending)?
-        .checked_sub(swap_pending)?;
-
-    Ok(StatusResponse {
-        account_bond: account.total,
-        assigned_revenue: account.pending,
-        liquid_bond_shares: liquid.shares(),
-        liquid_bond_size: liquid.size(),
-        undistributed_revenue: revenue_surplus,
-    })
-}
-
-pub fn account(storage: &dyn Storage, addr: Addr) -> StdResult<AccountResponse> {
-    let accounts = POOL_ACCOUNTS.load(storage)?;
-    let account = ACCOUNTS.load(storage, addr.clone())?;
-
-    Ok(AccountResponse {
-        addr: addr.to_string(),
-        bonded: account.amount,
-        pending_revenue: accounts.pending_revenue(&account),
-    })
-}
-
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::{
-        coin, coins,
-        testing::{mock_dependencies_with_balances, mock_env},
-        Binary,
-    };
-    use cw_multi_test::BasicApp;
-
-    use super::*;
-
-    #[test]
-    fn test_distribution() {
-        let app = BasicApp::default();
-        let env = mock_env();
-
-        let mut deps = mock_dependencies_with_balances(&[
-            (
-                app.api().addr_make("app").as_str(),
-                &coins(1_000_000u128, "uusdc"),
-            ),
-            (
-                env.contract.address.as_str(),
-                &[
-                    coin(1_000u128, "uusdc"),
-                    // Two operations below bond total of 3000 ruji
-                    // More complex testing executed in contract.rs with cw-multi-test
-                    coin(3_000u128, "uruji"),
-                ],
-            ),
-        ]);
-
-        let config = Config {
-            bond_denom: "uruji".to_string(),
-            revenue_denom: "uusdc".to_string(),
-            revenue_converter: (
-                app.api().addr_make("revenue"),
-                Binary::new(vec![0]),
-                Uint128::from(100u128),
-            ),
-            fee: None,
-        };
-
-        init(deps.as_mut().storage).unwrap();
-
-        assert_eq!(
-            POOL_LIQUID.load(deps.as_mut().storage).unwrap(),
-            SharePool::default()
-        );
-
-        assert_eq!(
-            POOL_ACCOUNTS.load(deps.as_mut().storage).unwrap(),
-            AccountPool::default()
-        );
-        let mutdeps = deps.as_mut();
-
-        execute_account_bond(
-            mutdeps.storage,
-            &app.api().addr_make("account"),
-            Uint128::from(750u128),
-        )
-        .unwrap();
-
-        execute_account_bond(
-            mutdeps.storage,
-            &app.api().addr_make("account2"),
-            Uint128::from(250u128),
-        )
-        .unwrap();
-
-        execute_liquid_bond(mutdeps.storage, Uint128::from(2_000u128)).unwrap();
-
-        assert_eq!(
-            POOL_LIQUID.load(mutdeps.storage).unwrap().shares(),
-            Uint128::from(2_000u128)
-        );
-
-        assert_eq!(
-            POOL_LIQUID.load(mutdeps.storage).unwrap().size(),
-            Uint128::from(2_000u128)
-        );
-
-        assert_eq!(
-            POOL_ACCOUNTS.load(mutdeps.storage).unwrap().total,
-            Uint128::from(1_000u128)
-        );
-
-        let (swap_amount, _fee_amount) = distribute(
-            &env,
-            mutdeps.querier,
-            mutdeps.storage,
-            &config,
-            &Uint128::zero(),
-        )
-        .unwrap();
-        // Balance of 1000 USDC split across 3000 RUJI - 2000 liquid and 1000 account. so 666 to be swapped, 333 to be allocated
-
-        assert_eq!(swap_amount, Uint128::from(100u128));
-
-        assert_eq!(
-            PENDING_SWAP.load(mutdeps.storage).unwrap(),
-            Uint128::from(566u128)
-        );
-
-        assert_eq!(
-            POOL_LIQUID.load(mutdeps.storage).unwrap().shares(),
-            Uint128::from(2_000u128)
-        );
-
-        assert_eq!(
-            POOL_LIQUID.load(mutdeps.storage).unwrap().size(),
-            Uint128::from(2_000u128)
-        );
-
-        assert_eq!(
-            POOL_ACCOUNTS.load(mutdeps.storage).unwrap().pending,
-            Uint128::from(333u128)
-        );
-
-        assert_eq!(
-            account(mutdeps.storage, app.api().addr_make("account")).unwrap(),
-            AccountResponse {
-                addr: app.api().addr_make("account").to_string(),
-                bonded: Uint128::from(750u128),
-                pending_revenue: Uint128::from(249u128)
-            }
-        );
-    }
-}
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, Addr, Coin, Decimal, Deps, Env, QuerierWrapper, StdError, StdResult, Storage, Timestamp,
+    Uint128, Uint256,
+};
+use cw_storage_plus::{Item, Map};
+use rujira_rs::{
+    staking::{AccountResponse, StatusResponse},
+    AccountPool, AccountPoolAccount, OracleValue, SharePool, TokenFactory,
+};
+use std::{cmp::min, ops::Add, ops::Sub};
+
+use crate::{config::Config, reward_index, twap, unbonding, ContractError};
+
+/// Optional reference-price guard on the `revenue_converter` swap, installed via
+/// `SudoMsg::SetSwapGuard`. [`guard_swap_output`] below is wired into `contract.rs`'s `reply`
+/// handling of the converter swap, so a guard that's present here is enforced. Absent by default, in which case the
+/// swap accepts whatever the swap contract returns.
+static SWAP_GUARD: Item<SwapGuard> = Item::new("swap_guard");
+static POOL_LIQUID: Item<SharePool> = Item::new("l");
+static ACCOUNTS: Map<Addr, AccountPoolAccount> = Map::new("a");
+static POOL_ACCOUNTS: Item<AccountPool> = Item::new("p");
+static PENDING_SWAP: Item<Uint128> = Item::new("s");
+/// High-water mark for `liquid_redemption_rate`, so a read mid an exploit that briefly drains
+/// the bond balance can't be mistaken for a genuine rate drop.
+static LIQUID_REDEMPTION_RATE_HIGH: Item<Decimal> = Item::new("lrr");
+/// Per-owner override for where an account's revenue coins are paid out, installed via
+/// `AccountMsg::SetBeneficiary`. Absent by default, in which case revenue pays the bonder
+/// directly.
+static BENEFICIARY: Map<&Addr, Addr> = Map::new("beneficiary");
+/// Optional batching policy for the `revenue_converter` swap, installed via
+/// `SudoMsg::SetSwapBatch`. [`swap_eligible`] and [`track_queued_since`] below are wired into
+/// `distribute`'s swap decision, so a policy that's present here takes effect. Absent by default,
+/// in which case every `distribute` call swaps whatever's pending (up to `revenue_converter.2`).
+static SWAP_BATCH: Item<SwapBatchConfig> = Item::new("swap_batch");
+/// When the oldest still-unswapped revenue in `PENDING_SWAP` started accumulating. Absent
+/// whenever `PENDING_SWAP` is empty; set the moment it goes from empty to non-empty and cleared
+/// the moment it's fully drained again.
+static QUEUED_SINCE: Item<Timestamp> = Item::new("swap_queued_since");
+
+pub fn init(storage: &mut dyn Storage) -> StdResult<()> {
+    POOL_LIQUID.save(storage, &Default::default())?;
+    POOL_ACCOUNTS.save(storage, &Default::default())?;
+    PENDING_SWAP.save(storage, &Default::default())?;
+    LIQUID_REDEMPTION_RATE_HIGH.save(storage, &Decimal::zero())?;
+    Ok(())
+}
+
+/// The liquid bond share token's exact redemption rate against the bond token: `size() /
+/// shares()`. Used both as a read-only oracle price source and, via `guard_liquid_redemption_rate`, to assert
+/// the rate never falls except through `execute_liquid_unbond` - the one path a falling rate is
+/// expected from, since every other mutation only ever adds bond tokens or mints proportionally
+/// many shares.
+pub fn liquid_redemption_rate(storage: &dyn Storage) -> Result<Decimal, ContractError> {
+    let liquid = POOL_LIQUID.load(storage)?;
+    if liquid.shares().is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "no liquid bond shares issued yet",
+        )));
+    }
+    Ok(Decimal::from_ratio(liquid.size(), liquid.shares()))
+}
+
+/// `liquid_redemption_rate`, but `1.0` rather than an error before any shares are issued - the
+/// convention a `RedemptionRate`/`TwapRate` query wants, as opposed to `liquid_redemption_rate`'s
+/// guard use where "no shares yet" means "nothing to compare against" rather than "rate is one".
+fn redemption_rate_or_one(storage: &dyn Storage) -> Result<Decimal, ContractError> {
+    match liquid_redemption_rate(storage) {
+        Ok(rate) => Ok(rate),
+        Err(_) => Ok(Decimal::one()),
+    }
+}
+
+/// The time-weighted average `liquid_redemption_rate` over the trailing `window_seconds`, backed
+/// by [`twap::accrue`]'s cumulative series. Exposed via `QueryMsg::TwapRate { window_seconds }`.
+pub fn twap_rate(
+    storage: &dyn Storage,
+    now: Timestamp,
+    window_seconds: u64,
+) -> Result<Decimal, ContractError> {
+    twap::twap_rate(storage, now, window_seconds, redemption_rate_or_one(storage)?)
+}
+
+/// Re-reads `liquid_redemption_rate` and persists it as the new high-water mark, rejecting a
+/// drop unless `allow_decrease` (set only when called from `execute_liquid_unbond`).
+fn guard_liquid_redemption_rate(
+    storage: &mut dyn Storage,
+    allow_decrease: bool,
+) -> Result<(), ContractError> {
+    let rate = match liquid_redemption_rate(storage) {
+        Ok(rate) => rate,
+        // Nothing bonded yet - there's no rate to guard
+        Err(_) => return Ok(()),
+    };
+    let high_water = LIQUID_REDEMPTION_RATE_HIGH.load(storage)?;
+
+    if rate < high_water && !allow_decrease {
+        return Err(ContractError::Std(StdError::generic_err(
+            "liquid bond redemption rate decreased unexpectedly",
+        )));
+    }
+
+    if rate > high_water {
+        LIQUID_REDEMPTION_RATE_HIGH.save(storage, &rate)?;
+    }
+
+    Ok(())
+}
+
+/// Besides the usual single-denom `revenue_denom` claim, also settles `owner`'s multi-denom
+/// [`reward_index`] against their bonded amount *before* it changes, so a denom credited via
+/// `ExecuteMsg::AddDenomRewards {}` is paid out pro-rata same as the `AccountPool` does.
+pub fn execute_account_bond(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    amount: Uint128,
+) -> Result<(Uint128, BTreeMap<String, Uint128>), ContractError> {
+    let mut pool = POOL_ACCOUNTS.load(storage)?;
+
+    match ACCOUNTS.load(storage, owner.clone()) {
+        Ok(mut account) => {
+            let rewards = pool.claim(&mut account);
+            let denom_rewards = reward_index::settle(storage, owner, account.amount)?;
+            let account = pool.increase_account(&account, amount);
+            ACCOUNTS.save(storage, owner.clone(), &account)?;
+            POOL_ACCOUNTS.save(storage, &pool)?;
+
+            Ok((rewards, denom_rewards))
+        }
+        Err(StdError::NotFound { .. }) => {
+            let account = pool.join(amount);
+            ACCOUNTS.save(storage, owner.clone(), &account)?;
+            POOL_ACCOUNTS.save(storage, &pool)?;
+            // Snapshots `owner` against the current index at a zero bonded amount, so their
+            // first bond doesn't retroactively claim a share of revenue credited before they
+            // joined.
+            reward_index::settle(storage, owner, Uint128::zero())?;
+
+            Ok((Uint128::default(), BTreeMap::new()))
+        }
+        Err(err) => Err(ContractError::Std(err)),
+    }
+}
+
+pub fn execute_account_claim(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+) -> Result<(Uint128, BTreeMap<String, Uint128>), ContractError> {
+    let mut pool = POOL_ACCOUNTS.load(storage)?;
+    let mut account = ACCOUNTS.load(storage, owner.clone())?;
+    let rewards = pool.claim(&mut account);
+    let denom_rewards = reward_index::settle(storage, owner, account.amount)?;
+    ACCOUNTS.save(storage, owner.clone(), &account)?;
+    POOL_ACCOUNTS.save(storage, &pool)?;
+    Ok((rewards, denom_rewards))
+}
+
+/// `rewards` (in `revenue_denom`) and the multi-denom [`reward_index`] settlement are both paid
+/// out immediately, same as `Claim`. The bond principal isn't: it's queued in [`unbonding`] under
+/// `owner`, same as `execute_liquid_unbond`, released only once `ExecuteMsg::ClaimUnbonded {}` is
+/// called after `unbonding_period` has elapsed.
+pub fn execute_account_withdraw(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    amount: Option<Uint128>,
+    now: Timestamp,
+    unbonding_period: u64,
+) -> Result<(Uint128, BTreeMap<String, Uint128>, Uint128, Timestamp), ContractError> {
+    let mut pool = POOL_ACCOUNTS.load(storage)?;
+    let mut account = ACCOUNTS.load(storage, owner.clone())?;
+    let rewards = pool.claim(&mut account);
+    let denom_rewards = reward_index::settle(storage, owner, account.amount)?;
+    let amount = amount.unwrap_or(account.amount);
+    let account = pool.decrease_account(&account, amount)?;
+    ACCOUNTS.save(storage, owner.clone(), &account)?;
+    POOL_ACCOUNTS.save(storage, &pool)?;
+    unbonding::enqueue(storage, owner, amount, now, unbonding_period)?;
+    let release_at = now.plus_seconds(unbonding_period);
+    Ok((rewards, denom_rewards, amount, release_at))
+}
+
+pub fn execute_liquid_bond(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    twap::accrue(storage, now, redemption_rate_or_one(storage)?)?;
+
+    // Add Bond token to the Compounding pool, mint and return the Compound Share Token
+    let mut pool = POOL_LIQUID.load(storage)?;
+    let shares = pool.join(amount)?;
+    POOL_LIQUID.save(storage, &pool)?;
+    guard_liquid_redemption_rate(storage, false)?;
+    Ok(shares)
+}
+
+/// Burns `shares` immediately, but - unlike the bond side - doesn't pay `bond_denom` straight
+/// back out: the returned amount is queued in [`unbonding`] under `owner`, released only once
+/// `ExecuteMsg::ClaimUnbonded {}` is called after `unbonding_period` has elapsed.
+pub fn execute_liquid_unbond(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    now: Timestamp,
+    shares: Uint128,
+    unbonding_period: u64,
+) -> Result<(Uint128, Timestamp), ContractError> {
+    twap::accrue(storage, now, redemption_rate_or_one(storage)?)?;
+
+    let mut pool = POOL_LIQUID.load(storage)?;
+    let returned = pool.leave(shares)?;
+    POOL_LIQUID.save(storage, &pool)?;
+    guard_liquid_redemption_rate(storage, true)?;
+    unbonding::enqueue(storage, owner, returned, now, unbonding_period)?;
+    let release_at = now.plus_seconds(unbonding_period);
+    Ok((returned, release_at))
+}
+
+/// `share * revenue_surplus / pool_total`, computed in `Uint256` so the numerator can't overflow
+/// `Uint128` before the divide narrows it back down. Returns zero for a zero `share` or
+/// `pool_total` without promoting at all.
+fn pro_rata_split(
+    share: Uint128,
+    revenue_surplus: Uint128,
+    pool_total: Uint128,
+) -> Result<Uint128, ContractError> {
+    if share.is_zero() || pool_total.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let numerator = Uint256::from(share).checked_mul(Uint256::from(revenue_surplus))?;
+    // `pool_total` is non-zero here, so this divide can't panic.
+    let allocation = numerator / Uint256::from(pool_total);
+
+    Uint128::try_from(allocation)
+        .map_err(|_| ContractError::Std(StdError::generic_err("pro-rata allocation overflow")))
+}
+
+/// Calculates the amount to be distributed between (account, liquid) pools;
+/// Revenue balance is queried and surplus (ie not allocated to Account stakers) is split pro-rata between ACCOUNT stakers, and LIQUID pool size.
+/// Revenue allocated to LIQUID is transformed to a Wasm Execute msg to swap to the bond token
+/// The surplus of bond_balance - LIQUID.size() - ACCOUNT.total is the return value of the previous swap and can be allocated to the total liquid pool
+pub fn distribute(
+    env: &Env,
+    querier: QuerierWrapper,
+    storage: &mut dyn Storage,
+    config: &Config,
+    bond_amount_sent: &Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
+    twap::accrue(storage, env.block.time, redemption_rate_or_one(storage)?)?;
+
+    let mut account = POOL_ACCOUNTS.load(storage)?;
+    let mut liquid = POOL_LIQUID.load(storage)?;
+    let swap_pending = PENDING_SWAP.load(storage)?;
+
+    let bond_balance = querier
+        .query_balance(env.contract.address.clone(), config.bond_denom.clone())?
+        .amount;
+
+    let revenue_balance = querier
+        .query_balance(env.contract.address.clone(), config.revenue_denom.clone())?
+        .amount;
+
+    let revenue_surplus_with_fees = revenue_balance
+        .checked_sub(account.pending)?
+        .checked_sub(swap_pending)?;
+
+    let fee_amount = match &config.fee {
+        None => Uint128::zero(),
+        Some(fee) => (Decimal::from_atomics(revenue_surplus_with_fees, 0).unwrap()
+            * fee.percentage)
+            .to_uint_ceil(),
+    };
+    let revenue_surplus = revenue_surplus_with_fees - fee_amount;
+
+    // `account.total` and `liquid.size()` can each grow to the full bonded supply, so the
+    // pro-rata split is done in `Uint256` to avoid the `Uint128` overflow panic a direct `*`
+    // would hit once both pools and accrued revenue are large. Only `account_allocation` is
+    // floored by `pro_rata_split`; `liquid_allocation` takes the exact remainder so a second
+    // independent floor-division can't strand a wei of `revenue_surplus` as permanent
+    // undistributed dust (as two separate `pro_rata_split` calls previously could).
+    let pool_total = account.total.checked_add(liquid.size())?;
+    let account_allocation = pro_rata_split(account.total, revenue_surplus, pool_total)?;
+    let liquid_allocation = revenue_surplus.checked_sub(account_allocation)?;
+
+    account.distribute(account_allocation);
+    POOL_ACCOUNTS.save(storage, &account)?;
+
+    let bond_surplus = bond_balance
+        // Discount any bond tokens sent in the tx, so they're not incorrectly allocated to the Share pool size as swap returned funds
+        .checked_sub(*bond_amount_sent)?
+        .checked_sub(liquid.size())?
+        .checked_sub(account.total)?
+        // Unclaimed unbonding payouts still sit in the contract's balance, but they've already
+        // left both pools - without this they'd be double-counted as swap-proceeds appreciation.
+        .checked_sub(unbonding::total_unbonding(storage)?)?;
+
+    liquid.deposit(bond_surplus)?;
+    POOL_LIQUID.save(storage, &liquid)?;
+    guard_liquid_redemption_rate(storage, false)?;
+
+    // Take pending swaps off the queue, add back any remaining
+    let swap_total = swap_pending.add(liquid_allocation);
+    let swap_amount = if swap_eligible(storage, env.block.time, swap_total)? {
+        min(config.revenue_converter.2, swap_total)
+    } else {
+        Uint128::zero()
+    };
+    let swap_remainder = swap_total.sub(swap_amount);
+    track_queued_since(storage, env.block.time, swap_pending, swap_remainder)?;
+    PENDING_SWAP.save(storage, &swap_remainder)?;
+
+    Ok((swap_amount, fee_amount))
+}
+
+/// Credits `amount` of already-in-`revenue_denom` funds straight into the distributable pool,
+/// split pro-rata across account and liquid stakers the same way `distribute` splits swap
+/// revenue - without itself triggering a swap. The account side is credited directly as
+/// `pending_revenue`; the liquid side (which only ever appreciates via bond-denom deposits) is
+/// queued in `PENDING_SWAP` so it follows the same swap-and-fold path a converted amount would.
+/// Returns `(account_allocation, liquid_allocation)` for the caller to include in
+/// `event_external_rewards`.
+///
+/// Called from `contract.rs`'s `ExecuteMsg::AddRewards {}` arm, which takes payment in
+/// `revenue_denom` via `must_pay` - any address (e.g. a DAO treasury or incentive campaign) can
+/// top up staker rewards on a schedule this way, independent of the `revenue_converter` swap
+/// path.
+pub fn add_rewards(
+    storage: &mut dyn Storage,
+    amount: Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let mut account = POOL_ACCOUNTS.load(storage)?;
+    let liquid = POOL_LIQUID.load(storage)?;
+
+    let pool_total = account.total.checked_add(liquid.size())?;
+    let account_allocation = pro_rata_split(account.total, amount, pool_total)?;
+    // Whatever isn't allocated to account stakers goes to liquid, rather than re-running
+    // `pro_rata_split` for it, so dust can't be stranded between the two allocations.
+    let liquid_allocation = amount.checked_sub(account_allocation)?;
+
+    account.distribute(account_allocation);
+    POOL_ACCOUNTS.save(storage, &account)?;
+    increase_pending_swap(storage, liquid_allocation)?;
+
+    Ok((account_allocation, liquid_allocation))
+}
+
+/// Credits each coin in `funds` to [`reward_index`]'s multi-denom accounting, pro-rata against
+/// the current account-pool bonded total. Unlike `add_rewards`, this never touches the liquid
+/// side - `reward_index` only ever settles through `execute_account_bond`/`claim`/`withdraw`.
+pub fn add_denom_rewards(storage: &mut dyn Storage, funds: &[Coin]) -> Result<(), ContractError> {
+    let account = POOL_ACCOUNTS.load(storage)?;
+    for coin in funds {
+        reward_index::receive(storage, &coin.denom, coin.amount, account.total)?;
+    }
+    Ok(())
+}
+
+pub fn increase_pending_swap(storage: &mut dyn Storage, amount: Uint128) -> StdResult<()> {
+    let swap_pending = PENDING_SWAP.load(storage)?;
+    PENDING_SWAP.save(storage, &(swap_pending + amount))
+}
+
+/// A reference-price bound on the `revenue_converter` swap: a realized output quoted at less
+/// than `(1 - max_slippage_bps/10000)` of the oracle-priced input is rejected as a bad fill.
+/// `oracle` names the price source an integration is expected to have configured (this contract
+/// always prices through the same `OracleValue` lookup `portfolio_value_usd` uses; it's carried
+/// here so a future multi-oracle setup has somewhere to record which one was intended).
+#[cw_serde]
+pub struct SwapGuard {
+    pub max_slippage_bps: u16,
+    pub oracle: Addr,
+}
+
+/// Installed by `sudo()`'s `SudoMsg::SetSwapGuard` arm. `None` removes the guard and reverts to
+/// accepting whatever the swap contract returns.
+pub fn set_swap_guard(storage: &mut dyn Storage, guard: Option<SwapGuard>) -> StdResult<()> {
+    match guard {
+        Some(guard) => SWAP_GUARD.save(storage, &guard),
+        None => {
+            SWAP_GUARD.remove(storage);
+            Ok(())
+        }
+    }
+}
+
+/// Fails if `realized_amount` of `bond_denom` is worth less than `(1 - max_slippage_bps)` of
+/// `swap_amount` of `revenue_denom` at the current oracle price. A no-op if no guard is
+/// configured.
+pub fn guard_swap_output(
+    deps: Deps,
+    config: &Config,
+    swap_amount: Uint128,
+    realized_amount: Uint128,
+) -> Result<(), ContractError> {
+    let Some(guard) = SWAP_GUARD.may_load(deps.storage)? else {
+        return Ok(());
+    };
+
+    let value_usd = |denom: &str, amount: Uint128| -> Result<Decimal, ContractError> {
+        coin(amount.u128(), denom)
+            .value_usd(deps.querier)
+            .map_err(|e| ContractError::Std(StdError::generic_err(e.to_string())))
+    };
+
+    let input_usd = value_usd(&config.revenue_denom, swap_amount)?;
+    let output_usd = value_usd(&config.bond_denom, realized_amount)?;
+
+    if !within_slippage(input_usd, output_usd, guard.max_slippage_bps) {
+        let min_acceptable = min_acceptable_output(input_usd, guard.max_slippage_bps);
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "swap output {output_usd} below slippage-guarded minimum {min_acceptable}"
+        ))));
+    }
+    Ok(())
+}
+
+fn min_acceptable_output(input_usd: Decimal, max_slippage_bps: u16) -> Decimal {
+    input_usd * Decimal::from_ratio(10_000u64 - max_slippage_bps as u64, 10_000u64)
+}
+
+/// `true` if `output_usd` is at least `(1 - max_slippage_bps/10000)` of `input_usd`. Split out
+/// from [`guard_swap_output`] so the slippage arithmetic is testable without an oracle-backed
+/// querier.
+fn within_slippage(input_usd: Decimal, output_usd: Decimal, max_slippage_bps: u16) -> bool {
+    output_usd >= min_acceptable_output(input_usd, max_slippage_bps)
+}
+
+/// Batches small swaps instead of converting every last wei the moment it's received: a
+/// `distribute` call only swaps once `min_swap_amount` has pooled, unless the oldest queued
+/// revenue has been waiting longer than `max_swap_age`, in which case it swaps regardless of size
+/// so a quiet period can't strand revenue unconverted forever. Realized-output slippage is
+/// already guarded separately by [`SwapGuard`]/[`guard_swap_output`]; this only gates *when* a
+/// swap fires, not what fill it accepts.
+#[cw_serde]
+pub struct SwapBatchConfig {
+    pub min_swap_amount: Uint128,
+    pub max_swap_age: u64,
+}
+
+/// Installed by `sudo()`'s `SudoMsg::SetSwapBatch` arm. `None` removes the policy and reverts to
+/// swapping whatever's pending on every `distribute` call.
+pub fn set_swap_batch_config(
+    storage: &mut dyn Storage,
+    policy: Option<SwapBatchConfig>,
+) -> StdResult<()> {
+    match policy {
+        Some(policy) => SWAP_BATCH.save(storage, &policy),
+        None => {
+            SWAP_BATCH.remove(storage);
+            Ok(())
+        }
+    }
+}
+
+/// Whether `swap_total` should be converted this call: always true with no policy configured
+/// (preserving the pre-batching behaviour), otherwise true once `swap_total` clears
+/// `min_swap_amount` or the queue has aged past `max_swap_age`.
+fn swap_eligible(storage: &dyn Storage, now: Timestamp, swap_total: Uint128) -> StdResult<bool> {
+    if swap_total.is_zero() {
+        return Ok(false);
+    }
+    let Some(policy) = SWAP_BATCH.may_load(storage)? else {
+        return Ok(true);
+    };
+    if swap_total >= policy.min_swap_amount {
+        return Ok(true);
+    }
+    let aged_out = QUEUED_SINCE.may_load(storage)?.is_some_and(|since| {
+        now.seconds().saturating_sub(since.seconds()) >= policy.max_swap_age
+    });
+    Ok(aged_out)
+}
+
+/// Starts the clock the moment the queue goes from empty to non-empty, and clears it the moment
+/// it's fully drained again - `swap_pending` is the balance entering this call, `swap_remainder`
+/// the balance leaving it.
+fn track_queued_since(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    swap_pending: Uint128,
+    swap_remainder: Uint128,
+) -> StdResult<()> {
+    if swap_pending.is_zero() && swap_remainder.gt(&Uint128::zero()) {
+        QUEUED_SINCE.save(storage, &now)?;
+    } else if swap_remainder.is_zero() {
+        QUEUED_SINCE.remove(storage);
+    }
+    Ok(())
+}
+
+/// A snapshot of the swap queue for a `QueryMsg::PendingSwap {}`-style query: how much
+/// `revenue_denom` is waiting, how long the oldest of it has been waiting, and - if a batching
+/// policy is configured and nothing has cleared the size threshold yet - when it becomes eligible
+/// to swap purely by age.
+#[cw_serde]
+pub struct PendingSwapStatus {
+    pub amount: Uint128,
+    pub queued_since: Option<Timestamp>,
+    pub next_eligible: Option<Timestamp>,
+}
+
+pub fn pending_swap_status(storage: &dyn Storage) -> StdResult<PendingSwapStatus> {
+    let amount = PENDING_SWAP.load(storage)?;
+    let queued_since = QUEUED_SINCE.may_load(storage)?;
+
+    let next_eligible = match (SWAP_BATCH.may_load(storage)?, queued_since) {
+        (Some(policy), Some(since)) if amount < policy.min_swap_amount => {
+            Some(Timestamp::from_seconds(since.seconds() + policy.max_swap_age))
+        }
+        _ => None,
+    };
+
+    Ok(PendingSwapStatus {
+        amount,
+        queued_since,
+        next_eligible,
+    })
+}
+
+/// Escape hatch for a stuck swap queue, called from `sudo()`'s `SudoMsg::CancelPendingSwap` arm:
+/// zeroes `PENDING_SWAP`, clears the age clock, and returns the amount for the caller to refund -
+/// so a queue stuck behind a `min_swap_amount` that can no longer be reached (e.g. after lowering
+/// `revenue_converter.2`) has a way out.
+pub fn cancel_pending_swap(storage: &mut dyn Storage) -> StdResult<Uint128> {
+    let amount = PENDING_SWAP.load(storage)?;
+    PENDING_SWAP.save(storage, &Uint128::zero())?;
+    QUEUED_SINCE.remove(storage);
+    Ok(amount)
+}
+
+pub fn status(env: Env, deps: Deps, config: &Config) -> StdResult<StatusResponse> {
+    let liquid = POOL_LIQUID.load(deps.storage)?;
+    let account = POOL_ACCOUNTS.load(deps.storage)?;
+    let swap_pending = PENDING_SWAP.load(deps.storage)?;
+
+    let revenue_balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), config.revenue_denom.clone())?
+        .amount;
+
+    let revenue_surplus = revenue_balance
+        .checked_sub(account.pending)?
+        .checked_sub(swap_pending)?;
+
+    Ok(StatusResponse {
+        account_bond: account.total,
+        assigned_revenue: account.pending,
+        liquid_bond_shares: liquid.shares(),
+        liquid_bond_size: liquid.size(),
+        undistributed_revenue: revenue_surplus,
+    })
+}
+
+/// `status`'s fields plus the unbonding queue's in-flight total across every address, exposed via
+/// `QueryMsg::StatusWithUnbonding {}`. Kept as a thin wrapper around `StatusResponse` rather than
+/// duplicating its fields, so it stays in sync if that struct grows.
+#[cw_serde]
+pub struct StatusWithUnbonding {
+    pub status: StatusResponse,
+    pub total_unbonding: Uint128,
+}
+
+pub fn status_with_unbonding(
+    env: Env,
+    deps: Deps,
+    config: &Config,
+) -> StdResult<StatusWithUnbonding> {
+    Ok(StatusWithUnbonding {
+        status: status(env, deps, config)?,
+        total_unbonding: unbonding::total_unbonding(deps.storage)?,
+    })
+}
+
+/// One denom's slice of a multi-denom `StatusResponse`: mirrors the single-denom
+/// `assigned_revenue`/`undistributed_revenue` pair `status` already reports, just keyed by denom
+/// instead of assumed to be `config.revenue_denom`.
+#[cw_serde]
+pub struct DenomRevenue {
+    pub denom: String,
+    pub assigned_revenue: Uint128,
+    pub undistributed_revenue: Uint128,
+}
+
+/// `status`'s fields plus a per-denom breakdown for every denom ever credited through
+/// [`reward_index::receive`] - what `StatusResponse` itself would carry a `Vec<DenomRevenue>`
+/// field for once `rujira_rs::staking` grows to track more than one `revenue_denom`.
+///
+/// `undistributed_revenue` here is the contract's balance of the denom less what [`reward_index`]
+/// has assigned to stakers and not yet paid out, for denoms that bypass a swap entirely ("distribute directly" in the request this answers); a denom
+/// configured "swap-then-distribute" needs the batching/eligibility machinery `PendingSwap`
+/// already has for `config.revenue_denom` extended per-denom, which needs a `Config` that can
+/// carry more than one `revenue_converter` - not present in this snapshot.
+#[cw_serde]
+pub struct MultiDenomStatus {
+    pub status: StatusResponse,
+    pub revenues: Vec<DenomRevenue>,
+}
+
+pub fn multi_denom_status(
+    env: Env,
+    deps: Deps,
+    config: &Config,
+) -> Result<MultiDenomStatus, ContractError> {
+    let status = status(env.clone(), deps, config)?;
+
+    let mut revenues = Vec::new();
+    for denom in reward_index::known_denoms(deps.storage)? {
+        let assigned = reward_index::assigned_revenue(deps.storage, &denom)?;
+        let balance = deps
+            .querier
+            .query_balance(env.contract.address.clone(), denom.clone())?
+            .amount;
+        let undistributed = balance.checked_sub(assigned).unwrap_or_default();
+        revenues.push(DenomRevenue {
+            denom,
+            assigned_revenue: assigned,
+            undistributed_revenue: undistributed,
+        });
+    }
+
+    Ok(MultiDenomStatus { status, revenues })
+}
+
+/// `account`'s payload generalized to a claimable balance per denom rather than a single
+/// `revenue_denom` amount - what `AccountResponse.pending_revenue` would become a `Vec<Coin>` for.
+#[cw_serde]
+pub struct AccountRevenueResponse {
+    pub addr: String,
+    pub bonded: Uint128,
+    pub pending_revenue: Vec<Coin>,
+}
+
+pub fn account_revenue(
+    storage: &dyn Storage,
+    addr: Addr,
+    bonded: Uint128,
+) -> Result<AccountRevenueResponse, ContractError> {
+    let pending = reward_index::pending(storage, &addr, bonded)?;
+    Ok(AccountRevenueResponse {
+        addr: addr.to_string(),
+        bonded,
+        pending_revenue: pending
+            .into_iter()
+            .map(|(denom, amount)| coin(amount.u128(), denom))
+            .collect(),
+    })
+}
+
+/// `QueryMsg::ExchangeRate {}`'s payload: the sRUJI share token's exact redemption rate against
+/// `bond_denom`, alongside the raw pool totals it's derived from, so an integrating AMM pair could
+/// treat the LSD like a rate-providing asset the way an LSD-aware stableswap pair uses a target
+/// exchange rate instead of assuming 1:1.
+#[cw_serde]
+pub struct ExchangeRateResponse {
+    pub rate: Decimal,
+    pub liquid_bond_size: Uint128,
+    pub liquid_bond_shares: Uint128,
+}
+
+/// Previews `liquid_redemption_rate` as it would read immediately after the next `distribute`, by
+/// running just the liquid side of `distribute`'s accounting against the current bond balance
+/// without persisting the result, so the rate reflects swap proceeds sitting in the contract's
+/// balance that haven't been folded into `POOL_LIQUID` yet.
+pub fn exchange_rate(
+    env: &Env,
+    querier: QuerierWrapper,
+    storage: &dyn Storage,
+    config: &Config,
+) -> Result<ExchangeRateResponse, ContractError> {
+    let mut liquid = POOL_LIQUID.load(storage)?;
+    let account = POOL_ACCOUNTS.load(storage)?;
+
+    let bond_balance = querier
+        .query_balance(env.contract.address.clone(), config.bond_denom.clone())?
+        .amount;
+    let bond_surplus = bond_balance
+        .checked_sub(liquid.size())?
+        .checked_sub(account.total)?
+        .checked_sub(unbonding::total_unbonding(storage)?)?;
+    liquid.deposit(bond_surplus)?;
+
+    let rate = if liquid.shares().is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(liquid.size(), liquid.shares())
+    };
+
+    Ok(ExchangeRateResponse {
+        rate,
+        liquid_bond_size: liquid.size(),
+        liquid_bond_shares: liquid.shares(),
+    })
+}
+
+pub fn account(storage: &dyn Storage, addr: Addr) -> StdResult<AccountResponse> {
+    let accounts = POOL_ACCOUNTS.load(storage)?;
+    let account = ACCOUNTS.load(storage, addr.clone())?;
+
+    Ok(AccountResponse {
+        addr: addr.to_string(),
+        bonded: account.amount,
+        pending_revenue: accounts.pending_revenue(&account),
+    })
+}
+
+/// Installed by `contract.rs`'s `AccountMsg::SetBeneficiary` arm. `None` clears the override,
+/// reverting revenue payouts to the bonder itself; bonding/unbonding the principal itself always
+/// stays with `owner` - only the revenue side of `Bond`/`Claim`/`Withdraw` is redirected.
+pub fn set_beneficiary(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    beneficiary: Option<Addr>,
+) -> StdResult<()> {
+    match beneficiary {
+        Some(beneficiary) => BENEFICIARY.save(storage, owner, &beneficiary),
+        None => {
+            BENEFICIARY.remove(storage, owner);
+            Ok(())
+        }
+    }
+}
+
+/// The address `owner`'s revenue coins should actually be paid to: their configured beneficiary,
+/// or `owner` itself if none is set.
+pub fn beneficiary_or_owner(storage: &dyn Storage, owner: &Addr) -> StdResult<Addr> {
+    Ok(BENEFICIARY.may_load(storage, owner)?.unwrap_or_else(|| owner.clone()))
+}
+
+/// `account`'s payload plus the configured beneficiary (or `addr` itself if none is set) - what
+/// `AccountResponse` would carry a `beneficiary` field for once `rujira_rs::staking` grows one.
+#[cw_serde]
+pub struct AccountWithBeneficiary {
+    pub account: AccountResponse,
+    pub beneficiary: Addr,
+}
+
+pub fn account_with_beneficiary(
+    storage: &dyn Storage,
+    addr: Addr,
+) -> StdResult<AccountWithBeneficiary> {
+    Ok(AccountWithBeneficiary {
+        beneficiary: beneficiary_or_owner(storage, &addr)?,
+        account: account(storage, addr)?,
+    })
+}
+
+/// USD value of `addr`'s position: bonded stake plus assigned-but-unclaimed `pending_revenue`,
+/// both oracle-priced via `OracleValue`, plus any liquid bond share balance held in the wallet,
+/// converted to bond tokens through `liquid_redemption_rate` before pricing. Used by
+/// `health_check` to bracket a multi-message flow with a before/after invariant.
+pub fn portfolio_value_usd(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    addr: &Addr,
+) -> Result<Decimal, ContractError> {
+    let value_usd = |denom: &str, amount: Uint128| -> Result<Decimal, ContractError> {
+        coin(amount.u128(), denom)
+            .value_usd(deps.querier)
+            .map_err(|e| ContractError::Std(StdError::generic_err(e.to_string())))
+    };
+
+    let accounts = POOL_ACCOUNTS.load(deps.storage)?;
+    let account = ACCOUNTS.load(deps.storage, addr.clone())?;
+
+    let mut value = value_usd(&config.bond_denom, account.amount)?;
+    value += value_usd(&config.revenue_denom, accounts.pending_revenue(&account))?;
+
+    let share_denom =
+        TokenFactory::new(env, format!("staking-{}", config.bond_denom).as_str()).denom();
+    let share_balance = deps.querier.query_balance(addr, share_denom)?.amount;
+    if !share_balance.is_zero() {
+        let rate = liquid_redemption_rate(deps.storage)?;
+        let bond_equivalent = share_balance.mul_floor(rate);
+        value += value_usd(&config.bond_denom, bond_equivalent)?;
+    }
+
+    Ok(value)
+}
+
+/// Fails unless `addr`'s `portfolio_value_usd` is at least `min_value_usd`. Intended to bracket
+/// a `DoSwap`/`DoOrder` style callback sequence so a flow that would leave a position underwater
+/// is rejected and the whole transaction rolled back, rather than leaving a caller to discover
+/// the shortfall after the fact.
+pub fn health_check(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    addr: &Addr,
+    min_value_usd: Decimal,
+) -> Result<(), ContractError> {
+    let value = portfolio_value_usd(deps, env, config, addr)?;
+    if value < min_value_usd {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "portfolio value {value} below required minimum {min_value_usd}"
+        ))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        coin, coins,
+        testing::{mock_dependencies_with_balances, mock_env, MockStorage},
+        Binary,
+    };
+    use cw_multi_test::BasicApp;
+    use proptest::prelude::{Strategy, *};
+
+    use super::*;
+
+    #[test]
+    fn test_distribution() {
+        let app = BasicApp::default();
+        let env = mock_env();
+
+        let mut deps = mock_dependencies_with_balances(&[
+            (
+                app.api().addr_make("app").as_str(),
+                &coins(1_000_000u128, "uusdc"),
+            ),
+            (
+                env.contract.address.as_str(),
+                &[
+                    coin(1_000u128, "uusdc"),
+                    // Two operations below bond total of 3000 ruji
+                    // More complex testing executed in contract.rs with cw-multi-test
+                    coin(3_000u128, "uruji"),
+                ],
+            ),
+        ]);
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::from(100u128),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(deps.as_mut().storage).unwrap();
+
+        assert_eq!(
+            POOL_LIQUID.load(deps.as_mut().storage).unwrap(),
+            SharePool::default()
+        );
+
+        assert_eq!(
+            POOL_ACCOUNTS.load(deps.as_mut().storage).unwrap(),
+            AccountPool::default()
+        );
+        let mutdeps = deps.as_mut();
+
+        execute_account_bond(
+            mutdeps.storage,
+            &app.api().addr_make("account"),
+            Uint128::from(750u128),
+        )
+        .unwrap();
+
+        execute_account_bond(
+            mutdeps.storage,
+            &app.api().addr_make("account2"),
+            Uint128::from(250u128),
+        )
+        .unwrap();
+
+        execute_liquid_bond(mutdeps.storage, env.block.time, Uint128::from(2_000u128)).unwrap();
+
+        assert_eq!(
+            POOL_LIQUID.load(mutdeps.storage).unwrap().shares(),
+            Uint128::from(2_000u128)
+        );
+
+        assert_eq!(
+            POOL_LIQUID.load(mutdeps.storage).unwrap().size(),
+            Uint128::from(2_000u128)
+        );
+
+        assert_eq!(
+            POOL_ACCOUNTS.load(mutdeps.storage).unwrap().total,
+            Uint128::from(1_000u128)
+        );
+
+        let (swap_amount, _fee_amount) = distribute(
+            &env,
+            mutdeps.querier,
+            mutdeps.storage,
+            &config,
+            &Uint128::zero(),
+        )
+        .unwrap();
+        // Balance of 1000 USDC split across 3000 RUJI - 1000 account floors to 333, and liquid
+        // takes the exact remainder (667) rather than its own floored 666, so no dust is
+        // stranded as undistributed revenue.
+
+        assert_eq!(swap_amount, Uint128::from(100u128));
+
+        assert_eq!(
+            PENDING_SWAP.load(mutdeps.storage).unwrap(),
+            Uint128::from(567u128)
+        );
+
+        assert_eq!(
+            POOL_LIQUID.load(mutdeps.storage).unwrap().shares(),
+            Uint128::from(2_000u128)
+        );
+
+        assert_eq!(
+            POOL_LIQUID.load(mutdeps.storage).unwrap().size(),
+            Uint128::from(2_000u128)
+        );
+
+        assert_eq!(
+            POOL_ACCOUNTS.load(mutdeps.storage).unwrap().pending,
+            Uint128::from(333u128)
+        );
+
+        assert_eq!(
+            account(mutdeps.storage, app.api().addr_make("account")).unwrap(),
+            AccountResponse {
+                addr: app.api().addr_make("account").to_string(),
+                bonded: Uint128::from(750u128),
+                pending_revenue: Uint128::from(249u128)
+            }
+        );
+    }
+
+    #[test]
+    fn distribute_strands_no_dust_on_a_non_divisible_split() {
+        let app = BasicApp::default();
+        let env = mock_env();
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            &[coin(101u128, "uusdc"), coin(4_000u128, "uruji")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::zero(),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(storage).unwrap();
+        execute_account_bond(storage, &app.api().addr_make("account"), Uint128::from(1_000u128))
+            .unwrap();
+        execute_liquid_bond(storage, env.block.time, Uint128::from(3_000u128)).unwrap();
+
+        let mutdeps = deps.as_mut();
+        distribute(&env, mutdeps.querier, mutdeps.storage, &config, &Uint128::zero()).unwrap();
+
+        // 101 split 1000:3000 floors account to 25 (101 * 1000 / 4000 = 25.25); every last wei
+        // of the remaining 76 lands in PENDING_SWAP rather than being stranded.
+        let storage = deps.as_ref().storage;
+        let account_pending = POOL_ACCOUNTS.load(storage).unwrap().pending;
+        let swap_pending = PENDING_SWAP.load(storage).unwrap();
+        assert_eq!(account_pending, Uint128::from(25u128));
+        assert_eq!(swap_pending, Uint128::from(76u128));
+        assert_eq!(account_pending + swap_pending, Uint128::from(101u128));
+    }
+
+    #[test]
+    fn exchange_rate_reflects_undistributed_swap_proceeds() {
+        let app = BasicApp::default();
+        let env = mock_env();
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            &[coin(2_500u128, "uruji")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::from(100u128),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(storage).unwrap();
+        execute_liquid_bond(storage, env.block.time, Uint128::from(2_000u128)).unwrap();
+
+        // The contract balance already holds 2_500 uruji against only 2_000 bonded - the other
+        // 500 are undistributed swap proceeds that `distribute` hasn't folded into the pool yet.
+        // The preview should fold them into the rate anyway.
+        let preview = exchange_rate(&env, deps.as_ref().querier, deps.as_ref().storage, &config)
+            .unwrap();
+        assert_eq!(preview.rate, Decimal::from_ratio(2_500u128, 2_000u128));
+        assert_eq!(preview.liquid_bond_size, Uint128::from(2_500u128));
+        assert_eq!(preview.liquid_bond_shares, Uint128::from(2_000u128));
+
+        // The preview never persisted - the stored pool is untouched.
+        assert_eq!(
+            POOL_LIQUID.load(deps.as_ref().storage).unwrap().size(),
+            Uint128::from(2_000u128)
+        );
+    }
+
+    #[test]
+    fn add_rewards_splits_pro_rata_without_stranding_dust() {
+        let app = BasicApp::default();
+        let mut deps = mock_dependencies_with_balances(&[]);
+        let storage = deps.as_mut().storage;
+
+        init(storage).unwrap();
+        execute_account_bond(storage, &app.api().addr_make("account"), Uint128::from(1_000u128))
+            .unwrap();
+        execute_liquid_bond(storage, Timestamp::from_seconds(0), Uint128::from(3_000u128)).unwrap();
+
+        let (account_allocation, liquid_allocation) =
+            add_rewards(storage, Uint128::from(101u128)).unwrap();
+        // 1000 account / 3000 liquid = 1:4 total; 101 * 1000 / 4000 floors to 25, the
+        // remaining 76 goes to liquid rather than a second floor-division of its own.
+        assert_eq!(account_allocation, Uint128::from(25u128));
+        assert_eq!(liquid_allocation, Uint128::from(76u128));
+        assert_eq!(account_allocation + liquid_allocation, Uint128::from(101u128));
+
+        assert_eq!(
+            POOL_ACCOUNTS.load(storage).unwrap().pending,
+            Uint128::from(25u128)
+        );
+        assert_eq!(PENDING_SWAP.load(storage).unwrap(), Uint128::from(76u128));
+    }
+
+    #[test]
+    fn twap_rate_reflects_a_distribute_driven_rate_step() {
+        let app = BasicApp::default();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            &[coin(1_100u128, "uruji")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::zero(),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(storage).unwrap();
+        // Bonding at a 1:1 rate seeds the series (no prior snapshot to accrue against).
+        execute_liquid_bond(storage, env.block.time, Uint128::from(1_000u128)).unwrap();
+
+        // 100 extra uruji sits in the contract balance as undistributed swap proceeds; the next
+        // `distribute`, 100 seconds later, folds it into the pool and lifts the rate to 1.10.
+        env.block.time = Timestamp::from_seconds(100);
+        let mutdeps = deps.as_mut();
+        distribute(&env, mutdeps.querier, mutdeps.storage, &config, &Uint128::zero()).unwrap();
+        assert_eq!(
+            liquid_redemption_rate(mutdeps.storage).unwrap(),
+            Decimal::percent(110)
+        );
+
+        // A mutation 100 seconds after that records the post-step rate as a new snapshot.
+        execute_liquid_bond(
+            mutdeps.storage,
+            Timestamp::from_seconds(200),
+            Uint128::zero(),
+        )
+        .unwrap();
+
+        // The window covering the rate step averages to 1.10 - the rate it actually held for all
+        // of [100, 200] - while a window reaching back before the step averages lower.
+        let recent = twap_rate(mutdeps.storage, Timestamp::from_seconds(200), 100).unwrap();
+        assert_eq!(recent, Decimal::percent(110));
+
+        let full = twap_rate(mutdeps.storage, Timestamp::from_seconds(200), 200).unwrap();
+        assert_eq!(full, Decimal::percent(105));
+    }
+
+    #[test]
+    fn exchange_rate_is_one_before_any_bond() {
+        let env = mock_env();
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            &[coin(0u128, "uruji")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (Addr::unchecked("revenue"), Binary::new(vec![0]), Uint128::one()),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(storage).unwrap();
+        let rate = exchange_rate(&env, deps.as_ref().querier, deps.as_ref().storage, &config)
+            .unwrap();
+        assert_eq!(rate.rate, Decimal::one());
+    }
+
+    #[test]
+    fn within_slippage_accepts_down_to_exactly_the_guarded_floor() {
+        let input_usd = Decimal::percent(10_000); // 100.00
+        // 200 bps guard -> floor is 98% of input.
+        assert!(within_slippage(input_usd, Decimal::percent(9_800), 200));
+        assert!(!within_slippage(input_usd, Decimal::percent(9_799), 200));
+        // Zero slippage guard requires an exact (or better) fill.
+        assert!(within_slippage(input_usd, input_usd, 0));
+        assert!(!within_slippage(input_usd, Decimal::percent(9_999), 0));
+    }
+
+    #[test]
+    fn set_swap_guard_round_trips_and_clears() {
+        let mut storage = MockStorage::new();
+        let guard = SwapGuard {
+            max_slippage_bps: 300,
+            oracle: Addr::unchecked("oracle"),
+        };
+        set_swap_guard(&mut storage, Some(guard.clone())).unwrap();
+        assert_eq!(SWAP_GUARD.load(&storage).unwrap(), guard);
+
+        set_swap_guard(&mut storage, None).unwrap();
+        assert!(SWAP_GUARD.may_load(&storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn set_swap_batch_config_round_trips_and_clears() {
+        let mut storage = MockStorage::new();
+        let policy = SwapBatchConfig {
+            min_swap_amount: Uint128::from(1_000u128),
+            max_swap_age: 3_600,
+        };
+        set_swap_batch_config(&mut storage, Some(policy.clone())).unwrap();
+        assert_eq!(SWAP_BATCH.load(&storage).unwrap(), policy);
+
+        set_swap_batch_config(&mut storage, None).unwrap();
+        assert!(SWAP_BATCH.may_load(&storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn swap_eligible_with_no_policy_always_swaps_whatever_is_pending() {
+        let storage = MockStorage::new();
+        assert!(swap_eligible(&storage, Timestamp::from_seconds(0), Uint128::one()).unwrap());
+        assert!(!swap_eligible(&storage, Timestamp::from_seconds(0), Uint128::zero()).unwrap());
+    }
+
+    #[test]
+    fn swap_eligible_waits_for_the_minimum_unless_the_queue_has_aged_out() {
+        let mut storage = MockStorage::new();
+        set_swap_batch_config(
+            &mut storage,
+            Some(SwapBatchConfig {
+                min_swap_amount: Uint128::from(1_000u128),
+                max_swap_age: 600,
+            }),
+        )
+        .unwrap();
+
+        // Below the minimum and never queued - not yet eligible.
+        assert!(!swap_eligible(&storage, Timestamp::from_seconds(0), Uint128::from(500u128))
+            .unwrap());
+
+        // Clearing the minimum is eligible regardless of age.
+        assert!(swap_eligible(&storage, Timestamp::from_seconds(0), Uint128::from(1_000u128))
+            .unwrap());
+
+        // Below the minimum, but the queue has been waiting long enough - eligible anyway.
+        QUEUED_SINCE
+            .save(&mut storage, &Timestamp::from_seconds(0))
+            .unwrap();
+        assert!(!swap_eligible(&storage, Timestamp::from_seconds(599), Uint128::from(500u128))
+            .unwrap());
+        assert!(swap_eligible(&storage, Timestamp::from_seconds(600), Uint128::from(500u128))
+            .unwrap());
+    }
+
+    #[test]
+    fn track_queued_since_starts_on_first_deposit_and_clears_on_drain() {
+        let mut storage = MockStorage::new();
+
+        // Queue goes from empty to non-empty - starts the clock.
+        track_queued_since(
+            &mut storage,
+            Timestamp::from_seconds(100),
+            Uint128::zero(),
+            Uint128::from(50u128),
+        )
+        .unwrap();
+        assert_eq!(
+            QUEUED_SINCE.load(&storage).unwrap(),
+            Timestamp::from_seconds(100)
+        );
+
+        // Still non-empty after a partial swap - the clock doesn't reset.
+        track_queued_since(
+            &mut storage,
+            Timestamp::from_seconds(200),
+            Uint128::from(50u128),
+            Uint128::from(20u128),
+        )
+        .unwrap();
+        assert_eq!(
+            QUEUED_SINCE.load(&storage).unwrap(),
+            Timestamp::from_seconds(100)
+        );
+
+        // Fully drained - the clock clears.
+        track_queued_since(
+            &mut storage,
+            Timestamp::from_seconds(300),
+            Uint128::from(20u128),
+            Uint128::zero(),
+        )
+        .unwrap();
+        assert!(QUEUED_SINCE.may_load(&storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn pending_swap_status_reports_amount_and_age_based_eligibility() {
+        let mut storage = MockStorage::new();
+        init(&mut storage).unwrap();
+        set_swap_batch_config(
+            &mut storage,
+            Some(SwapBatchConfig {
+                min_swap_amount: Uint128::from(1_000u128),
+                max_swap_age: 600,
+            }),
+        )
+        .unwrap();
+
+        // Nothing queued yet.
+        let empty = pending_swap_status(&storage).unwrap();
+        assert_eq!(empty.amount, Uint128::zero());
+        assert_eq!(empty.queued_since, None);
+        assert_eq!(empty.next_eligible, None);
+
+        PENDING_SWAP
+            .save(&mut storage, &Uint128::from(200u128))
+            .unwrap();
+        QUEUED_SINCE
+            .save(&mut storage, &Timestamp::from_seconds(1_000))
+            .unwrap();
+
+        let queued = pending_swap_status(&storage).unwrap();
+        assert_eq!(queued.amount, Uint128::from(200u128));
+        assert_eq!(queued.queued_since, Some(Timestamp::from_seconds(1_000)));
+        assert_eq!(queued.next_eligible, Some(Timestamp::from_seconds(1_600)));
+
+        // Once the amount clears the minimum, it's already eligible - no age-based ETA to report.
+        PENDING_SWAP
+            .save(&mut storage, &Uint128::from(1_000u128))
+            .unwrap();
+        let cleared = pending_swap_status(&storage).unwrap();
+        assert_eq!(cleared.next_eligible, None);
+    }
+
+    #[test]
+    fn cancel_pending_swap_zeroes_the_queue_and_returns_the_amount() {
+        let mut storage = MockStorage::new();
+        init(&mut storage).unwrap();
+        PENDING_SWAP
+            .save(&mut storage, &Uint128::from(750u128))
+            .unwrap();
+        QUEUED_SINCE
+            .save(&mut storage, &Timestamp::from_seconds(1_000))
+            .unwrap();
+
+        let refunded = cancel_pending_swap(&mut storage).unwrap();
+        assert_eq!(refunded, Uint128::from(750u128));
+        assert_eq!(PENDING_SWAP.load(&storage).unwrap(), Uint128::zero());
+        assert!(QUEUED_SINCE.may_load(&storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn distribute_withholds_a_swap_below_the_minimum_and_releases_it_once_aged_out() {
+        let app = BasicApp::default();
+        let env = mock_env();
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            &[coin(50u128, "uusdc"), coin(1_000u128, "uruji")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::from(1_000u128),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(storage).unwrap();
+        execute_liquid_bond(storage, env.block.time, Uint128::from(1_000u128)).unwrap();
+        set_swap_batch_config(
+            storage,
+            Some(SwapBatchConfig {
+                min_swap_amount: Uint128::from(1_000u128),
+                max_swap_age: 600,
+            }),
+        )
+        .unwrap();
+
+        // 50 uusdc of revenue is all liquid allocation, well below the 1,000 minimum - withheld.
+        let mutdeps = deps.as_mut();
+        let (swap_amount, _) =
+            distribute(&env, mutdeps.querier, mutdeps.storage, &config, &Uint128::zero()).unwrap();
+        assert_eq!(swap_amount, Uint128::zero());
+        assert_eq!(
+            PENDING_SWAP.load(mutdeps.storage).unwrap(),
+            Uint128::from(50u128)
+        );
+        let queued_since = QUEUED_SINCE.load(mutdeps.storage).unwrap();
+
+        // Calling again 599s later, with the balance unchanged and the queue one second short
+        // of max_swap_age, changes nothing.
+        let mut later_env = env.clone();
+        later_env.block.time = queued_since.plus_seconds(599);
+        let mutdeps = deps.as_mut();
+        let (swap_amount, _) = distribute(
+            &later_env,
+            mutdeps.querier,
+            mutdeps.storage,
+            &config,
+            &Uint128::zero(),
+        )
+        .unwrap();
+        assert_eq!(swap_amount, Uint128::zero());
+
+        // Once the queue has aged past max_swap_age, it releases even though it's still tiny.
+        later_env.block.time = queued_since.plus_seconds(600);
+        let mutdeps = deps.as_mut();
+        let (swap_amount, _) = distribute(
+            &later_env,
+            mutdeps.querier,
+            mutdeps.storage,
+            &config,
+            &Uint128::zero(),
+        )
+        .unwrap();
+        assert_eq!(swap_amount, Uint128::from(50u128));
+        assert_eq!(
+            PENDING_SWAP.load(mutdeps.storage).unwrap(),
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn status_with_unbonding_reports_the_in_flight_total() {
+        let app = BasicApp::default();
+        let env = mock_env();
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            &[coin(0u128, "uusdc")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::one(),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+
+        init(storage).unwrap();
+        unbonding::enqueue(
+            storage,
+            &app.api().addr_make("account"),
+            Uint128::from(42u128),
+            mock_env().block.time,
+            100,
+        )
+        .unwrap();
+
+        let result =
+            status_with_unbonding(mock_env(), deps.as_ref(), &config).unwrap();
+        assert_eq!(result.total_unbonding, Uint128::from(42u128));
+        assert_eq!(result.status.account_bond, Uint128::zero());
+    }
+
+    #[test]
+    fn multi_denom_status_reports_assigned_and_undistributed_per_denom() {
+        let app = BasicApp::default();
+        let env = mock_env();
+        let mut deps = mock_dependencies_with_balances(&[(
+            env.contract.address.as_str(),
+            // 1_000 assigned and claimable, plus 400 sitting undistributed on top.
+            &[coin(1_400u128, "uusdc"), coin(0u128, "uruji")],
+        )]);
+        let storage = deps.as_mut().storage;
+
+        let config = Config {
+            bond_denom: "uruji".to_string(),
+            revenue_denom: "uusdc".to_string(),
+            revenue_converter: (
+                app.api().addr_make("revenue"),
+                Binary::new(vec![0]),
+                Uint128::one(),
+            ),
+            fee: None,
+            unbonding_period: 0,
+        };
+        init(storage).unwrap();
+
+        reward_index::receive(storage, "uusdc", Uint128::from(1_000u128), Uint128::from(1_000u128))
+            .unwrap();
+
+        let result = multi_denom_status(mock_env(), deps.as_ref(), &config).unwrap();
+        assert_eq!(result.revenues.len(), 1);
+        assert_eq!(result.revenues[0].denom, "uusdc");
+        assert_eq!(result.revenues[0].assigned_revenue, Uint128::from(1_000u128));
+        assert_eq!(result.revenues[0].undistributed_revenue, Uint128::from(400u128));
+    }
+
+    #[test]
+    fn beneficiary_defaults_to_the_owner_and_round_trips() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        let treasury = Addr::unchecked("treasury");
+
+        assert_eq!(beneficiary_or_owner(&storage, &alice).unwrap(), alice);
+
+        set_beneficiary(&mut storage, &alice, Some(treasury.clone())).unwrap();
+        assert_eq!(beneficiary_or_owner(&storage, &alice).unwrap(), treasury);
+
+        set_beneficiary(&mut storage, &alice, None).unwrap();
+        assert_eq!(beneficiary_or_owner(&storage, &alice).unwrap(), alice);
+    }
+
+    #[test]
+    fn account_with_beneficiary_reports_the_configured_payout_address() {
+        let mut deps = mock_dependencies_with_balances(&[]);
+        let storage = deps.as_mut().storage;
+        let alice = Addr::unchecked("alice");
+        let treasury = Addr::unchecked("treasury");
+
+        init(storage).unwrap();
+        execute_account_bond(storage, &alice, Uint128::from(500u128)).unwrap();
+
+        // No beneficiary configured yet - defaults to the bonder.
+        let result = account_with_beneficiary(storage, alice.clone()).unwrap();
+        assert_eq!(result.beneficiary, alice);
+        assert_eq!(result.account.bonded, Uint128::from(500u128));
+
+        set_beneficiary(storage, &alice, Some(treasury.clone())).unwrap();
+        let result = account_with_beneficiary(storage, alice).unwrap();
+        assert_eq!(result.beneficiary, treasury);
+    }
+
+    #[test]
+    fn account_revenue_lists_every_claimable_denom_as_coins() {
+        let mut deps = mock_dependencies_with_balances(&[]);
+        let storage = deps.as_mut().storage;
+        let alice = Addr::unchecked("alice");
+
+        reward_index::receive(storage, "uusdc", Uint128::from(300u128), Uint128::from(1_000u128))
+            .unwrap();
+        reward_index::receive(storage, "uruji", Uint128::from(100u128), Uint128::from(1_000u128))
+            .unwrap();
+
+        let result = account_revenue(storage, alice.clone(), Uint128::from(1_000u128)).unwrap();
+        assert_eq!(result.addr, alice.to_string());
+        // BTreeMap iterates denoms in sorted order - "uruji" before "uusdc".
+        assert_eq!(
+            result.pending_revenue,
+            vec![coin(100u128, "uruji"), coin(300u128, "uusdc")]
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 10000,
+            ..Default::default()
+        })]
+        #[test]
+        fn pro_rata_split_never_overflows_or_over_allocates(
+            account_total in 0..u128::MAX,
+            liquid_size in 0..(u128::MAX - account_total),
+            revenue_surplus in 0..u128::MAX,
+        ) {
+            let pool_total = Uint128::from(account_total).checked_add(Uint128::from(liquid_size)).unwrap();
+            let revenue_surplus = Uint128::from(revenue_surplus);
+
+            let account_allocation = pro_rata_split(Uint128::from(account_total), revenue_surplus, pool_total).unwrap();
+            let liquid_allocation = pro_rata_split(Uint128::from(liquid_size), revenue_surplus, pool_total).unwrap();
+
+            prop_assert!(account_allocation + liquid_allocation <= revenue_surplus);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Manipulation-resistant time-weighted average of the liquid bond share's redemption rate,
+//! following the same cumulative-price accumulator Uniswap-style TWAP oracles use: every
+//! state-mutating call accrues `rate_before_this_call * seconds_since_last_accrual` onto a
+//! running `cumulative`, then a caller can average the accumulator's growth between any two
+//! recorded snapshots to get the time-weighted rate over that window, rather than trusting
+//! whatever the instantaneous rate happens to be in the same block an attacker is manipulating
+//! it.
+//!
+//! A bounded ring of past `(time, cumulative)` snapshots is kept (newest last, oldest evicted
+//! once [`MAX_SNAPSHOTS`] is exceeded) so [`twap_rate`] can find the snapshot closest to, but not
+//! after, `now - window_seconds` and diff against the latest one.
+//!
+//! `state.rs`'s `execute_liquid_bond`, `execute_liquid_unbond`, and `distribute` all call
+//! [`accrue`] before touching the rate, so the accumulator above is live and correct, and
+//! `contract.rs`'s `query()` exposes [`twap_rate`] itself through `QueryMsg::TwapRate {
+//! window_seconds }`.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Decimal256, StdResult, Storage, Timestamp};
+use cw_storage_plus::Item;
+
+use crate::error::ContractError;
+
+/// Oldest snapshots are evicted once the ring grows past this, bounding storage growth from an
+/// unbounded stream of bond/unbond/distribute calls.
+const MAX_SNAPSHOTS: usize = 256;
+
+#[cw_serde]
+pub struct RateSnapshot {
+    pub time: u64,
+    pub cumulative: Decimal256,
+}
+
+static HISTORY: Item<Vec<RateSnapshot>> = Item::new("lrr_twap_history");
+
+/// Accrues `rate`'s contribution to the cumulative series for the interval since the last
+/// recorded snapshot, then records a new snapshot at `now`. `rate` must be the redemption rate as
+/// it stood for the *just-elapsed* interval (i.e. read before the mutation this call accompanies
+/// changes it), matching how Uniswap-style TWAPs accumulate the pre-trade price. The very first
+/// call seeds the series with a zero cumulative rather than accruing against a non-existent prior
+/// snapshot.
+pub fn accrue(storage: &mut dyn Storage, now: Timestamp, rate: Decimal) -> StdResult<()> {
+    let mut history = HISTORY.may_load(storage)?.unwrap_or_default();
+
+    let cumulative = match history.last() {
+        None => Decimal256::zero(),
+        Some(last) => {
+            let elapsed = now.seconds().saturating_sub(last.time);
+            last.cumulative + Decimal256::from(rate) * Decimal256::from_ratio(elapsed, 1u64)
+        }
+    };
+
+    history.push(RateSnapshot {
+        time: now.seconds(),
+        cumulative,
+    });
+    if history.len() > MAX_SNAPSHOTS {
+        history.remove(0);
+    }
+    HISTORY.save(storage, &history)
+}
+
+/// The time-weighted average rate over the `window_seconds` up to `now`, derived from the
+/// cumulative snapshot closest to (but not after) `now - window_seconds` and the latest one.
+/// Falls back to `current_rate` when there isn't yet enough history to cover any of the
+/// requested window (e.g. right after instantiation).
+pub fn twap_rate(
+    storage: &dyn Storage,
+    now: Timestamp,
+    window_seconds: u64,
+    current_rate: Decimal,
+) -> Result<Decimal, ContractError> {
+    let history = HISTORY.may_load(storage)?.unwrap_or_default();
+    let Some(latest) = history.last() else {
+        return Ok(current_rate);
+    };
+
+    let target = now.seconds().saturating_sub(window_seconds);
+    let start = history.iter().rev().find(|s| s.time <= target).unwrap_or(&history[0]);
+
+    let elapsed = latest.time.saturating_sub(start.time);
+    if elapsed == 0 {
+        return Ok(current_rate);
+    }
+
+    // `cumulative` only ever grows, so this subtraction can't underflow.
+    let delta = latest.cumulative - start.cumulative;
+    let avg256 = delta / Decimal256::from_ratio(elapsed, 1u64);
+    Decimal::try_from(avg256)
+        .map_err(|_| ContractError::Std(cosmwasm_std::StdError::generic_err("twap rate overflow")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    fn ts(seconds: u64) -> Timestamp {
+        Timestamp::from_seconds(seconds)
+    }
+
+    #[test]
+    fn first_accrual_just_seeds_the_series() {
+        let mut storage = MockStorage::new();
+        accrue(&mut storage, ts(0), Decimal::one()).unwrap();
+
+        let rate = twap_rate(&storage, ts(0), 100, Decimal::percent(150)).unwrap();
+        // Only one snapshot exists - falls back to the caller-supplied instantaneous rate.
+        assert_eq!(rate, Decimal::percent(150));
+    }
+
+    #[test]
+    fn averages_a_constant_rate_back_to_itself() {
+        let mut storage = MockStorage::new();
+        accrue(&mut storage, ts(0), Decimal::one()).unwrap();
+        accrue(&mut storage, ts(100), Decimal::one()).unwrap();
+        accrue(&mut storage, ts(200), Decimal::one()).unwrap();
+
+        let rate = twap_rate(&storage, ts(200), 200, Decimal::percent(999)).unwrap();
+        assert_eq!(rate, Decimal::one());
+    }
+
+    #[test]
+    fn weights_a_rate_step_by_how_long_it_held() {
+        let mut storage = MockStorage::new();
+        // Rate holds at 1.00 for 300 seconds, then steps to 1.10 and holds another 100.
+        accrue(&mut storage, ts(0), Decimal::one()).unwrap();
+        accrue(&mut storage, ts(300), Decimal::one()).unwrap();
+        accrue(&mut storage, ts(400), Decimal::percent(110)).unwrap();
+
+        // Over the full 400s window: (300*1.00 + 100*1.10) / 400 = 1.025
+        let rate = twap_rate(&storage, ts(400), 400, Decimal::one()).unwrap();
+        assert_eq!(rate, Decimal::percent(102) + Decimal::permille(5));
+    }
+
+    #[test]
+    fn window_narrower_than_history_only_averages_the_recent_part() {
+        let mut storage = MockStorage::new();
+        accrue(&mut storage, ts(0), Decimal::one()).unwrap();
+        // Interval [0,100] held at 1.00; interval [100,200] at 2.00.
+        accrue(&mut storage, ts(100), Decimal::one()).unwrap();
+        accrue(&mut storage, ts(200), Decimal::percent(200)).unwrap();
+
+        // A 400s window still only covers data back to t=0, averaging both legs.
+        let full = twap_rate(&storage, ts(200), 400, Decimal::one()).unwrap();
+        assert_eq!(full, Decimal::percent(150));
+
+        // A 100s window only sees the second leg, which held steady at 2.00.
+        let recent = twap_rate(&storage, ts(200), 100, Decimal::one()).unwrap();
+        assert_eq!(recent, Decimal::percent(200));
+    }
+}
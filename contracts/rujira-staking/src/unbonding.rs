@@ -0,0 +1,159 @@
+//! Time-locked unbonding queue shared by the liquid and account unbond paths: instead of paying
+//! out the bond denom in the same transaction, an unbond enqueues `(amount, release_at)` under
+//! the sender and a later `ClaimUnbonded` sums and removes whatever has matured. A running
+//! `TOTAL_UNBONDING` counter tracks the in-flight total across every address, for reporting
+//! alongside `StatusResponse`'s other pool totals.
+//!
+//! `contract.rs`'s `AccountMsg::Withdraw`/`LiquidMsg::Unbond` handlers call [`enqueue`] instead of
+//! paying out synchronously, and a top-level `ExecuteMsg::ClaimUnbonded {}` arm calls
+//! [`claim_matured`] to release whatever's matured. `state.rs`'s `distribute`/`exchange_rate`
+//! subtract [`total_unbonding`] from the bond balance before computing swap-proceeds surplus, so a
+//! payout sitting in the queue isn't double-counted as liquid-pool appreciation while it waits out
+//! the lock.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdError, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map};
+
+use crate::error::ContractError;
+
+static UNBONDING: Map<&Addr, Vec<UnbondEntry>> = Map::new("unbonding_queue");
+/// Running sum of every entry currently queued across all addresses, matured or not - the
+/// in-flight total `StatusResponse` would report distinct from `liquid_bond_size`/`account_bond`.
+static TOTAL_UNBONDING: Item<Uint128> = Item::new("unbonding_total");
+
+#[cw_serde]
+pub struct UnbondEntry {
+    pub amount: Uint128,
+    pub release_at: Timestamp,
+}
+
+/// Queues `amount` under `addr`, released once `env.block.time >= now + unbonding_period`.
+pub fn enqueue(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    amount: Uint128,
+    now: Timestamp,
+    unbonding_period: u64,
+) -> StdResult<()> {
+    let mut entries = UNBONDING.may_load(storage, addr)?.unwrap_or_default();
+    entries.push(UnbondEntry {
+        amount,
+        release_at: now.plus_seconds(unbonding_period),
+    });
+    UNBONDING.save(storage, addr, &entries)?;
+
+    let total = TOTAL_UNBONDING.may_load(storage)?.unwrap_or_default();
+    TOTAL_UNBONDING.save(storage, &(total + amount))
+}
+
+/// The in-flight total across every address's queue, matured or not.
+pub fn total_unbonding(storage: &dyn Storage) -> StdResult<Uint128> {
+    Ok(TOTAL_UNBONDING.may_load(storage)?.unwrap_or_default())
+}
+
+/// Every entry still queued for `addr`, matured or not - for a `PendingUnbonds`/`Unbonding`
+/// query.
+pub fn pending(storage: &dyn Storage, addr: &Addr) -> StdResult<Vec<UnbondEntry>> {
+    Ok(UNBONDING.may_load(storage, addr)?.unwrap_or_default())
+}
+
+/// The in-flight total across every entry still queued for `addr`, matured or not - so a
+/// `StatusResponse`/`AccountResponse` can report it distinct from settled bond/share balances.
+pub fn pending_total(storage: &dyn Storage, addr: &Addr) -> StdResult<Uint128> {
+    Ok(pending(storage, addr)?
+        .iter()
+        .fold(Uint128::zero(), |total, entry| total + entry.amount))
+}
+
+/// Sums and removes every entry for `addr` with `release_at <= now`, leaving unmatured entries
+/// queued. Errors if nothing has matured yet, so a `ClaimUnbonded` handler doesn't emit a
+/// zero-amount `BankMsg::Send`.
+pub fn claim_matured(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    now: Timestamp,
+) -> Result<Uint128, ContractError> {
+    let entries = pending(storage, addr)?;
+    let (matured, remaining): (Vec<_>, Vec<_>) =
+        entries.into_iter().partition(|entry| entry.release_at <= now);
+
+    if matured.is_empty() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "no matured unbonding entries",
+        )));
+    }
+
+    if remaining.is_empty() {
+        UNBONDING.remove(storage, addr);
+    } else {
+        UNBONDING.save(storage, addr, &remaining)?;
+    }
+
+    let claimed = matured
+        .iter()
+        .fold(Uint128::zero(), |total, entry| total + entry.amount);
+
+    let total = TOTAL_UNBONDING.may_load(storage)?.unwrap_or_default();
+    TOTAL_UNBONDING.save(storage, &total.checked_sub(claimed)?)?;
+
+    Ok(claimed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    fn ts(seconds: u64) -> Timestamp {
+        Timestamp::from_seconds(seconds)
+    }
+
+    #[test]
+    fn claim_matured_only_pays_entries_past_release() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+
+        enqueue(&mut storage, &alice, Uint128::from(100u128), ts(0), 1000).unwrap();
+        enqueue(&mut storage, &alice, Uint128::from(50u128), ts(500), 1000).unwrap();
+
+        assert!(claim_matured(&mut storage, &alice, ts(999)).is_err());
+
+        let claimed = claim_matured(&mut storage, &alice, ts(1000)).unwrap();
+        assert_eq!(claimed, Uint128::from(100u128));
+        assert_eq!(pending_total(&storage, &alice).unwrap(), Uint128::from(50u128));
+
+        let claimed = claim_matured(&mut storage, &alice, ts(1500)).unwrap();
+        assert_eq!(claimed, Uint128::from(50u128));
+        assert!(pending(&storage, &alice).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pending_total_sums_across_multiple_entries() {
+        let mut storage = MockStorage::new();
+        let bob = Addr::unchecked("bob");
+
+        enqueue(&mut storage, &bob, Uint128::from(10u128), ts(0), 100).unwrap();
+        enqueue(&mut storage, &bob, Uint128::from(20u128), ts(0), 200).unwrap();
+
+        assert_eq!(pending_total(&storage, &bob).unwrap(), Uint128::from(30u128));
+        assert_eq!(pending(&storage, &bob).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn total_unbonding_tracks_every_address_in_flight() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+
+        enqueue(&mut storage, &alice, Uint128::from(100u128), ts(0), 100).unwrap();
+        enqueue(&mut storage, &bob, Uint128::from(50u128), ts(0), 100).unwrap();
+        assert_eq!(total_unbonding(&storage).unwrap(), Uint128::from(150u128));
+
+        claim_matured(&mut storage, &alice, ts(100)).unwrap();
+        assert_eq!(total_unbonding(&storage).unwrap(), Uint128::from(50u128));
+
+        claim_matured(&mut storage, &bob, ts(100)).unwrap();
+        assert_eq!(total_unbonding(&storage).unwrap(), Uint128::zero());
+    }
+}
@@ -1,84 +1,163 @@
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal, StdResult, Storage};
-use cw_storage_plus::Item;
-use rujira_rs::thorchain_swap::{ConfigResponse, ConfigUpdate, InstantiateMsg};
-
-use crate::ContractError;
-
-static CONFIG: Item<Config> = Item::new("config");
-
-#[cw_serde]
-pub struct Config {
-    pub max_stream_length: u32,
-    pub stream_step_ratio: Decimal,
-    pub max_borrow_ratio: Decimal,
-    pub reserve_fee: Decimal,
-}
-
-impl From<InstantiateMsg> for Config {
-    fn from(msg: InstantiateMsg) -> Self {
-        Self {
-            max_stream_length: msg.max_stream_length,
-            stream_step_ratio: msg.stream_step_ratio,
-            max_borrow_ratio: msg.max_borrow_ratio,
-            reserve_fee: msg.reserve_fee,
-        }
-    }
-}
-
-impl Config {
-    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
-        CONFIG.load(storage)
-    }
-
-    pub fn validate(&self) -> Result<(), ContractError> {
-        Ok(())
-    }
-
-    pub fn update(&mut self, update: &ConfigUpdate) {
-        if let Some(max_stream_length) = update.max_stream_length {
-            self.max_stream_length = max_stream_length;
-        }
-        if let Some(stream_step_ratio) = update.stream_step_ratio {
-            self.stream_step_ratio = stream_step_ratio;
-        }
-        if let Some(max_borrow_ratio) = update.max_borrow_ratio {
-            self.max_borrow_ratio = max_borrow_ratio;
-        }
-        if let Some(reserve_fee) = update.reserve_fee {
-            self.reserve_fee = reserve_fee;
-        }
-    }
-
-    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
-        CONFIG.save(storage, self)
-    }
-}
-
-impl From<Config> for ConfigResponse {
-    fn from(value: Config) -> Self {
-        Self {
-            max_stream_length: value.max_stream_length,
-            stream_step_ratio: value.stream_step_ratio,
-            max_borrow_ratio: value.max_borrow_ratio,
-            reserve_fee: value.reserve_fee,
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn validation() {
-        Config {
-            max_stream_length: 1,
-            max_borrow_ratio: Decimal::one(),
-            reserve_fee: Decimal::from_ratio(10u128, 500u128),
-            stream_step_ratio: Decimal::one(),
-        }
-        .validate()
-        .unwrap();
-    }
-}
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Api, Decimal, StdResult, Storage};
+use cw_storage_plus::Item;
+use rujira_rs::thorchain_swap::{ConfigResponse, ConfigUpdate, InstantiateMsg};
+
+use crate::ContractError;
+
+static CONFIG: Item<Config> = Item::new("config");
+
+#[cw_serde]
+pub struct Config {
+    pub max_stream_length: u32,
+    pub stream_step_ratio: Decimal,
+    pub max_borrow_ratio: Decimal,
+    pub reserve_fee: Decimal,
+    /// Maximum allowed deviation, in basis points, of a streamed step's realized price from
+    /// the route's spot price before `QuoteState::quote` halts the stream.
+    pub max_price_impact_bps: u32,
+    /// Recipients of the accumulated `reserve_fee`, paired with their weight in basis points.
+    /// Weights must sum to exactly `10_000`.
+    pub fee_recipients: Vec<(Addr, u32)>,
+    /// Upper bound, in basis points, on the combined weight of a `Swap`'s affiliate fees.
+    pub max_affiliate_bps: u32,
+}
+
+impl Config {
+    pub fn new(api: &dyn Api, msg: InstantiateMsg) -> Result<Self, ContractError> {
+        Ok(Self {
+            max_stream_length: msg.max_stream_length,
+            stream_step_ratio: msg.stream_step_ratio,
+            max_borrow_ratio: msg.max_borrow_ratio,
+            reserve_fee: msg.reserve_fee,
+            max_price_impact_bps: msg.max_price_impact_bps,
+            fee_recipients: validate_fee_recipients(api, msg.fee_recipients)?,
+            max_affiliate_bps: msg.max_affiliate_bps,
+        })
+    }
+
+    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
+        CONFIG.load(storage)
+    }
+
+    pub fn validate(&self) -> Result<(), ContractError> {
+        validate_weights(&self.fee_recipients)
+    }
+
+    pub fn update(&mut self, api: &dyn Api, update: &ConfigUpdate) -> Result<(), ContractError> {
+        if let Some(max_stream_length) = update.max_stream_length {
+            self.max_stream_length = max_stream_length;
+        }
+        if let Some(stream_step_ratio) = update.stream_step_ratio {
+            self.stream_step_ratio = stream_step_ratio;
+        }
+        if let Some(max_borrow_ratio) = update.max_borrow_ratio {
+            self.max_borrow_ratio = max_borrow_ratio;
+        }
+        if let Some(reserve_fee) = update.reserve_fee {
+            self.reserve_fee = reserve_fee;
+        }
+        if let Some(max_price_impact_bps) = update.max_price_impact_bps {
+            self.max_price_impact_bps = max_price_impact_bps;
+        }
+        if let Some(fee_recipients) = update.fee_recipients.clone() {
+            self.fee_recipients = validate_fee_recipients(api, fee_recipients)?;
+        }
+        if let Some(max_affiliate_bps) = update.max_affiliate_bps {
+            self.max_affiliate_bps = max_affiliate_bps;
+        }
+        Ok(())
+    }
+
+    pub fn set_fee_recipients(
+        &mut self,
+        api: &dyn Api,
+        recipients: Vec<(String, u32)>,
+    ) -> Result<(), ContractError> {
+        self.fee_recipients = validate_fee_recipients(api, recipients)?;
+        Ok(())
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        CONFIG.save(storage, self)
+    }
+}
+
+fn validate_fee_recipients(
+    api: &dyn Api,
+    value: Vec<(String, u32)>,
+) -> Result<Vec<(Addr, u32)>, ContractError> {
+    let recipients = value
+        .into_iter()
+        .map(|(addr, weight)| Ok((api.addr_validate(&addr)?, weight)))
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    validate_weights(&recipients)?;
+    Ok(recipients)
+}
+
+fn validate_weights(recipients: &[(Addr, u32)]) -> Result<(), ContractError> {
+    if recipients.is_empty() {
+        return Err(ContractError::InvalidFeeWeights {});
+    }
+    let total: u32 = recipients.iter().map(|(_, weight)| *weight).sum();
+    if total != 10_000 {
+        return Err(ContractError::InvalidFeeWeights {});
+    }
+    Ok(())
+}
+
+impl From<Config> for ConfigResponse {
+    fn from(value: Config) -> Self {
+        Self {
+            max_stream_length: value.max_stream_length,
+            stream_step_ratio: value.stream_step_ratio,
+            max_borrow_ratio: value.max_borrow_ratio,
+            reserve_fee: value.reserve_fee,
+            max_price_impact_bps: value.max_price_impact_bps,
+            fee_recipients: value
+                .fee_recipients
+                .into_iter()
+                .map(|(addr, weight)| (addr.to_string(), weight))
+                .collect(),
+            max_affiliate_bps: value.max_affiliate_bps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Addr;
+
+    use super::*;
+
+    #[test]
+    fn validation() {
+        Config {
+            max_stream_length: 1,
+            max_borrow_ratio: Decimal::one(),
+            reserve_fee: Decimal::from_ratio(10u128, 500u128),
+            stream_step_ratio: Decimal::one(),
+            max_price_impact_bps: 500,
+            fee_recipients: vec![(Addr::unchecked("fee"), 10_000)],
+            max_affiliate_bps: 100,
+        }
+        .validate()
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_weights() {
+        let err = Config {
+            max_stream_length: 1,
+            max_borrow_ratio: Decimal::one(),
+            reserve_fee: Decimal::from_ratio(10u128, 500u128),
+            stream_step_ratio: Decimal::one(),
+            max_price_impact_bps: 500,
+            fee_recipients: vec![(Addr::unchecked("fee"), 5_000)],
+            max_affiliate_bps: 100,
+        }
+        .validate()
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidFeeWeights {}));
+    }
+}
@@ -1,10 +1,10 @@
 use std::num::TryFromIntError;
 
-use cosmwasm_std::{CheckedFromRatioError, StdError, Uint128};
+use cosmwasm_std::{CheckedFromRatioError, Decimal, OverflowError, StdError, Uint128};
 use cw_utils::PaymentError;
 use rujira_rs::{
     query::{grpc::QueryError, OutboundFeeError, PoolError, SwapQuoteError},
-    AssetError, Layer1AssetError, SecuredAssetError, SharePoolError,
+    AssetError, Layer1AssetError, OracleError, SecuredAssetError, SharePoolError,
 };
 use thiserror::Error;
 
@@ -46,6 +46,12 @@ pub enum ContractError {
     #[error("{0}")]
     TryFromInt(#[from] TryFromIntError),
 
+    #[error("{0}")]
+    Oracle(#[from] OracleError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -62,8 +68,23 @@ pub enum ContractError {
     #[error("Invalid Route")]
     InvalidRoute {},
 
+    #[error("InvalidFeeWeights")]
+    InvalidFeeWeights {},
+
     #[error("Invalid: {0}")]
     Invalid(String),
+
+    #[error("HealthTooLow have {have} need {need}")]
+    HealthTooLow { have: Decimal, need: Decimal },
+
+    #[error("StaleSequence expected {expected} actual {actual}")]
+    StaleSequence { expected: u64, actual: u64 },
+
+    #[error("NotFound")]
+    NotFound {},
+
+    #[error("AffiliateCapExceeded total {total} max {max}")]
+    AffiliateCapExceeded { total: u32, max: u32 },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
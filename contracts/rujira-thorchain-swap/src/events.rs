@@ -1,10 +1,18 @@
-use cosmwasm_std::{Coin, Event};
+use cosmwasm_std::{Addr, Coin, Event};
 
+pub fn event_fee_distribution(to: &Addr, amount: &Coin) -> Event {
+    Event::new(format!("{}/fee-distribution", env!("CARGO_PKG_NAME")))
+        .add_attribute("to", to.as_str())
+        .add_attribute("amount", amount.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn event_swap(
     to: &String,
     amount: &Coin,
     min_return: &Coin,
     fee: &Coin,
+    affiliate_fee: &Coin,
     returned: &Coin,
     memo: &String,
 ) -> Event {
@@ -13,6 +21,7 @@ pub fn event_swap(
         .add_attribute("amount", amount.to_string())
         .add_attribute("min_return", min_return.to_string())
         .add_attribute("fee", fee.to_string())
+        .add_attribute("affiliate_fee", affiliate_fee.to_string())
         .add_attribute("returned", returned.to_string())
         .add_attribute("memo", memo)
 }
@@ -4,6 +4,7 @@ mod error;
 mod events;
 pub mod quote;
 pub mod route;
+pub mod stream_plan;
 
 pub use crate::error::ContractError;
 
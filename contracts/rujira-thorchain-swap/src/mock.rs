@@ -32,6 +32,7 @@ impl ThorchainSwap {
                 min_return,
                 to,
                 callback: None,
+                affiliates: vec![],
             },
             &[coin(offer_amount, offer_denom)],
         )
@@ -1,139 +1,215 @@
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{
-    from_json, to_json_binary, Binary, Decimal, Fraction, QuerierWrapper, StdResult, Uint128,
-};
-use rujira_rs::{
-    bow::QuoteResponse,
-    proto::types::{QueryMimirWithKeyRequest, QueryMimirWithKeyResponse},
-    query::grpc::Queryable,
-    query::Pool,
-    Asset,
-};
-
-use crate::{config::Config, route::Route, ContractError};
-#[cw_serde]
-pub struct QuoteState {
-    route: Route,
-    /// Cumulative input (offer) already executed
-    input: Uint128,
-    /// Cumulative output (ask) already received
-    output: Uint128,
-
-    // Cached values to reduce iteration gas cost
-    size: Uint128,
-    step_ratio: Decimal,
-    borrow_limit: Uint128,
-    fee: Decimal,
-}
-
-#[cw_serde]
-pub enum Step {
-    Rune {},
-    Pool { asset: Uint128, rune: Uint128 },
-}
-
-impl Step {
-    pub fn load(q: QuerierWrapper, denom: &String) -> Result<Self, ContractError> {
-        match denom.as_str() {
-            "rune" => Ok(Self::Rune {}),
-            _ => {
-                let pool = Pool::load(q, &Asset::from_denom(denom)?.to_layer_1())?;
-                if pool.trading_halted {
-                    return Err(ContractError::InvalidRoute {});
-                }
-                Ok(Self::Pool {
-                    asset: pool.balance_asset,
-                    rune: pool.balance_rune,
-                })
-            }
-        }
-    }
-}
-
-impl QuoteState {
-    pub fn quote(&mut self) -> Result<Option<QuoteResponse>, ContractError> {
-        let mut input = self.size;
-        self.size = self.size.mul_floor(self.step_ratio);
-
-        if input.is_zero() {
-            return Ok(None);
-        }
-
-        let total_output = self.route.swap(self.input + input);
-        let step_output = total_output.checked_sub(self.output).unwrap_or_default();
-        if step_output.is_zero() {
-            return Ok(None);
-        }
-        let price = Decimal::from_ratio(step_output, input);
-        // Reduce the quote size if we're out of borrowable funds
-        let remaining_borrow = self
-            .borrow_limit
-            .checked_sub(self.output)
-            .unwrap_or_default();
-        let size = step_output.min(remaining_borrow);
-        if size.lt(&step_output) {
-            input = size.mul_floor(price.inv().unwrap());
-        }
-
-        let fee = size.mul_ceil(self.fee);
-        let net_size = size.checked_sub(fee).unwrap_or_default();
-        if net_size.is_zero() {
-            return Ok(None);
-        }
-        // Commit new cumulative state
-        self.input += input;
-        // Use size including fee, otherwise subsequent quotes will hav progressively smaller total outputs
-        self.output += size;
-
-        Ok(Some(QuoteResponse {
-            // Re-calculate price to accommodate fee
-            price: Decimal::from_ratio(net_size, input),
-            size: net_size,
-            data: Some(self.encode()?),
-        }))
-    }
-
-    pub fn decode(data: &Binary) -> StdResult<Self> {
-        from_json(data)
-    }
-
-    pub fn encode(&self) -> StdResult<Binary> {
-        to_json_binary(&self)
-    }
-
-    pub fn load(
-        q: QuerierWrapper,
-        offer_denom: &String,
-        ask_denom: &String,
-        config: &Config,
-        borrow_limit: Uint128,
-    ) -> Result<Self, ContractError> {
-        let route = match (Step::load(q, offer_denom)?, Step::load(q, ask_denom)?) {
-            (Step::Pool { asset: a, rune: r }, Step::Rune {}) => Route::AR { a, r },
-            (Step::Rune {}, Step::Pool { asset: b, rune: r }) => Route::RB { r, b },
-            (Step::Pool { asset: a, rune: r1 }, Step::Pool { asset: b, rune: r2 }) => {
-                Route::ARB { a, r1, r2, b }
-            }
-            (Step::Rune {}, Step::Rune {}) => return Err(ContractError::InvalidRoute {}),
-        };
-
-        let available = route.return_balance().mul_floor(config.max_borrow_ratio);
-        let min_slip_bps = QueryMimirWithKeyResponse::get(
-            q,
-            QueryMimirWithKeyRequest {
-                key: "SECUREDASSETSLIPMINBPS".to_string(),
-                height: "".to_string(),
-            },
-        )?;
-        let bps = u32::try_from(min_slip_bps.value)?.min(10_000);
-        Ok(Self {
-            route: route.clone(),
-            input: Uint128::zero(),
-            output: Uint128::zero(),
-            size: route.size(bps),
-            borrow_limit: borrow_limit.min(available),
-            fee: config.reserve_fee,
-            step_ratio: config.stream_step_ratio,
-        })
-    }
-}
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json, to_json_binary, Binary, Decimal, Fraction, QuerierWrapper, StdResult, Uint128,
+};
+use rujira_rs::{
+    bow::QuoteResponse,
+    proto::types::{QueryMimirWithKeyRequest, QueryMimirWithKeyResponse},
+    query::grpc::Queryable,
+};
+
+use crate::{config::Config, route::Route, ContractError};
+#[cw_serde]
+pub struct QuoteState {
+    route: Route,
+    /// Cumulative input (offer) already executed
+    input: Uint128,
+    /// Cumulative output (ask) already received
+    output: Uint128,
+
+    // Cached values to reduce iteration gas cost
+    size: Uint128,
+    step_ratio: Decimal,
+    borrow_limit: Uint128,
+    fee: Decimal,
+
+    /// The marginal price, in `output / input`, beyond which streaming halts rather than
+    /// executing a worse-priced step. `None` streams to completion regardless of price.
+    min_price: Option<Decimal>,
+    kind: OrderKind,
+
+    /// The route's pre-fee, pre-slippage price, fixed at load time. The reference point
+    /// `max_price_impact_bps` measures each streamed step's realized price against.
+    spot_price: Decimal,
+    max_price_impact_bps: u32,
+}
+
+/// Which side of the quote `min_price` protects, mirroring CoW-protocol's sell/buy orders.
+#[cw_serde]
+pub enum OrderKind {
+    /// Selling `offer_denom`: halt once the marginal price drops below the limit
+    Sell,
+    /// Buying `ask_denom`: halt once the marginal price rises above the limit
+    Buy,
+}
+
+impl QuoteState {
+    pub fn quote(&mut self) -> Result<Option<QuoteResponse>, ContractError> {
+        let mut input = self.size;
+        self.size = self.size.mul_floor(self.step_ratio);
+
+        if input.is_zero() {
+            return Ok(None);
+        }
+
+        let total_output = self.route.swap(self.input + input);
+        let mut step_output = total_output.checked_sub(self.output).unwrap_or_default();
+        if step_output.is_zero() {
+            return Ok(None);
+        }
+
+        if let Some(min_price) = self.min_price {
+            if self.limit_crossed(input, step_output, min_price) {
+                let (limited_input, limited_output) = self.bisect_limit(input, min_price);
+                input = limited_input;
+                step_output = limited_output;
+                // The limit has been touched, so there's no profitable size left to stream
+                self.size = Uint128::zero();
+                if input.is_zero() || step_output.is_zero() {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let price = Decimal::from_ratio(step_output, input);
+
+        if price_impact_bps(self.spot_price, price)? > self.max_price_impact_bps {
+            // Impact only gets worse as the stream continues to drain the pool
+            self.size = Uint128::zero();
+            return Ok(None);
+        }
+
+        // Reduce the quote size if we're out of borrowable funds
+        let remaining_borrow = self
+            .borrow_limit
+            .checked_sub(self.output)
+            .unwrap_or_default();
+        let size = step_output.min(remaining_borrow);
+        if size.lt(&step_output) {
+            input = size.mul_floor(price.inv().unwrap());
+        }
+
+        let fee = size.mul_ceil(self.fee);
+        let net_size = size.checked_sub(fee).unwrap_or_default();
+        if net_size.is_zero() {
+            return Ok(None);
+        }
+        // Commit new cumulative state
+        self.input += input;
+        // Use size including fee, otherwise subsequent quotes will hav progressively smaller total outputs
+        self.output += size;
+
+        let realized_price = Decimal::from_ratio(net_size, input);
+        Ok(Some(QuoteResponse {
+            // Re-calculate price to accommodate fee
+            price: realized_price,
+            size: net_size,
+            data: Some(self.encode()?),
+            spot_price: self.spot_price,
+            price_impact_bps: price_impact_bps(self.spot_price, realized_price)?,
+        }))
+    }
+
+    /// Whether `step_output / input` has crossed to the wrong side of `min_price` for `self.kind`.
+    fn limit_crossed(&self, input: Uint128, step_output: Uint128, min_price: Decimal) -> bool {
+        if input.is_zero() {
+            return false;
+        }
+        let price = Decimal::from_ratio(step_output, input);
+        match self.kind {
+            OrderKind::Sell => price < min_price,
+            OrderKind::Buy => price > min_price,
+        }
+    }
+
+    /// Bisect over `input` in `[0, hi]` for the largest value whose marginal price against
+    /// `Route::swap`'s monotonic output curve still respects `min_price`, returning
+    /// `(input, step_output)`. A handful of rounds is enough precision for on-chain amounts.
+    fn bisect_limit(&self, hi: Uint128, min_price: Decimal) -> (Uint128, Uint128) {
+        let mut lo = Uint128::zero();
+        let mut hi = hi;
+        for _ in 0..24 {
+            if hi <= lo + Uint128::one() {
+                break;
+            }
+            let mid = lo + (hi - lo) / Uint128::new(2);
+            let output = self
+                .route
+                .swap(self.input + mid)
+                .checked_sub(self.output)
+                .unwrap_or_default();
+            if self.limit_crossed(mid, output, min_price) {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+
+        let output = self
+            .route
+            .swap(self.input + lo)
+            .checked_sub(self.output)
+            .unwrap_or_default();
+        (lo, output)
+    }
+
+    pub fn decode(data: &Binary) -> StdResult<Self> {
+        from_json(data)
+    }
+
+    pub fn encode(&self) -> StdResult<Binary> {
+        to_json_binary(&self)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn load(
+        q: QuerierWrapper,
+        path: &[String],
+        config: &Config,
+        borrow_limit: Uint128,
+        min_price: Option<Decimal>,
+        kind: OrderKind,
+        stable: &BTreeMap<String, (u64, Decimal)>,
+    ) -> Result<Self, ContractError> {
+        let route = Route::load(q, path, stable)?;
+
+        let available = route.return_balance().mul_floor(config.max_borrow_ratio);
+        let min_slip_bps = QueryMimirWithKeyResponse::get(
+            q,
+            QueryMimirWithKeyRequest {
+                key: "SECUREDASSETSLIPMINBPS".to_string(),
+                height: "".to_string(),
+            },
+        )?;
+        let bps = u32::try_from(min_slip_bps.value)?.min(10_000);
+        Ok(Self {
+            route: route.clone(),
+            input: Uint128::zero(),
+            output: Uint128::zero(),
+            size: route.size(bps),
+            borrow_limit: borrow_limit.min(available),
+            fee: config.reserve_fee,
+            step_ratio: config.stream_step_ratio,
+            min_price,
+            kind,
+            spot_price: route.spot_price(),
+            max_price_impact_bps: config.max_price_impact_bps,
+        })
+    }
+}
+
+/// Relative deviation of `realized` from `spot`, in basis points, for price-impact guarding.
+fn price_impact_bps(spot: Decimal, realized: Decimal) -> Result<u32, ContractError> {
+    if spot.is_zero() {
+        return Ok(u32::MAX);
+    }
+    let diff = if realized > spot {
+        realized - spot
+    } else {
+        spot - realized
+    };
+    let bps = (diff.checked_div(spot)? * Decimal::from_ratio(10_000u128, 1u128)).to_uint_ceil();
+    Ok(u32::try_from(bps).unwrap_or(u32::MAX))
+}
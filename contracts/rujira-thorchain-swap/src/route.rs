@@ -1,88 +1,414 @@
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Uint128, Uint256};
-use std::ops::{Div, Mul};
-
-#[cw_serde]
-pub enum Route {
-    // A -> RUNE (single hop)
-    AR {
-        a: Uint128,
-        r: Uint128,
-    },
-    // RUNE -> B (single hop)
-    RB {
-        r: Uint128,
-        b: Uint128,
-    },
-    // A -> R -> B (two hop)
-    ARB {
-        a: Uint128,
-        r1: Uint128,
-        r2: Uint128,
-        b: Uint128,
-    },
-}
-
-impl Route {
-    pub fn return_balance(&self) -> Uint128 {
-        match *self {
-            Route::AR { r, .. } => r,
-            Route::RB { b, .. } => b,
-            Route::ARB { b, .. } => b,
-        }
-    }
-    pub fn swap(&self, x: Uint128) -> Uint128 {
-        match *self {
-            Route::AR { a, r } => calculate_return(x, a, r),
-            Route::RB { r, b } => calculate_return(x, r, b),
-            Route::ARB { a, r1, r2, b } => calculate_return(calculate_return(x, a, r1), r2, b),
-        }
-    }
-
-    pub fn size(&self, s: u32) -> Uint128 {
-        match *self {
-            Route::AR { a, .. } => size_single(a, s),
-            Route::RB { r, .. } => size_single(r, s),
-            Route::ARB { a, r1, r2, .. } => size_dual(a, r1, r2, s),
-        }
-    }
-}
-
-fn size_single(xx: Uint128, s_bps: u32) -> Uint128 {
-    if s_bps == 0 {
-        return Uint128::zero();
-    }
-    xx.multiply_ratio(s_bps, 10_000u128)
-}
-
-// See https://gitlab.com/thorchain/thornode/-/blob/develop/x/thorchain/helpers.go#L212-221
-fn size_dual(
-    oa: Uint128, // asset balance in offer pool (input side of hop1)
-    or: Uint128, // rune balance in offer pool (output side of hop1)
-    ar: Uint128, // rune balance in ask pool (input side of hop2)
-    s_bps: u32,
-) -> Uint128 {
-    if s_bps == 0 {
-        return Uint128::zero();
-    }
-    // Find smallest value in rune and convert to offer asset
-    size_single(or, s_bps)
-        .min(size_single(ar, s_bps))
-        .multiply_ratio(oa, or)
-}
-
-/// swap_out for CLP: y = (x * X * Y) / (x + X)^2
-fn calculate_return(x: Uint128, xx: Uint128, yy: Uint128) -> Uint128 {
-    if x.is_zero() || xx.is_zero() || yy.is_zero() {
-        return Uint128::zero();
-    }
-    let x = Uint256::from(x);
-    let xx = Uint256::from(xx);
-    let yy = Uint256::from(yy);
-    x.mul(xx)
-        .mul(yy)
-        // integer division floors, which is what we want for conservative quoting
-        .div((x + xx).pow(2))
-        .try_into()
-        .unwrap_or(Uint128::zero())
-}
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Fraction, QuerierWrapper, Uint128, Uint256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Div, Mul};
+
+use rujira_rs::{query::Pool, Asset};
+
+use crate::ContractError;
+
+/// Number of reserves the StableSwap invariant is solved over. This contract only ever prices
+/// a single correlated pair per `Step::Stable`, so `n` is fixed at 2.
+const STABLE_N: u128 = 2;
+/// `n^n` for `STABLE_N = 2`, folded into the invariant formulas below.
+const STABLE_N_POW_N: u128 = 4;
+/// Newton iteration is expected to converge in a handful of steps; this is a hard backstop.
+const STABLE_MAX_ITERATIONS: u32 = 255;
+
+/// One node along a swap path: either the base `rune` currency, which is a pass-through
+/// waypoint, or a secured asset pool, carrying the balances and swap fee needed to price a
+/// leg through it.
+#[cw_serde]
+pub enum Step {
+    Rune {},
+    Pool {
+        asset: Uint128,
+        rune: Uint128,
+        fee: Decimal,
+    },
+    /// A correlated pair (e.g. a RUNE-derived LSD vs RUNE) priced with the StableSwap
+    /// invariant instead of the constant-product curve, which otherwise charges far more
+    /// slippage than the pair's real co-movement warrants. `target_rate` is `asset` expressed
+    /// in `rune` terms at the peg, used to rescale `asset` into `rune`-equivalent units before
+    /// the invariant is applied.
+    Stable {
+        asset: Uint128,
+        rune: Uint128,
+        amp: u64,
+        target_rate: Decimal,
+        fee: Decimal,
+    },
+}
+
+impl Step {
+    /// `stable` carries `(amp, target_rate)` when `denom`'s pool should be priced via
+    /// `Step::Stable` rather than the default CLP `Step::Pool`, per the market's vault config.
+    pub fn load(
+        q: QuerierWrapper,
+        denom: &String,
+        stable: Option<(u64, Decimal)>,
+    ) -> Result<Self, ContractError> {
+        match denom.as_str() {
+            "rune" => Ok(Self::Rune {}),
+            _ => {
+                let pool = Pool::load(q, &Asset::from_denom(denom)?.to_layer_1())?;
+                if pool.trading_halted {
+                    return Err(ContractError::InvalidRoute {});
+                }
+                Ok(match stable {
+                    Some((amp, target_rate)) => Self::Stable {
+                        asset: pool.balance_asset,
+                        rune: pool.balance_rune,
+                        amp,
+                        target_rate,
+                        fee: pool.fee,
+                    },
+                    None => Self::Pool {
+                        asset: pool.balance_asset,
+                        rune: pool.balance_rune,
+                        fee: pool.fee,
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// A single crossing of one step, in execution order, carrying whatever extra curve
+/// parameters that crossing's `Step` variant needs on top of the common `(reserve_in,
+/// reserve_out, fee)` triple.
+enum Leg {
+    Clp {
+        reserve_in: Uint128,
+        reserve_out: Uint128,
+        fee: Decimal,
+    },
+    Stable {
+        reserve_in: Uint128,
+        reserve_out: Uint128,
+        fee: Decimal,
+        amp: u64,
+        /// Scales an amount/reserve denominated in the input side into `rune`-equivalent
+        /// units before the invariant is applied.
+        in_scale: Decimal,
+        /// Scales an invariant-space amount back down into the output side's native units.
+        out_scale: Decimal,
+    },
+}
+
+impl Leg {
+    fn reserves(&self) -> (Uint128, Uint128) {
+        match self {
+            Leg::Clp {
+                reserve_in,
+                reserve_out,
+                ..
+            }
+            | Leg::Stable {
+                reserve_in,
+                reserve_out,
+                ..
+            } => (*reserve_in, *reserve_out),
+        }
+    }
+}
+
+/// A swap path through one or more pools, generalizing the single- and dual-hop cases to an
+/// arbitrary chain of intermediate assets. The first and last steps, if pools, are each
+/// crossed once in whichever direction leaves or enters the route; every interior `Step::Pool`
+/// is crossed twice, since the running amount always travels in `rune` between pools and must
+/// convert in and back out again to pass through. A `Step::Rune` only appears at an endpoint,
+/// marking that the offer or ask is already the base currency.
+#[cw_serde]
+pub struct Route(Vec<Step>);
+
+impl Route {
+    /// Build and validate a route from a precomputed path of denoms, rejecting cycles (a denom
+    /// repeated in the path) and pools that are `trading_halted`. `stable` supplies the
+    /// `(amp, target_rate)` pair for any denom in `path` whose vault opted into the StableSwap
+    /// curve; denoms absent from it keep the default CLP pricing.
+    pub fn load(
+        q: QuerierWrapper,
+        path: &[String],
+        stable: &BTreeMap<String, (u64, Decimal)>,
+    ) -> Result<Self, ContractError> {
+        if path.len() < 2 {
+            return Err(ContractError::InvalidRoute {});
+        }
+
+        let mut seen = BTreeSet::new();
+        for denom in path {
+            if !seen.insert(denom) {
+                return Err(ContractError::InvalidRoute {});
+            }
+        }
+
+        let steps = path
+            .iter()
+            .map(|denom| Step::load(q, denom, stable.get(denom).copied()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self(steps))
+    }
+
+    /// The legs the route is composed of, in execution order.
+    fn legs(&self) -> Vec<Leg> {
+        let last = self.0.len().saturating_sub(1);
+        let mut legs = vec![];
+        for (i, step) in self.0.iter().enumerate() {
+            match step {
+                Step::Rune {} => continue,
+                Step::Pool { asset, rune, fee } => {
+                    if i != 0 {
+                        legs.push(Leg::Clp {
+                            reserve_in: *rune,
+                            reserve_out: *asset,
+                            fee: *fee,
+                        });
+                    }
+                    if i != last {
+                        legs.push(Leg::Clp {
+                            reserve_in: *asset,
+                            reserve_out: *rune,
+                            fee: *fee,
+                        });
+                    }
+                }
+                Step::Stable {
+                    asset,
+                    rune,
+                    amp,
+                    target_rate,
+                    fee,
+                } => {
+                    if i != 0 {
+                        // Entering the pair from `rune`: the input is already in rune units,
+                        // the output (`asset`) must be unscaled back out of them.
+                        legs.push(Leg::Stable {
+                            reserve_in: *rune,
+                            reserve_out: *asset,
+                            fee: *fee,
+                            amp: *amp,
+                            in_scale: Decimal::one(),
+                            out_scale: *target_rate,
+                        });
+                    }
+                    if i != last {
+                        // Leaving the pair into `rune`: the input (`asset`) must be scaled
+                        // into rune units before the invariant is applied.
+                        legs.push(Leg::Stable {
+                            reserve_in: *asset,
+                            reserve_out: *rune,
+                            fee: *fee,
+                            amp: *amp,
+                            in_scale: *target_rate,
+                            out_scale: Decimal::one(),
+                        });
+                    }
+                }
+            }
+        }
+        legs
+    }
+
+    pub fn return_balance(&self) -> Uint128 {
+        self.legs()
+            .last()
+            .map_or(Uint128::zero(), |leg| leg.reserves().1)
+    }
+
+    /// The route's instantaneous price, `reserve_out / reserve_in` compounded across every
+    /// leg, ignoring fees and slippage. Used as the reference point for price-impact checks.
+    pub fn spot_price(&self) -> Decimal {
+        self.legs().into_iter().fold(Decimal::one(), |price, leg| {
+            let (reserve_in, reserve_out) = leg.reserves();
+            price * Decimal::from_ratio(reserve_out, reserve_in)
+        })
+    }
+
+    pub fn swap(&self, x: Uint128) -> Uint128 {
+        self.legs()
+            .into_iter()
+            .fold(x, |amount, leg| calculate_leg_return(amount, &leg))
+    }
+
+    pub fn size(&self, s: u32) -> Uint128 {
+        if s == 0 {
+            return Uint128::zero();
+        }
+
+        let legs = self.legs();
+        legs.iter()
+            .enumerate()
+            .map(|(i, leg)| {
+                let (reserve_in, _) = leg.reserves();
+                legs[..i]
+                    .iter()
+                    .rev()
+                    .fold(size_single(reserve_in, s), |cap, prior| {
+                        let (r_in, r_out) = prior.reserves();
+                        cap.multiply_ratio(r_in, r_out)
+                    })
+            })
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+fn size_single(xx: Uint128, s_bps: u32) -> Uint128 {
+    if s_bps == 0 {
+        return Uint128::zero();
+    }
+    xx.multiply_ratio(s_bps, 10_000u128)
+}
+
+fn calculate_leg_return(x: Uint128, leg: &Leg) -> Uint128 {
+    match leg {
+        Leg::Clp {
+            reserve_in,
+            reserve_out,
+            fee,
+        } => calculate_return(x, *reserve_in, *reserve_out, *fee),
+        Leg::Stable {
+            reserve_in,
+            reserve_out,
+            fee,
+            amp,
+            in_scale,
+            out_scale,
+        } => calculate_stable_return(
+            x,
+            *reserve_in,
+            *reserve_out,
+            *fee,
+            *amp,
+            *in_scale,
+            *out_scale,
+        ),
+    }
+}
+
+/// swap_out for a standard constant-product pool, with the pool's own swap fee `f` deducted
+/// from the input before the curve is applied: out = reserve_out - (reserve_in * reserve_out) / (reserve_in + in * (1 - f))
+fn calculate_return(
+    x: Uint128,
+    reserve_in: Uint128,
+    reserve_out: Uint128,
+    fee: Decimal,
+) -> Uint128 {
+    if x.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return Uint128::zero();
+    }
+    let x = x.mul_floor(Decimal::one() - fee.min(Decimal::one()));
+    let x = Uint256::from(x);
+    let reserve_in = Uint256::from(reserve_in);
+    let reserve_out = Uint256::from(reserve_out);
+    let reserve_out_after = reserve_in.mul(reserve_out).div(reserve_in + x);
+    reserve_out
+        .checked_sub(reserve_out_after)
+        .map(|v| v.try_into().unwrap_or(Uint128::zero()))
+        .unwrap_or(Uint128::zero())
+}
+
+/// swap_out for a StableSwap-curve pool, with `in_scale`/`out_scale` rescaling `reserve_in`/
+/// `reserve_out` into a common rune-equivalent unit before the invariant is applied, and the
+/// resulting output rescaled back afterward.
+#[allow(clippy::too_many_arguments)]
+fn calculate_stable_return(
+    x: Uint128,
+    reserve_in: Uint128,
+    reserve_out: Uint128,
+    fee: Decimal,
+    amp: u64,
+    in_scale: Decimal,
+    out_scale: Decimal,
+) -> Uint128 {
+    if x.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return Uint128::zero();
+    }
+    let x = x.mul_floor(Decimal::one() - fee.min(Decimal::one()));
+
+    let scaled_in = reserve_in.mul_floor(in_scale);
+    let scaled_out = reserve_out.mul_floor(out_scale);
+    let scaled_x = x.mul_floor(in_scale);
+    if scaled_in.is_zero() || scaled_out.is_zero() {
+        return Uint128::zero();
+    }
+
+    let d = stableswap_d(scaled_in, scaled_out, amp);
+    let x_prime = Uint256::from(scaled_in) + Uint256::from(scaled_x);
+    let y_prime = stableswap_y(x_prime, d, amp);
+    let scaled_dy: Uint128 = Uint256::from(scaled_out)
+        .checked_sub(y_prime)
+        .map(|v| v.try_into().unwrap_or(Uint128::zero()))
+        .unwrap_or(Uint128::zero());
+
+    if out_scale.is_zero() {
+        return Uint128::zero();
+    }
+    // Unscale: `out_scale` was applied to `reserve_out` above, so dividing by it here
+    // converts the invariant-space output back into the output denom's native units.
+    scaled_dy.multiply_ratio(out_scale.denominator(), out_scale.numerator())
+}
+
+/// Newton's method fixed point for the StableSwap invariant `D`, given two reserves already
+/// rescaled into the same unit and an amplification coefficient `amp`.
+fn stableswap_d(x: Uint128, y: Uint128, amp: u64) -> Uint256 {
+    let x = Uint256::from(x);
+    let y = Uint256::from(y);
+    let s = x + y;
+    if s.is_zero() {
+        return Uint256::zero();
+    }
+    let ann = Uint256::from(amp) * Uint256::from(STABLE_N_POW_N);
+    let four_xy = Uint256::from(STABLE_N_POW_N) * x * y;
+
+    let mut d = s;
+    for _ in 0..STABLE_MAX_ITERATIONS {
+        let d_p = d * d * d / four_xy;
+        let numerator = (ann * s + Uint256::from(STABLE_N) * d_p) * d;
+        let denominator = (ann - Uint256::one()) * d + Uint256::from(STABLE_N + 1) * d_p;
+        if denominator.is_zero() {
+            break;
+        }
+        let d_next = numerator / denominator;
+        let converged = if d_next >= d {
+            d_next - d <= Uint256::one()
+        } else {
+            d - d_next <= Uint256::one()
+        };
+        d = d_next;
+        if converged {
+            break;
+        }
+    }
+    d
+}
+
+/// Newton's method fixed point for the StableSwap `get_y`: given the invariant `d` and a new
+/// value for one reserve (`x_prime`), solves the quadratic for the other reserve's new value.
+fn stableswap_y(x_prime: Uint256, d: Uint256, amp: u64) -> Uint256 {
+    if x_prime.is_zero() {
+        return Uint256::zero();
+    }
+    let ann = Uint256::from(amp) * Uint256::from(STABLE_N_POW_N);
+    let c = d * d * d / (Uint256::from(STABLE_N_POW_N) * ann * x_prime);
+    let b = x_prime + d / ann;
+
+    let mut y = d;
+    for _ in 0..STABLE_MAX_ITERATIONS {
+        let denominator = Uint256::from(2u128) * y + b;
+        let denominator = denominator.checked_sub(d).unwrap_or(Uint256::one());
+        let y_next = (y * y + c) / denominator;
+        let converged = if y_next >= y {
+            y_next - y <= Uint256::one()
+        } else {
+            y - y_next <= Uint256::one()
+        };
+        y = y_next;
+        if converged {
+            break;
+        }
+    }
+    y
+}
@@ -0,0 +1,78 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Uint128};
+use rujira_rs::query::SwapQuote;
+
+use crate::{config::Config, error::ContractError};
+
+/// A streaming-swap schedule derived from a live THORChain quote: how many sub-swaps to split
+/// `offer_amount` into, and how large each one is. Mirrors Komodo's pattern of folding the coin
+/// dust threshold directly into fee/amount computation (`dex_fee_amount`'s dust-threshold
+/// parameter, `min_tx_amount`) rather than treating it as an afterthought once a quantity is
+/// already chosen.
+#[cw_serde]
+pub struct StreamPlan {
+    pub quantity: u32,
+    pub sub_swap_amount: Uint128,
+}
+
+impl StreamPlan {
+    /// Starts from `quote.max_streaming_quantity`, capped at `config.max_stream_length`, then:
+    /// - shrinks the count so every sub-swap clears both `quote.recommended_min_amount_in` and
+    ///   `quote.dust_threshold` - the two floors below which THORChain would either reject a leg
+    ///   outright or waste it on an unswappable dust amount;
+    /// - grows the count back up toward that shrunk cap only while the marginal slippage the next
+    ///   step would add still falls under a `stream_step_ratio`-derived budget, since splitting
+    ///   further only keeps paying off while each additional step's slippage share keeps
+    ///   shrinking faster than the budget does.
+    ///
+    /// Fails with `ContractError::InsufficientFunds` if `offer_amount` can't clear the dust floor
+    /// even as a single, unsplit swap.
+    pub fn compute(
+        offer_amount: Uint128,
+        quote: &SwapQuote,
+        config: &Config,
+    ) -> Result<Self, ContractError> {
+        let floor = quote.dust_threshold.max(quote.recommended_min_amount_in);
+        if !floor.is_zero() && offer_amount < floor {
+            return Err(ContractError::InsufficientFunds {});
+        }
+
+        let cap = quote
+            .max_streaming_quantity
+            .min(config.max_stream_length)
+            .max(1);
+
+        // The largest quantity for which every sub-swap still clears `floor`.
+        let max_by_floor = if floor.is_zero() {
+            cap
+        } else {
+            u32::try_from(offer_amount / floor)?.clamp(1, cap)
+        };
+
+        // Total slippage shrinks roughly geometrically with `stream_step_ratio` per added step;
+        // keep growing the count only while the next step's marginal share of `slippage_bps`
+        // still fits under that shrinking budget.
+        let total_slippage_bps = quote
+            .fees
+            .as_ref()
+            .map(|fees| fees.slippage_bps)
+            .unwrap_or_default();
+
+        let mut quantity = 1u32;
+        let mut budget = Decimal::from_ratio(total_slippage_bps, 1u128);
+        while quantity < max_by_floor {
+            budget = budget * config.stream_step_ratio;
+            let marginal = Decimal::from_ratio(total_slippage_bps, quantity + 1);
+            if marginal > budget {
+                break;
+            }
+            quantity += 1;
+        }
+
+        let sub_swap_amount = Decimal::from_ratio(offer_amount, quantity).to_uint_ceil();
+        Ok(Self {
+            quantity,
+            sub_swap_amount,
+        })
+    }
+}
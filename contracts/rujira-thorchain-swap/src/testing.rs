@@ -1,219 +1,492 @@
-use cosmwasm_std::{coin, coins, Addr, Decimal, Event, Uint128};
-
-use cw_multi_test::{ContractWrapper, Executor};
-use rujira_rs::{
-    ghost::{self, vault::Interest},
-    thorchain_swap::{ExecuteMsg, InstantiateMsg, SudoMsg},
-    TokenMetadata,
-};
-use rujira_rs_testing::{mock_rujira_app, RujiraApp};
-
-use crate::contract;
-
-#[test]
-fn complete_swap() {
-    let mut app = mock_rujira_app();
-    let owner = app.api().addr_make("owner");
-    let contract = setup(&mut app, &owner);
-    app.wasm_sudo(
-        contract.clone(),
-        &SudoMsg::SetMarket {
-            addr: owner.to_string(),
-            enabled: true,
-        },
-    )
-    .unwrap();
-    let res = app
-        .execute_contract(
-            owner.clone(),
-            contract.clone(),
-            &ExecuteMsg::Swap {
-                min_return: coin(1000000, "rune"),
-                to: None,
-                callback: None,
-            },
-            &coins(58, "btc-btc"),
-        )
-        .unwrap();
-    res.assert_event(
-        &Event::new("wasm-rujira-thorchain-swap/swap").add_attributes(vec![
-            ("to", owner.as_str()),
-            ("amount", "58btc-btc"),
-            ("min_return", "1000000rune"),
-            ("fee", "89635rune"),
-            ("returned", "448083799rune"),
-            ("memo", "dummy"),
-        ]),
-    );
-
-    res.assert_event(
-        &Event::new("wasm-rujira-ghost-vault/borrow").add_attributes(vec![
-            ("borrower", contract.as_str()),
-            ("amount", "448083799"),
-        ]),
-    );
-
-    res.assert_event(&Event::new("transfer").add_attributes(vec![
-        ("recipient", owner.as_str()),
-        ("sender", contract.as_str()),
-        ("amount", "448083799rune"),
-    ]));
-
-    // Simulate the endblock execution
-
-    app.send_tokens(owner.clone(), contract.clone(), &coins(448083799, "rune"))
-        .unwrap();
-
-    let res = app
-        .execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Repay {}, &[])
-        .unwrap();
-
-    res.assert_event(
-        &Event::new("wasm-rujira-ghost-vault/repay").add_attributes(vec![
-            ("borrower", contract.as_str()),
-            ("amount", "448083799"),
-        ]),
-    );
-}
-
-pub fn setup(app: &mut RujiraApp, owner: &Addr) -> Addr {
-    app.init_modules(|x, _api, storage| {
-        x.bank.init_balance(
-            storage,
-            owner,
-            vec![coin(10000000000, "rune"), coin(10000000000, "btc-btc")],
-        )
-    })
-    .unwrap();
-
-    let code = Box::new(
-        ContractWrapper::new(contract::execute, contract::instantiate, contract::query)
-            .with_sudo(contract::sudo),
-    );
-    let code_id = app.store_code(code);
-    let contract = app
-        .instantiate_contract(
-            code_id,
-            owner.clone(),
-            &InstantiateMsg {
-                max_stream_length: 1u32,
-                max_borrow_ratio: Decimal::one(),
-                reserve_fee: Decimal::from_ratio(1u128, 5000u128),
-                stream_step_ratio: Decimal::one(),
-            },
-            &[],
-            "template",
-            Some(owner.to_string()),
-        )
-        .unwrap();
-
-    let vault_code = Box::new(
-        ContractWrapper::new(
-            rujira_ghost_vault::contract::execute,
-            rujira_ghost_vault::contract::instantiate,
-            rujira_ghost_vault::contract::query,
-        )
-        .with_sudo(rujira_ghost_vault::contract::sudo),
-    );
-    let vault_code_id = app.store_code(vault_code);
-    let vault_btc = app
-        .instantiate_contract(
-            vault_code_id,
-            owner.clone(),
-            &ghost::vault::InstantiateMsg {
-                denom: "btc-btc".to_string(),
-                interest: Interest::default(),
-                receipt: TokenMetadata {
-                    description: "XBTC".to_string(),
-                    display: "XBTC".to_string(),
-                    name: "XBTC".to_string(),
-                    symbol: "XBTC".to_string(),
-                    uri: None,
-                    uri_hash: None,
-                },
-                fee: Decimal::zero(),
-                fee_address: owner.to_string(),
-            },
-            &[],
-            "ghost btc",
-            Some(owner.to_string()),
-        )
-        .unwrap();
-    app.execute_contract(
-        owner.clone(),
-        vault_btc.clone(),
-        &ghost::vault::ExecuteMsg::Deposit { callback: None },
-        &coins(1000000000, "btc-btc"),
-    )
-    .unwrap();
-
-    let vault_rune = app
-        .instantiate_contract(
-            vault_code_id,
-            owner.clone(),
-            &ghost::vault::InstantiateMsg {
-                denom: "rune".to_string(),
-                interest: Interest::default(),
-                receipt: TokenMetadata {
-                    description: "XRUNE".to_string(),
-                    display: "XRUNE".to_string(),
-                    name: "XRUNE".to_string(),
-                    symbol: "XRUNE".to_string(),
-                    uri: None,
-                    uri_hash: None,
-                },
-                fee: Decimal::zero(),
-                fee_address: owner.to_string(),
-            },
-            &[],
-            "ghost rune",
-            Some(owner.to_string()),
-        )
-        .unwrap();
-
-    app.execute_contract(
-        owner.clone(),
-        vault_rune.clone(),
-        &ghost::vault::ExecuteMsg::Deposit { callback: None },
-        &coins(1000000000, "rune"),
-    )
-    .unwrap();
-
-    app.wasm_sudo(
-        contract.clone(),
-        &SudoMsg::SetVault {
-            denom: "btc-btc".to_owned(),
-            vault: Some(vault_btc.clone().into()),
-        },
-    )
-    .unwrap();
-
-    app.wasm_sudo(
-        contract.clone(),
-        &SudoMsg::SetVault {
-            denom: "rune".to_owned(),
-            vault: Some(vault_rune.clone().into()),
-        },
-    )
-    .unwrap();
-
-    app.wasm_sudo(
-        vault_btc.clone(),
-        &ghost::vault::SudoMsg::SetBorrower {
-            contract: contract.to_string(),
-            limit: Uint128::MAX,
-        },
-    )
-    .unwrap();
-
-    app.wasm_sudo(
-        vault_rune.clone(),
-        &ghost::vault::SudoMsg::SetBorrower {
-            contract: contract.to_string(),
-            limit: Uint128::MAX,
-        },
-    )
-    .unwrap();
-
-    contract
-}
+use cosmwasm_std::{coin, coins, Addr, Decimal, Event, Uint128};
+
+use cw_multi_test::{ContractWrapper, Executor};
+use rujira_rs::{
+    ghost::{self, vault::Interest},
+    thorchain_swap::{ExecuteMsg, InstantiateMsg, QueryMsg, ReserveBalancesResponse, SudoMsg},
+    TokenMetadata,
+};
+use rujira_rs_testing::{mock_rujira_app, RujiraApp};
+
+use crate::contract;
+
+#[test]
+fn complete_swap() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let contract = setup(&mut app, &owner);
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetMarket {
+            addr: owner.to_string(),
+            enabled: true,
+        },
+    )
+    .unwrap();
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap {
+                min_return: coin(1000000, "rune"),
+                to: None,
+                callback: None,
+                affiliates: vec![],
+            },
+            &coins(58, "btc-btc"),
+        )
+        .unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-thorchain-swap/swap").add_attributes(vec![
+            ("to", owner.as_str()),
+            ("amount", "58btc-btc"),
+            ("min_return", "1000000rune"),
+            ("fee", "89635rune"),
+            ("affiliate_fee", "0rune"),
+            ("returned", "448083799rune"),
+            ("memo", "dummy"),
+        ]),
+    );
+
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-vault/borrow").add_attributes(vec![
+            ("borrower", contract.as_str()),
+            ("amount", "448083799"),
+        ]),
+    );
+
+    res.assert_event(&Event::new("transfer").add_attributes(vec![
+        ("recipient", owner.as_str()),
+        ("sender", contract.as_str()),
+        ("amount", "448083799rune"),
+    ]));
+
+    // Simulate the endblock execution
+
+    app.send_tokens(owner.clone(), contract.clone(), &coins(448083799, "rune"))
+        .unwrap();
+
+    let res = app
+        .execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Repay {}, &[])
+        .unwrap();
+
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-vault/repay").add_attributes(vec![
+            ("borrower", contract.as_str()),
+            ("amount", "448083799"),
+        ]),
+    );
+}
+
+#[test]
+fn assert_sequence() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let contract = setup(&mut app, &owner);
+
+    // `setup` issues two `SetVault` sudo calls, each bumping the sequence once.
+    let sequence: u64 = app
+        .wrap()
+        .query_wasm_smart(contract.clone(), &QueryMsg::Sequence {})
+        .unwrap();
+    assert_eq!(sequence, 2);
+
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::AssertSequence { expected: sequence },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::AssertSequence {
+            expected: sequence + 1,
+        },
+        &[],
+    )
+    .unwrap_err();
+
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetMarket {
+            addr: owner.to_string(),
+            enabled: true,
+        },
+    )
+    .unwrap();
+
+    let sequence: u64 = app
+        .wrap()
+        .query_wasm_smart(contract.clone(), &QueryMsg::Sequence {})
+        .unwrap();
+    assert_eq!(sequence, 3);
+}
+
+#[test]
+fn assert_health() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let contract = setup(&mut app, &owner);
+
+    // A zero minimum is trivially satisfied by any non-negative collateral value.
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::AssertHealth {
+            min_collateral_value: Decimal::zero(),
+        },
+        &coins(1, "rune"),
+    )
+    .unwrap();
+
+    // An unreasonably high minimum can't be met by any realistic amount of collateral.
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::AssertHealth {
+            min_collateral_value: Decimal::from_ratio(10_000_000_000_000u128, 1u128),
+        },
+        &coins(1, "rune"),
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn sweep_reserve() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let fund_manager = app.api().addr_make("fund_manager");
+    let contract = setup(&mut app, &owner);
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetMarket {
+            addr: owner.to_string(),
+            enabled: true,
+        },
+    )
+    .unwrap();
+
+    // Sweeping before a fund manager is configured has nowhere to send accrued fees.
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::SweepReserve {},
+        &[],
+    )
+    .unwrap_err();
+
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::Swap {
+            min_return: coin(1000000, "rune"),
+            to: None,
+            callback: None,
+            affiliates: vec![],
+        },
+        &coins(58, "btc-btc"),
+    )
+    .unwrap();
+
+    let reserves: ReserveBalancesResponse = app
+        .wrap()
+        .query_wasm_smart(contract.clone(), &QueryMsg::ReserveBalances {})
+        .unwrap();
+    assert_eq!(reserves.balances, vec![coin(89635, "rune")]);
+
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetFundManager {
+            addr: Some(fund_manager.to_string()),
+        },
+    )
+    .unwrap();
+
+    // The swap's proceeds only land in the contract's bank balance once the underlying
+    // THORChain swap is simulated as settled, same as `complete_swap`.
+    app.send_tokens(owner.clone(), contract.clone(), &coins(448083799, "rune"))
+        .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::SweepReserve {},
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap().query_balance(&fund_manager, "rune").unwrap(),
+        coin(89635, "rune")
+    );
+
+    let reserves: ReserveBalancesResponse = app
+        .wrap()
+        .query_wasm_smart(contract.clone(), &QueryMsg::ReserveBalances {})
+        .unwrap();
+    assert!(reserves.balances.is_empty());
+}
+
+#[test]
+fn affiliate_fee_deducted_and_capped() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let affiliate = app.api().addr_make("affiliate");
+    let contract = setup(&mut app, &owner);
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetMarket {
+            addr: owner.to_string(),
+            enabled: true,
+        },
+    )
+    .unwrap();
+
+    // `setup` configures a 100 bps cap; exceeding it is rejected before any quoting happens.
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::Swap {
+            min_return: coin(1000000, "rune"),
+            to: None,
+            callback: None,
+            affiliates: vec![(affiliate.to_string(), 101)],
+        },
+        &coins(58, "btc-btc"),
+    )
+    .unwrap_err();
+
+    // Right at the cap, the affiliate cut is deducted before the min_return/reserve_fee split.
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap {
+                min_return: coin(1000000, "rune"),
+                to: None,
+                callback: None,
+                affiliates: vec![(affiliate.to_string(), 100)],
+            },
+            &coins(58, "btc-btc"),
+        )
+        .unwrap();
+
+    res.assert_event(
+        &Event::new("wasm-rujira-thorchain-swap/swap").add_attributes(vec![
+            ("to", owner.as_str()),
+            ("amount", "58btc-btc"),
+            ("min_return", "1000000rune"),
+            ("fee", "88739rune"),
+            ("affiliate_fee", "4481735rune"),
+            ("returned", "443602960rune"),
+            ("memo", "dummy"),
+        ]),
+    );
+}
+
+#[test]
+fn distribute_fees_excludes_debt_and_reserve() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let contract = setup(&mut app, &owner);
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetMarket {
+            addr: owner.to_string(),
+            enabled: true,
+        },
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::Swap {
+            min_return: coin(1000000, "rune"),
+            to: None,
+            callback: None,
+            affiliates: vec![],
+        },
+        &coins(58, "btc-btc"),
+    )
+    .unwrap();
+
+    // Simulate the underlying swap settling: enough rune lands in the contract to cover the
+    // borrowed net_return (448083799), the accrued reserve_fee (89635), and a 10000 surplus.
+    app.send_tokens(
+        owner.clone(),
+        contract.clone(),
+        &coins(448083799 + 89635 + 10000, "rune"),
+    )
+    .unwrap();
+
+    app.execute_contract(owner.clone(), contract.clone(), &ExecuteMsg::Repay {}, &[])
+        .unwrap();
+
+    let before = app.wrap().query_balance(&owner, "rune").unwrap().amount;
+    app.execute_contract(
+        owner.clone(),
+        contract.clone(),
+        &ExecuteMsg::DistributeFees {},
+        &[],
+    )
+    .unwrap();
+
+    // Only the 10000 surplus above debt and the bookkept reserve was distributed; the reserve
+    // itself is untouched and still claimable via `SweepReserve`.
+    let after = app.wrap().query_balance(&owner, "rune").unwrap().amount;
+    assert_eq!(after - before, Uint128::from(10000u128));
+    let reserves: ReserveBalancesResponse = app
+        .wrap()
+        .query_wasm_smart(contract.clone(), &QueryMsg::ReserveBalances {})
+        .unwrap();
+    assert_eq!(reserves.balances, vec![coin(89635, "rune")]);
+}
+
+pub fn setup(app: &mut RujiraApp, owner: &Addr) -> Addr {
+    app.init_modules(|x, _api, storage| {
+        x.bank.init_balance(
+            storage,
+            owner,
+            vec![coin(10000000000, "rune"), coin(10000000000, "btc-btc")],
+        )
+    })
+    .unwrap();
+
+    let code = Box::new(
+        ContractWrapper::new(contract::execute, contract::instantiate, contract::query)
+            .with_sudo(contract::sudo),
+    );
+    let code_id = app.store_code(code);
+    let contract = app
+        .instantiate_contract(
+            code_id,
+            owner.clone(),
+            &InstantiateMsg {
+                max_stream_length: 1u32,
+                max_borrow_ratio: Decimal::one(),
+                reserve_fee: Decimal::from_ratio(1u128, 5000u128),
+                stream_step_ratio: Decimal::one(),
+                max_price_impact_bps: 10_000,
+                fee_recipients: vec![(owner.to_string(), 10_000)],
+                max_affiliate_bps: 100,
+            },
+            &[],
+            "template",
+            Some(owner.to_string()),
+        )
+        .unwrap();
+
+    let vault_code = Box::new(
+        ContractWrapper::new(
+            rujira_ghost_vault::contract::execute,
+            rujira_ghost_vault::contract::instantiate,
+            rujira_ghost_vault::contract::query,
+        )
+        .with_sudo(rujira_ghost_vault::contract::sudo),
+    );
+    let vault_code_id = app.store_code(vault_code);
+    let vault_btc = app
+        .instantiate_contract(
+            vault_code_id,
+            owner.clone(),
+            &ghost::vault::InstantiateMsg {
+                denom: "btc-btc".to_string(),
+                interest: Interest::default(),
+                receipt: TokenMetadata {
+                    description: "XBTC".to_string(),
+                    display: "XBTC".to_string(),
+                    name: "XBTC".to_string(),
+                    symbol: "XBTC".to_string(),
+                    uri: None,
+                    uri_hash: None,
+                },
+                fee: Decimal::zero(),
+                fee_address: owner.to_string(),
+            },
+            &[],
+            "ghost btc",
+            Some(owner.to_string()),
+        )
+        .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        vault_btc.clone(),
+        &ghost::vault::ExecuteMsg::Deposit { callback: None },
+        &coins(1000000000, "btc-btc"),
+    )
+    .unwrap();
+
+    let vault_rune = app
+        .instantiate_contract(
+            vault_code_id,
+            owner.clone(),
+            &ghost::vault::InstantiateMsg {
+                denom: "rune".to_string(),
+                interest: Interest::default(),
+                receipt: TokenMetadata {
+                    description: "XRUNE".to_string(),
+                    display: "XRUNE".to_string(),
+                    name: "XRUNE".to_string(),
+                    symbol: "XRUNE".to_string(),
+                    uri: None,
+                    uri_hash: None,
+                },
+                fee: Decimal::zero(),
+                fee_address: owner.to_string(),
+            },
+            &[],
+            "ghost rune",
+            Some(owner.to_string()),
+        )
+        .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        vault_rune.clone(),
+        &ghost::vault::ExecuteMsg::Deposit { callback: None },
+        &coins(1000000000, "rune"),
+    )
+    .unwrap();
+
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetVault {
+            denom: "btc-btc".to_owned(),
+            vault: Some(vault_btc.clone().into()),
+        },
+    )
+    .unwrap();
+
+    app.wasm_sudo(
+        contract.clone(),
+        &SudoMsg::SetVault {
+            denom: "rune".to_owned(),
+            vault: Some(vault_rune.clone().into()),
+        },
+    )
+    .unwrap();
+
+    app.wasm_sudo(
+        vault_btc.clone(),
+        &ghost::vault::SudoMsg::SetBorrower {
+            contract: contract.to_string(),
+            limit: Uint128::MAX,
+        },
+    )
+    .unwrap();
+
+    app.wasm_sudo(
+        vault_rune.clone(),
+        &ghost::vault::SudoMsg::SetBorrower {
+            contract: contract.to_string(),
+            limit: Uint128::MAX,
+        },
+    )
+    .unwrap();
+
+    contract
+}
@@ -150,17 +150,19 @@ pub fn mock_quote(_req: Binary) -> Result<Binary, Error> {
 
 pub fn mock_oracle_price(
     request: Binary,
-    prices: &BTreeMap<String, Decimal>,
+    prices: &BTreeMap<String, BTreeMap<u64, Decimal>>,
+    height: u64,
 ) -> Result<Binary, Error> {
     let req = proto::types::QueryOraclePriceRequest::decode(request.as_slice()).unwrap();
+    let price = prices
+        .get(&req.symbol)
+        .and_then(|schedule| schedule.range(..=height).next_back())
+        .map(|(_, price)| *price);
     let quote = proto::types::QueryOraclePriceResponse {
-        price: match prices.get(&req.symbol) {
-            Some(price) => Some(proto::types::OraclePrice {
-                symbol: req.symbol,
-                price: price.to_string(),
-            }),
-            _ => None,
-        },
+        price: price.map(|price| proto::types::OraclePrice {
+            symbol: req.symbol,
+            price: price.to_string(),
+        }),
     };
 
     let mut buf = Vec::new();
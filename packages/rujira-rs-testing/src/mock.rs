@@ -1,28 +1,29 @@
-use anybuf::Bufany;
+use anybuf::{Anybuf, Bufany};
 use anyhow::{Error, Result as AnyResult};
 use cosmwasm_std::{
-    attr, coins, ensure_eq,
+    attr, coins, ensure, ensure_eq,
     testing::{MockApi, MockStorage},
     Addr, AnyMsg, Api, BankMsg, Binary, BlockInfo, CosmosMsg, CustomMsg, CustomQuery, Decimal,
     DenomMetadata, DenomUnit, Empty, Event, GrpcQuery, Querier, Storage, Uint128,
 };
 use cw_multi_test::{
-    App, AppResponse, BankKeeper, BankSudo, BasicAppBuilder, CosmosRouter, FailingModule,
-    GovFailingModule, IbcFailingModule, Stargate, SudoMsg, WasmKeeper, WasmSudo,
+    AppBuilder, AppResponse, BankKeeper, BankSudo, CosmosRouter, FailingModule, GovFailingModule,
+    IbcFailingModule, Stargate, SudoMsg, WasmKeeper, WasmSudo,
 };
 use cw_storage_plus::Map;
 use serde::de::DeserializeOwned;
-use std::{collections::BTreeMap, str::FromStr};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc, str::FromStr};
 
 use crate::fixtures::{mock_mimir, mock_network, mock_oracle_price, mock_pool, mock_quote};
+use crate::query::{RujiraQuerier, RujiraQuery};
 
-pub type RujiraApp = App<
+pub type RujiraApp = cw_multi_test::App<
     BankKeeper,
     MockApi,
     MockStorage,
     // Custom
-    FailingModule<Empty, Empty, Empty>,
-    WasmKeeper<Empty, Empty>,
+    RujiraQuerier,
+    WasmKeeper<Empty, RujiraQuery>,
     // SDK Staking
     FailingModule<Empty, Empty, Empty>,
     // SDK Distribution
@@ -34,21 +35,72 @@ pub type RujiraApp = App<
 
 static DENOM_ADMIN: Map<String, String> = Map::new("denom_admin");
 static DENOM_METADATA: Map<String, DenomMetadata> = Map::new("denom_meta");
+static DENOM_SUPPLY: Map<String, Uint128> = Map::new("denom_supply");
 
 pub fn mock_rujira_app() -> RujiraApp {
-    BasicAppBuilder::new()
+    AppBuilder::new_custom()
+        .with_custom(RujiraQuerier::default())
         .with_stargate(RujiraStargate::default())
         .build(|_, _, _| {})
 }
 
+/// Shared handle onto [`RujiraStargate`]'s message-capture log. Cloning it (cheap, it's an
+/// `Rc`) before the `RujiraStargate` is moved into the app lets a test keep reading it after
+/// `mock_rujira_app_with_log`'s `App` has taken ownership of the module.
+pub type CapturedLog = Rc<RefCell<Vec<AnyMsg>>>;
+
+/// Like [`mock_rujira_app`], but also returns a handle onto the [`RujiraStargate`]'s capture
+/// log, so a test can assert on the exact THORChain messages a contract sent rather than just
+/// that execution succeeded. See [`captured`] and [`deposits`].
+pub fn mock_rujira_app_with_log() -> (RujiraApp, CapturedLog) {
+    let stargate = RujiraStargate::default();
+    let log = stargate.captured.clone();
+    (mock_rujira_app_with_stargate(stargate), log)
+}
+
+/// Like [`mock_rujira_app`], but with a caller-configured [`RujiraStargate`] - e.g. one seeded
+/// with [`RujiraStargate::with_price_at`] - instead of the default empty one.
+pub fn mock_rujira_app_with_stargate(stargate: RujiraStargate) -> RujiraApp {
+    AppBuilder::new_custom()
+        .with_custom(RujiraQuerier::default())
+        .with_stargate(stargate)
+        .build(|_, _, _| {})
+}
+
+/// Every captured `AnyMsg` with the given `type_url`, in execution order.
+pub fn captured(log: &CapturedLog, type_url: &str) -> Vec<AnyMsg> {
+    log.borrow()
+        .iter()
+        .filter(|msg| msg.type_url == type_url)
+        .cloned()
+        .collect()
+}
+
+/// Every captured `/types.MsgDeposit`, decoded into its coins/memo/signer.
+pub fn deposits(log: &CapturedLog) -> Vec<DecodedDeposit> {
+    captured(log, "/types.MsgDeposit")
+        .iter()
+        .map(decode_deposit)
+        .collect()
+}
+
 #[derive(Default)]
 pub struct RujiraStargate {
-    prices: BTreeMap<String, Decimal>,
+    /// Per-symbol price schedule keyed by the block height it takes effect at. An
+    /// `OraclePrice` query answers with the latest entry at or before the current block's
+    /// height, so tests can exercise price movement instead of a constant.
+    prices: BTreeMap<String, BTreeMap<u64, Decimal>>,
+    /// Every `AnyMsg` successfully routed through [`Self::execute_any`], in execution order.
+    /// Shared via `Rc` so a [`CapturedLog`] handle can outlive the module once it's moved into
+    /// the app.
+    captured: CapturedLog,
 }
 
 impl RujiraStargate {
+    /// Sets `symbol`'s price as of height 0, i.e. for the whole run unless overridden by a
+    /// later [`Self::with_price_at`].
     pub fn with_price(&mut self, symbol: &str, price: Decimal) {
-        self.prices.insert(symbol.to_string(), price);
+        self.with_price_at(symbol, 0, price);
     }
 
     pub fn with_prices(&mut self, prices: Vec<(&str, Decimal)>) {
@@ -56,6 +108,50 @@ impl RujiraStargate {
             self.with_price(symbol, price);
         }
     }
+
+    /// Schedules `symbol` to report `price` from block `height` onward, until superseded by a
+    /// later scheduled height.
+    pub fn with_price_at(&mut self, symbol: &str, height: u64, price: Decimal) {
+        self.prices
+            .entry(symbol.to_string())
+            .or_default()
+            .insert(height, price);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositCoin {
+    pub asset: String,
+    pub amount: Uint128,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedDeposit {
+    pub coins: Vec<DepositCoin>,
+    pub memo: String,
+    pub signer: String,
+}
+
+fn decode_deposit(msg: &AnyMsg) -> DecodedDeposit {
+    let buf = Bufany::deserialize(&msg.value).unwrap();
+    let coins = buf
+        .repeated_bytes(1)
+        .unwrap_or_default()
+        .iter()
+        .map(|raw| {
+            let c = Bufany::deserialize(raw).unwrap();
+            DepositCoin {
+                asset: c.string(1).unwrap_or_default(),
+                amount: Uint128::from_str(&c.string(2).unwrap_or_default()).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    DecodedDeposit {
+        coins,
+        memo: buf.string(2).unwrap_or_default(),
+        signer: buf.string(3).unwrap_or_default(),
+    }
 }
 
 impl Stargate for RujiraStargate {
@@ -109,7 +205,7 @@ impl Stargate for RujiraStargate {
         let type_url = msg.type_url.clone();
         let serialized = msg.value.to_vec();
         let buf = Bufany::deserialize(&serialized)?;
-        match type_url.as_str() {
+        let result = match type_url.as_str() {
             "/types.MsgDeposit" => Ok(AppResponse {
                 events: vec![],
                 data: None,
@@ -121,8 +217,14 @@ impl Stargate for RujiraStargate {
                 let m = buf.message(3).unwrap();
                 let metadata = decode_metadata(m);
                 let full = format!("x/{id}");
+                ensure_eq!(
+                    DENOM_ADMIN.has(storage, full.clone()),
+                    false,
+                    Error::msg(format!("denom already exists: {full}"))
+                );
                 DENOM_ADMIN.save(storage, full.clone(), &sender)?;
-                DENOM_METADATA.save(storage, full, &metadata)?;
+                DENOM_METADATA.save(storage, full.clone(), &metadata)?;
+                DENOM_SUPPLY.save(storage, full, &Uint128::zero())?;
 
                 Ok(AppResponse {
                     events: vec![],
@@ -140,6 +242,11 @@ impl Stargate for RujiraStargate {
                 let admin = DENOM_ADMIN.load(storage, denom.clone())?;
                 ensure_eq!(admin, sender, Error::msg("Unauthorized"));
 
+                let supply = DENOM_SUPPLY
+                    .may_load(storage, denom.clone())?
+                    .unwrap_or_default();
+                DENOM_SUPPLY.save(storage, denom.clone(), &(supply + amount))?;
+
                 router.sudo(
                     api,
                     storage,
@@ -168,6 +275,17 @@ impl Stargate for RujiraStargate {
                 let admin = DENOM_ADMIN.load(storage, denom.clone())?;
                 ensure_eq!(admin, sender, Error::msg("Unauthorized"));
 
+                let supply = DENOM_SUPPLY
+                    .may_load(storage, denom.clone())?
+                    .unwrap_or_default();
+                ensure!(
+                    supply >= amount,
+                    Error::msg(format!(
+                        "burn {amount} exceeds tracked supply {supply} for {denom}"
+                    ))
+                );
+                DENOM_SUPPLY.save(storage, denom.clone(), &(supply - amount))?;
+
                 router.execute(
                     api,
                     storage,
@@ -231,15 +349,20 @@ impl Stargate for RujiraStargate {
             _ => {
                 anyhow::bail!("Unexpected any execute: msg={:?} from {}", msg, sender)
             }
+        };
+
+        if result.is_ok() {
+            self.captured.borrow_mut().push(msg.clone());
         }
+        result
     }
 
     fn query_grpc(
         &self,
         _api: &dyn Api,
-        _storage: &dyn Storage,
+        storage: &dyn Storage,
         _querier: &dyn Querier,
-        _block: &BlockInfo,
+        block: &BlockInfo,
         request: GrpcQuery,
     ) -> AnyResult<Binary> {
         match request.path.as_str() {
@@ -247,7 +370,36 @@ impl Stargate for RujiraStargate {
             "/types.Query/Pool" => mock_pool(request.data),
             "/types.Query/Network" => mock_network(),
             "/types.Query/QuoteSwap" => mock_quote(request.data),
-            "/types.Query/OraclePrice" => mock_oracle_price(request.data, &self.prices),
+            "/types.Query/OraclePrice" => {
+                mock_oracle_price(request.data, &self.prices, block.height)
+            }
+            "/types.Query/OraclePriceTwap" => {
+                let buf = Bufany::deserialize(&request.data)?;
+                let symbol = buf.string(1).unwrap_or_default();
+                let start = buf.uint64(2).unwrap_or_default();
+                let empty = BTreeMap::new();
+                let schedule = self.prices.get(&symbol).unwrap_or(&empty);
+                let twap = twap(schedule, start, block.height);
+                Ok(Anybuf::new()
+                    .append_string(1, twap.to_string())
+                    .into_vec()
+                    .into())
+            }
+            "/thorchain.denom.v1.Query/DenomAdmin" => {
+                let buf = Bufany::deserialize(&request.data)?;
+                let denom = buf.string(1).unwrap_or_default();
+                let admin = DENOM_ADMIN.load(storage, denom)?;
+                Ok(Anybuf::new().append_string(1, admin).into_vec().into())
+            }
+            "/thorchain.denom.v1.Query/DenomMetadata" => {
+                let buf = Bufany::deserialize(&request.data)?;
+                let denom = buf.string(1).unwrap_or_default();
+                let metadata = DENOM_METADATA.load(storage, denom)?;
+                Ok(Anybuf::new()
+                    .append_message(1, &encode_metadata(&metadata))
+                    .into_vec()
+                    .into())
+            }
             _ => {
                 anyhow::bail!("Unexpected grpc query: request={:?}", request)
             }
@@ -255,6 +407,37 @@ impl Stargate for RujiraStargate {
     }
 }
 
+/// Integral of `schedule`'s step function (the price in effect at each scheduled height holds
+/// until the next one) from its earliest entry up to and including height `at`.
+fn cumulative(schedule: &BTreeMap<u64, Decimal>, at: u64) -> Decimal {
+    let entries: Vec<(&u64, &Decimal)> = schedule.range(..=at).collect();
+    entries
+        .iter()
+        .enumerate()
+        .fold(Decimal::zero(), |acc, (i, (height, price))| {
+            let next = entries.get(i + 1).map(|(h, _)| **h).unwrap_or(at);
+            let duration = next.saturating_sub(**height);
+            acc + Decimal::from_ratio(duration as u128, 1u128) * **price
+        })
+}
+
+/// Time-weighted average price between `start` and `now`, i.e. the cumulative price
+/// accumulator's rise over the elapsed height, mirroring a Uniswap-style TWAP oracle. Falls
+/// back to the latest scheduled price when `now` hasn't advanced past `start`.
+fn twap(schedule: &BTreeMap<u64, Decimal>, start: u64, now: u64) -> Decimal {
+    if now <= start {
+        return schedule
+            .range(..=now)
+            .next_back()
+            .map(|(_, price)| *price)
+            .unwrap_or_default();
+    }
+    let elapsed = Decimal::from_ratio((now - start) as u128, 1u128);
+    (cumulative(schedule, now) - cumulative(schedule, start))
+        .checked_div(elapsed)
+        .unwrap_or_default()
+}
+
 fn decode_metadata(m: Bufany) -> DenomMetadata {
     let denom_units = m
         .repeated_bytes(2)
@@ -282,9 +465,32 @@ fn decode_metadata(m: Bufany) -> DenomMetadata {
     }
 }
 
+fn encode_metadata(m: &DenomMetadata) -> Anybuf {
+    let denom_units: Vec<Anybuf> = m
+        .denom_units
+        .iter()
+        .map(|unit| {
+            Anybuf::new()
+                .append_string(1, &unit.denom)
+                .append_uint32(2, unit.exponent)
+                .append_repeated_string(3, &unit.aliases)
+        })
+        .collect();
+
+    Anybuf::new()
+        .append_string(1, &m.description)
+        .append_repeated_message(2, &denom_units)
+        .append_string(3, &m.base)
+        .append_string(4, &m.display)
+        .append_string(5, &m.name)
+        .append_string(6, &m.symbol)
+        .append_string(7, &m.uri)
+        .append_string(8, &m.uri_hash)
+}
+
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::Decimal;
+    use cosmwasm_std::{AnyMsg, CosmosMsg, Decimal, QueryRequest};
     use rujira_rs::{
         query::{Pool, PoolStatus},
         Asset, Layer1Asset,
@@ -292,6 +498,116 @@ mod tests {
 
     use super::*;
 
+    fn create_denom_msg(admin: &Addr, id: &str, name: &str) -> CosmosMsg {
+        CosmosMsg::Any(AnyMsg {
+            type_url: "/thorchain.denom.v1.MsgCreateDenom".to_string(),
+            value: Anybuf::new()
+                .append_string(1, admin)
+                .append_string(2, id)
+                .append_message(
+                    3,
+                    &Anybuf::new()
+                        .append_string(1, format!("{name} description"))
+                        .append_string(3, format!("x/{id}"))
+                        .append_string(4, format!("x/{id}"))
+                        .append_string(5, name)
+                        .append_string(6, name),
+                )
+                .into_vec()
+                .into(),
+        })
+    }
+
+    fn query_grpc(app: &RujiraApp, path: &str, data: Binary) -> Binary {
+        app.wrap()
+            .query(&QueryRequest::Grpc(GrpcQuery {
+                path: path.to_string(),
+                data,
+            }))
+            .unwrap()
+    }
+
+    #[test]
+    fn denom_create_rejects_duplicate_and_exposes_admin_and_metadata() {
+        let mut app = mock_rujira_app();
+        let admin = app.api().addr_make("admin");
+
+        app.execute(admin.clone(), create_denom_msg(&admin, "mytoken", "My Token"))
+            .unwrap();
+
+        // A second create for the same denom is rejected rather than silently overwriting.
+        app.execute(admin.clone(), create_denom_msg(&admin, "mytoken", "My Token"))
+            .unwrap_err();
+
+        let admin_res = query_grpc(
+            &app,
+            "/thorchain.denom.v1.Query/DenomAdmin",
+            Anybuf::new().append_string(1, "x/mytoken").into_vec().into(),
+        );
+        let decoded = Bufany::deserialize(&admin_res).unwrap();
+        assert_eq!(decoded.string(1).unwrap(), admin.to_string());
+
+        let meta_res = query_grpc(
+            &app,
+            "/thorchain.denom.v1.Query/DenomMetadata",
+            Anybuf::new().append_string(1, "x/mytoken").into_vec().into(),
+        );
+        let decoded = Bufany::deserialize(&meta_res).unwrap();
+        let metadata = decode_metadata(decoded.message(1).unwrap());
+        assert_eq!(metadata.name, "My Token");
+        assert_eq!(metadata.base, "x/mytoken");
+    }
+
+    #[test]
+    fn denom_burn_tracks_supply_and_rejects_overdraw() {
+        let mut app = mock_rujira_app();
+        let admin = app.api().addr_make("admin");
+
+        app.execute(admin.clone(), create_denom_msg(&admin, "mytoken", "My Token"))
+            .unwrap();
+
+        app.execute(
+            admin.clone(),
+            CosmosMsg::Any(AnyMsg {
+                type_url: "/thorchain.denom.v1.MsgMintTokens".to_string(),
+                value: Anybuf::new()
+                    .append_string(1, &admin)
+                    .append_message(
+                        2,
+                        &Anybuf::new()
+                            .append_string(1, "x/mytoken")
+                            .append_string(2, "1000"),
+                    )
+                    .append_string(3, &admin)
+                    .into_vec()
+                    .into(),
+            }),
+        )
+        .unwrap();
+
+        let burn_msg = |amount: &str| {
+            CosmosMsg::Any(AnyMsg {
+                type_url: "/thorchain.denom.v1.MsgBurnTokens".to_string(),
+                value: Anybuf::new()
+                    .append_string(1, &admin)
+                    .append_message(
+                        2,
+                        &Anybuf::new()
+                            .append_string(1, "x/mytoken")
+                            .append_string(2, amount),
+                    )
+                    .into_vec()
+                    .into(),
+            })
+        };
+
+        // Burning more than the tracked supply is rejected.
+        app.execute(admin.clone(), burn_msg("1001")).unwrap_err();
+
+        // Burning up to the tracked supply succeeds.
+        app.execute(admin.clone(), burn_msg("1000")).unwrap();
+    }
+
     #[test]
     fn query_pool() {
         let app = mock_rujira_app();
@@ -318,4 +634,107 @@ mod tests {
         assert_eq!(res.synth_supply_remaining, Uint128::from(22913550433u128));
         assert_eq!(res.derived_depth_bps, 9639);
     }
+
+    #[test]
+    fn query_pool_custom() {
+        let app = mock_rujira_app();
+        let res: crate::query::PoolResponse = app
+            .wrap()
+            .query(&QueryRequest::Custom(RujiraQuery::Pool {
+                asset: "BTC.BTC".to_string(),
+            }))
+            .unwrap();
+        assert_eq!(res.asset, "BTC.BTC");
+        assert_eq!(res.short_code, "b".to_string());
+        assert_eq!(res.balance_asset, Uint128::from(68602648901u128));
+    }
+
+    fn deposit_msg(signer: &Addr, asset: &str, amount: &str, memo: &str) -> CosmosMsg {
+        CosmosMsg::Any(AnyMsg {
+            type_url: "/types.MsgDeposit".to_string(),
+            value: Anybuf::new()
+                .append_repeated_message(
+                    1,
+                    &[Anybuf::new()
+                        .append_string(1, asset)
+                        .append_string(2, amount)],
+                )
+                .append_string(2, memo)
+                .append_string(3, signer)
+                .into_vec()
+                .into(),
+        })
+    }
+
+    #[test]
+    fn captures_deposits_for_test_assertions() {
+        let (mut app, log) = mock_rujira_app_with_log();
+        let signer = app.api().addr_make("signer");
+
+        app.execute(
+            signer.clone(),
+            deposit_msg(&signer, "BTC.BTC", "123456", "SWAP:THOR.RUNE"),
+        )
+        .unwrap();
+
+        assert_eq!(captured(&log, "/types.MsgDeposit").len(), 1);
+
+        let deposits = deposits(&log);
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].memo, "SWAP:THOR.RUNE");
+        assert_eq!(deposits[0].signer, signer.to_string());
+        assert_eq!(
+            deposits[0].coins,
+            vec![DepositCoin {
+                asset: "BTC.BTC".to_string(),
+                amount: Uint128::from(123456u128),
+            }]
+        );
+    }
+
+    #[test]
+    fn oracle_price_evolves_with_block_height() {
+        let mut stargate = RujiraStargate::default();
+        stargate.with_price_at("BTC.BTC", 0, Decimal::percent(10_000));
+        stargate.with_price_at("BTC.BTC", 100, Decimal::percent(20_000));
+        let mut app = mock_rujira_app_with_stargate(stargate);
+
+        app.update_block(|b| b.height = 50);
+        let res: crate::query::OraclePriceResponse = app
+            .wrap()
+            .query(&QueryRequest::Custom(RujiraQuery::OraclePrice {
+                symbol: "BTC.BTC".to_string(),
+            }))
+            .unwrap();
+        assert_eq!(res.price, Some(Decimal::percent(10_000)));
+
+        app.update_block(|b| b.height = 150);
+        let res: crate::query::OraclePriceResponse = app
+            .wrap()
+            .query(&QueryRequest::Custom(RujiraQuery::OraclePrice {
+                symbol: "BTC.BTC".to_string(),
+            }))
+            .unwrap();
+        assert_eq!(res.price, Some(Decimal::percent(20_000)));
+    }
+
+    #[test]
+    fn oracle_price_twap_averages_over_the_window() {
+        let mut stargate = RujiraStargate::default();
+        // Price holds at 100 for the first 50 blocks, then jumps to 200.
+        stargate.with_price_at("BTC.BTC", 0, Decimal::percent(10_000));
+        stargate.with_price_at("BTC.BTC", 50, Decimal::percent(20_000));
+        let mut app = mock_rujira_app_with_stargate(stargate);
+        app.update_block(|b| b.height = 100);
+
+        let res: crate::query::OraclePriceTwapResponse = app
+            .wrap()
+            .query(&QueryRequest::Custom(RujiraQuery::OraclePriceTwap {
+                symbol: "BTC.BTC".to_string(),
+                start_height: 0,
+            }))
+            .unwrap();
+        // 50 blocks at 100 + 50 blocks at 200, averaged over 100 blocks = 150.
+        assert_eq!(res.twap, Decimal::percent(15_000));
+    }
 }
@@ -0,0 +1,264 @@
+use std::str::FromStr;
+
+use anybuf::{Anybuf, Bufany};
+use anyhow::Result as AnyResult;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Api, Binary, BlockInfo, CustomMsg, CustomQuery, Decimal, Empty,
+    Querier, QuerierWrapper, Storage, Uint128,
+};
+use cw_multi_test::{AppResponse, CosmosRouter, Module};
+use prost::Message;
+use rujira_rs::proto::types;
+use serde::de::DeserializeOwned;
+
+/// Strongly-typed stand-in for the stringly-matched `/types.Query/...` gRPC paths that
+/// [`crate::mock::RujiraStargate`] answers. Contracts under test can issue these via
+/// `QuerierWrapper::query(&QueryRequest::Custom(...))` and get a real type back, instead of
+/// hand-building a protobuf request and decoding a raw `Binary` response themselves.
+#[cw_serde]
+pub enum RujiraQuery {
+    Pool { asset: String },
+    Mimir { key: String },
+    Network {},
+    QuoteSwap {
+        from_asset: String,
+        to_asset: String,
+        amount: Uint128,
+        destination: String,
+    },
+    OraclePrice { symbol: String },
+    OraclePriceTwap { symbol: String, start_height: u64 },
+}
+
+impl CustomQuery for RujiraQuery {}
+
+#[cw_serde]
+pub struct PoolResponse {
+    pub asset: String,
+    pub short_code: String,
+    pub status: String,
+    pub decimals: u32,
+    pub pending_inbound_asset: Uint128,
+    pub pending_inbound_rune: Uint128,
+    pub balance_asset: Uint128,
+    pub balance_rune: Uint128,
+    pub asset_tor_price: Decimal,
+    pub pool_units: Uint128,
+    pub lp_units: Uint128,
+    pub synth_units: Uint128,
+    pub synth_supply: Uint128,
+    pub savers_depth: Uint128,
+    pub savers_units: Uint128,
+    pub savers_fill_bps: u32,
+    pub savers_capacity_remaining: Uint128,
+    pub synth_mint_paused: bool,
+    pub synth_supply_remaining: Uint128,
+    pub derived_depth_bps: u32,
+    pub trading_halted: bool,
+}
+
+#[cw_serde]
+pub struct NetworkResponse {
+    pub bond_reward_rune: Uint128,
+    pub total_bond_units: Uint128,
+    pub effective_security_bond: Uint128,
+    pub total_reserve: Uint128,
+    pub vaults_migrating: bool,
+    pub gas_spent_rune: Uint128,
+    pub gas_withheld_rune: Uint128,
+    pub outbound_fee_multiplier: Uint128,
+    pub native_outbound_fee_rune: Uint128,
+    pub native_tx_fee_rune: Uint128,
+    pub tns_register_fee_rune: Uint128,
+    pub tns_fee_per_block_rune: Uint128,
+    pub rune_price_in_tor: Uint128,
+    pub tor_price_in_rune: Uint128,
+}
+
+#[cw_serde]
+pub struct QuoteSwapResponse {
+    pub expected_amount_out: Uint128,
+    pub fees_total: Uint128,
+    pub memo: String,
+}
+
+#[cw_serde]
+pub struct OraclePriceResponse {
+    pub symbol: String,
+    pub price: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct OraclePriceTwapResponse {
+    pub symbol: String,
+    pub twap: Decimal,
+}
+
+/// [`Module`] implementation that answers [`RujiraQuery`] by forwarding the request, re-encoded
+/// as the matching `/types.Query/...` gRPC call, to whatever [`crate::mock::RujiraStargate`] is
+/// wired into the same app - so it's ultimately the existing `mock_pool`/`mock_mimir`/
+/// `mock_network`/`mock_quote`/`mock_oracle_price` fixtures answering it - then re-types the
+/// decoded protobuf response as a `cw_serde` struct. Two exceptions: `QuoteSwap` is forwarded
+/// with an empty request body, so its fields never reach `mock_quote`, which answers with a
+/// fixed quote; `OraclePriceTwap` is answered by `RujiraStargate`'s own TWAP over its price
+/// schedule.
+#[derive(Default)]
+pub struct RujiraQuerier {}
+
+impl Module for RujiraQuerier {
+    type ExecT = Empty;
+    type QueryT = RujiraQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        _sender: Addr,
+        msg: Self::ExecT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        anyhow::bail!("Unexpected custom exec: {:?}", msg)
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Self::SudoT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        anyhow::bail!("Unexpected custom sudo: {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        _storage: &dyn Storage,
+        querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: Self::QueryT,
+    ) -> AnyResult<Binary> {
+        let q = QuerierWrapper::<Empty>::new(querier);
+
+        match request {
+            RujiraQuery::Pool { asset } => {
+                let mut req = Vec::new();
+                types::QueryPoolRequest { asset }.encode(&mut req).unwrap();
+                let data = q.query_grpc("/types.Query/Pool".to_string(), req.into())?;
+                let res = types::QueryPoolResponse::decode(data.as_slice())?;
+                Ok(to_json_binary(&PoolResponse {
+                    asset: res.asset,
+                    short_code: res.short_code,
+                    status: res.status,
+                    decimals: res.decimals,
+                    pending_inbound_asset: Uint128::from_str(&res.pending_inbound_asset)?,
+                    pending_inbound_rune: Uint128::from_str(&res.pending_inbound_rune)?,
+                    balance_asset: Uint128::from_str(&res.balance_asset)?,
+                    balance_rune: Uint128::from_str(&res.balance_rune)?,
+                    asset_tor_price: Decimal::from_str(&res.asset_tor_price)?,
+                    pool_units: Uint128::from_str(&res.pool_units)?,
+                    lp_units: Uint128::from_str(&res.lp_units)?,
+                    synth_units: Uint128::from_str(&res.synth_units)?,
+                    synth_supply: Uint128::from_str(&res.synth_supply)?,
+                    savers_depth: Uint128::from_str(&res.savers_depth)?,
+                    savers_units: Uint128::from_str(&res.savers_units)?,
+                    savers_fill_bps: res.savers_fill_bps.parse()?,
+                    savers_capacity_remaining: Uint128::from_str(&res.savers_capacity_remaining)?,
+                    synth_mint_paused: res.synth_mint_paused,
+                    synth_supply_remaining: Uint128::from_str(&res.synth_supply_remaining)?,
+                    derived_depth_bps: res.derived_depth_bps.parse()?,
+                    trading_halted: res.trading_halted,
+                })?)
+            }
+            RujiraQuery::Mimir { key } => {
+                let mut req = Vec::new();
+                types::QueryMimirWithKeyRequest { key }
+                    .encode(&mut req)
+                    .unwrap();
+                let data = q.query_grpc("/types.Query/MimirWithKey".to_string(), req.into())?;
+                let res = types::QueryMimirWithKeyResponse::decode(data.as_slice())?;
+                Ok(to_json_binary(&res.value)?)
+            }
+            RujiraQuery::Network {} => {
+                let data = q.query_grpc("/types.Query/Network".to_string(), Binary::default())?;
+                let res = types::QueryNetworkResponse::decode(data.as_slice())?;
+                Ok(to_json_binary(&NetworkResponse {
+                    bond_reward_rune: Uint128::from_str(&res.bond_reward_rune)?,
+                    total_bond_units: Uint128::from_str(&res.total_bond_units)?,
+                    effective_security_bond: Uint128::from_str(&res.effective_security_bond)?,
+                    total_reserve: Uint128::from_str(&res.total_reserve)?,
+                    vaults_migrating: res.vaults_migrating,
+                    gas_spent_rune: Uint128::from_str(&res.gas_spent_rune)?,
+                    gas_withheld_rune: Uint128::from_str(&res.gas_withheld_rune)?,
+                    outbound_fee_multiplier: Uint128::from_str(&res.outbound_fee_multiplier)?,
+                    native_outbound_fee_rune: Uint128::from_str(&res.native_outbound_fee_rune)?,
+                    native_tx_fee_rune: Uint128::from_str(&res.native_tx_fee_rune)?,
+                    tns_register_fee_rune: Uint128::from_str(&res.tns_register_fee_rune)?,
+                    tns_fee_per_block_rune: Uint128::from_str(&res.tns_fee_per_block_rune)?,
+                    rune_price_in_tor: Uint128::from_str(&res.rune_price_in_tor)?,
+                    tor_price_in_rune: Uint128::from_str(&res.tor_price_in_rune)?,
+                })?)
+            }
+            RujiraQuery::QuoteSwap { .. } => {
+                let data =
+                    q.query_grpc("/types.Query/QuoteSwap".to_string(), Binary::default())?;
+                let res = types::QueryQuoteSwapResponse::decode(data.as_slice())?;
+                let fees_total = res
+                    .fees
+                    .as_ref()
+                    .map(|f| Uint128::from_str(&f.total))
+                    .transpose()?
+                    .unwrap_or_default();
+                Ok(to_json_binary(&QuoteSwapResponse {
+                    expected_amount_out: Uint128::from_str(&res.expected_amount_out)?,
+                    fees_total,
+                    memo: res.memo,
+                })?)
+            }
+            RujiraQuery::OraclePrice { symbol } => {
+                let mut req = Vec::new();
+                types::QueryOraclePriceRequest {
+                    height: "0".to_string(),
+                    symbol: symbol.clone(),
+                }
+                .encode(&mut req)
+                .unwrap();
+                let data = q.query_grpc("/types.Query/OraclePrice".to_string(), req.into())?;
+                let res = types::QueryOraclePriceResponse::decode(data.as_slice())?;
+                Ok(to_json_binary(&OraclePriceResponse {
+                    symbol,
+                    price: res
+                        .price
+                        .map(|p| Decimal::from_str(&p.price))
+                        .transpose()?,
+                })?)
+            }
+            RujiraQuery::OraclePriceTwap {
+                symbol,
+                start_height,
+            } => {
+                let req = Anybuf::new()
+                    .append_string(1, &symbol)
+                    .append_uint64(2, start_height)
+                    .into_vec();
+                let data =
+                    q.query_grpc("/types.Query/OraclePriceTwap".to_string(), req.into())?;
+                let buf = Bufany::deserialize(&data)?;
+                let twap = Decimal::from_str(&buf.string(1).unwrap_or_default())?;
+                Ok(to_json_binary(&OraclePriceTwapResponse { symbol, twap })?)
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{coin, coins, to_json_binary, Addr, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cosmwasm_std::{coin, coins, to_json_binary, Addr, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg};
 use std::{
     collections::HashMap,
     ops::{Add, AddAssign},
@@ -39,6 +39,49 @@ impl Commitment {
             })
             .collect()
     }
+
+    /// Walk a sorted order book level by level, filling `input` until it's exhausted or the
+    /// book runs out of liquidity. For `Side::Base`, `input` is denominated in the base asset
+    /// and each level yields `filled * price` of quote; for `Side::Quote` it's the reverse,
+    /// each level yielding `filled / price` of base, mirroring the base/quote decimal delta
+    /// `Denoms::bid`/`Denoms::ask` already account for. Returns the output received, the worst
+    /// (last) price touched, and whether the book had enough depth to fill all of `input`.
+    pub fn simulate(
+        &self,
+        book: &[(Decimal, Uint128)],
+        side: &Side,
+        input: Uint128,
+    ) -> (Uint128, Decimal, bool) {
+        let mut remaining = input;
+        let mut output = Uint128::zero();
+        let mut worst_price = Decimal::zero();
+
+        for (price, quantity) in book {
+            if remaining.is_zero() {
+                break;
+            }
+            let filled = remaining.min(*quantity);
+            output += match side {
+                Side::Base => filled.mul_floor(*price),
+                Side::Quote => filled.mul_floor(price.inv().unwrap_or_default()),
+            };
+            remaining -= filled;
+            worst_price = *price;
+        }
+
+        (output, worst_price, remaining.is_zero())
+    }
+
+    /// The slippage of `worst_price` versus the oracle `mid` price, as a fraction of `mid`
+    pub fn slip(worst_price: Decimal, mid: Decimal) -> Decimal {
+        if mid.is_zero() {
+            return Decimal::one();
+        }
+        worst_price
+            .abs_diff(mid)
+            .checked_div(mid)
+            .unwrap_or(Decimal::one())
+    }
 }
 
 impl Add for Commitment {
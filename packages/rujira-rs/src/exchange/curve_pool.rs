@@ -0,0 +1,205 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Attribute, Decimal, Storage, Uint128, Uint256};
+
+use super::{commitment::Commitment, error::SwapError, swappable::Swappable};
+
+/// A two-asset AMM pool priced against the Curve-style StableSwap invariant, rather than a
+/// single flat `rate()`: `Ann*(x+y) + D = Ann*D + D^3/(4*x*y)` for reserves `x`, `y` at
+/// amplification `A` (`Ann = A*4`). Unlike a discrete bid pool, the marginal price moves as
+/// the fill progresses, so `Swapper` can merge this alongside fixed-rate levels in one
+/// ordered iterator and let each fill at its own instantaneous price. This is the bare
+/// two-reserve primitive - a consuming contract owns its own storage key and persists
+/// `reserves` itself after `swap`, the same way it would for any other `Swappable` state.
+#[cw_serde]
+pub struct CurvePool {
+    /// `[x, y]`: the offered-asset reserve and the counter-asset reserve `swap` draws from.
+    pub reserves: [Uint128; 2],
+    /// Curve amplification coefficient `A` - higher values flatten the curve near parity,
+    /// tightening slippage for reserves expected to trade close to 1:1.
+    pub amplification: Uint128,
+}
+
+impl Swappable for CurvePool {
+    fn swap(&mut self, offer: Uint128) -> Result<(Uint128, Uint128), SwapError> {
+        if offer.is_zero() || self.reserves[0].is_zero() || self.reserves[1].is_zero() {
+            return Ok((Uint128::zero(), Uint128::zero()));
+        }
+
+        let x = Uint256::from(self.reserves[0]);
+        let y = Uint256::from(self.reserves[1]);
+        let d = get_d(self.amplification, x, y);
+
+        let x_new = x + Uint256::from(offer);
+        let y_new = get_y(self.amplification, d, x_new);
+        let dy = y.checked_sub(y_new).unwrap_or(Uint256::zero());
+
+        // Round down so the curve never pays out more than it actually holds.
+        let returned: Uint128 = dy.try_into().unwrap_or(Uint128::MAX).min(self.reserves[1]);
+
+        self.reserves[0] += offer;
+        self.reserves[1] -= returned;
+
+        Ok((offer, returned))
+    }
+
+    fn commit(&self, _storage: &mut dyn Storage) -> Result<Commitment, SwapError> {
+        Ok(Commitment::default())
+    }
+
+    fn attributes(&self) -> Vec<Attribute> {
+        vec![Attribute::new("pool", "curve")]
+    }
+
+    /// Marginal spot price at the current balances, from the invariant's partial derivatives
+    /// rather than the naive `y / x` ratio: `y*(Ann*x + D_P) / (x*(Ann*y + D_P))`, where
+    /// `D_P = D^3/(4*x*y)`.
+    fn rate(&self) -> Decimal {
+        let x = Uint256::from(self.reserves[0]);
+        let y = Uint256::from(self.reserves[1]);
+        if x.is_zero() || y.is_zero() {
+            return Decimal::zero();
+        }
+
+        let d = get_d(self.amplification, x, y);
+        let ann = Uint256::from(self.amplification) * Uint256::from(4u128);
+        let d_p = d * d * d / (Uint256::from(4u128) * x * y);
+
+        let numerator = (ann * x + d_p) * y;
+        let denominator = (ann * y + d_p) * x;
+        if denominator.is_zero() {
+            return Decimal::zero();
+        }
+
+        let (numerator, denominator): (Uint128, Uint128) =
+            match (numerator.try_into(), denominator.try_into()) {
+                (Ok(n), Ok(d)) => (n, d),
+                // The invariant's cross terms overflowed a Uint128 - report no actionable
+                // price rather than a misleadingly precise one.
+                _ => return Decimal::zero(),
+            };
+
+        Decimal::from_ratio(numerator, denominator.max(Uint128::one()))
+    }
+
+    fn total(&self) -> Uint128 {
+        self.reserves[1]
+    }
+}
+
+/// Solves the 2-coin StableSwap invariant `D` for reserves `x`, `y` at amplification `amp`,
+/// via Newton iteration: `D_{n+1} = (Ann*S + D_P*2)*D_n / ((Ann-1)*D_n + 3*D_P)`, where
+/// `Ann = 4*amp` and `D_P = D_n^3/(4*x*y)`. Stops once consecutive iterates differ by at most
+/// 1 atomic unit, or after a hard cap of iterations, so a degenerate input (e.g. one reserve
+/// near zero) can't diverge instead of just converging slowly.
+fn get_d(amp: Uint128, x: Uint256, y: Uint256) -> Uint256 {
+    let s = x + y;
+    if s.is_zero() {
+        return Uint256::zero();
+    }
+
+    let ann = Uint256::from(amp) * Uint256::from(4u128);
+    let mut d = s;
+    for _ in 0..255 {
+        if x.is_zero() || y.is_zero() {
+            break;
+        }
+
+        let d_p = d * d * d / (Uint256::from(4u128) * x * y);
+        let d_prev = d;
+
+        let numerator = (ann * s + d_p * Uint256::from(2u128)) * d;
+        let denominator = (ann - Uint256::one()) * d + Uint256::from(3u128) * d_p;
+        if denominator.is_zero() {
+            break;
+        }
+        d = numerator / denominator;
+
+        let diff = if d > d_prev { d - d_prev } else { d_prev - d };
+        if diff <= Uint256::one() {
+            break;
+        }
+    }
+    d
+}
+
+/// Solves for the new `y` reserve that keeps `D` constant after the other reserve moves to
+/// `x_new`, via Newton iteration on `y^2 + (b - D)*y - c = 0` (`b = x_new + D/Ann`,
+/// `c = D^3/(4*Ann*x_new)`): `y_{n+1} = (y_n^2 + c) / (2*y_n + b - D)`. Returns `0` for
+/// degenerate inputs (zero `x_new`/`D`, or a denominator that would go non-positive) rather
+/// than let Newton diverge.
+fn get_y(amp: Uint128, d: Uint256, x_new: Uint256) -> Uint256 {
+    if x_new.is_zero() || d.is_zero() {
+        return Uint256::zero();
+    }
+
+    let ann = Uint256::from(amp) * Uint256::from(4u128);
+    if ann.is_zero() {
+        return Uint256::zero();
+    }
+
+    let c = d * d * d / (Uint256::from(4u128) * ann * x_new);
+    let b = x_new + d / ann;
+
+    let mut y = d;
+    for _ in 0..255 {
+        let y_prev = y;
+        let denom_plus = Uint256::from(2u128) * y + b;
+        if denom_plus < d {
+            break;
+        }
+        let denom = denom_plus - d;
+        if denom.is_zero() {
+            break;
+        }
+        y = (y * y + c) / denom;
+
+        let diff = if y > y_prev { y - y_prev } else { y_prev - y };
+        if diff <= Uint256::one() {
+            break;
+        }
+    }
+    y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_near_peg_returns_close_to_one_to_one() {
+        let mut pool = CurvePool {
+            reserves: [Uint128::from(1_000_000u128), Uint128::from(1_000_000u128)],
+            amplification: Uint128::from(100u128),
+        };
+
+        let (out_offer, out_bid) = pool.swap(Uint128::from(10_000u128)).unwrap();
+        assert_eq!(out_offer, Uint128::from(10_000u128));
+        assert!(out_bid > Uint128::from(9_900u128));
+        assert!(out_bid <= Uint128::from(10_000u128));
+        assert_eq!(pool.reserves[0], Uint128::from(1_010_000u128));
+    }
+
+    #[test]
+    fn rate_is_close_to_parity_at_balanced_reserves() {
+        let pool = CurvePool {
+            reserves: [Uint128::from(1_000_000u128), Uint128::from(1_000_000u128)],
+            amplification: Uint128::from(100u128),
+        };
+        let rate = pool.rate();
+        assert!(rate > Decimal::percent(99));
+        assert!(rate <= Decimal::one());
+    }
+
+    #[test]
+    fn zero_reserves_do_not_panic() {
+        let mut pool = CurvePool {
+            reserves: [Uint128::zero(), Uint128::from(1_000_000u128)],
+            amplification: Uint128::from(100u128),
+        };
+        assert_eq!(pool.rate(), Decimal::zero());
+        assert_eq!(
+            pool.swap(Uint128::from(1_000u128)).unwrap(),
+            (Uint128::zero(), Uint128::zero())
+        );
+    }
+}
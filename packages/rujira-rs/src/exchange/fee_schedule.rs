@@ -0,0 +1,126 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Uint128};
+use std::ops::Mul;
+
+use super::error::SwapError;
+
+/// One recipient's cut of a swap's fee, paired with its rate against `returned`.
+#[cw_serde]
+pub struct FeeRecipient {
+    pub recipient: Addr,
+    pub rate: Decimal,
+}
+
+/// An ordered list of fee recipients (e.g. protocol, affiliate, referrer) sharing a single
+/// gross fee taken from a swap's `returned` amount, replacing a lump scalar `fee: Decimal`
+/// with a per-recipient breakdown.
+#[cw_serde]
+#[derive(Default)]
+pub struct FeeSchedule {
+    entries: Vec<FeeRecipient>,
+}
+
+impl FeeSchedule {
+    /// Builds a schedule from `(recipient, rate)` pairs. Errors if the combined rate is not
+    /// strictly less than 100%, since a schedule that takes the whole (or more than the whole)
+    /// return amount leaves nothing for the swapper.
+    pub fn new(entries: Vec<(Addr, Decimal)>) -> Result<Self, SwapError> {
+        let total = entries
+            .iter()
+            .fold(Decimal::zero(), |acc, (_, rate)| acc + *rate);
+        if total >= Decimal::one() {
+            return Err(SwapError::InvalidFeeSchedule {});
+        }
+        Ok(Self {
+            entries: entries
+                .into_iter()
+                .map(|(recipient, rate)| FeeRecipient { recipient, rate })
+                .collect(),
+        })
+    }
+
+    /// The combined rate across all recipients, used to gross a net amount back up to what the
+    /// liquidity needs to produce before fees.
+    pub fn total_rate(&self) -> Decimal {
+        self.entries
+            .iter()
+            .fold(Decimal::zero(), |acc, e| acc + e.rate)
+    }
+
+    /// Splits `returned` into the net amount, the gross fee, and each recipient's cut of that
+    /// fee. The gross fee is `ceil(returned * total_rate())`, matching the rounding of the old
+    /// scalar-fee path exactly; each recipient's cut is then floored from its share of the
+    /// gross fee, with the remainder credited to the first recipient so the cuts can never sum
+    /// to more than the gross fee actually deducted.
+    pub fn apply(&self, returned: Uint128) -> (Uint128, Uint128, Vec<(Addr, Uint128)>) {
+        if self.entries.is_empty() {
+            return (returned, Uint128::zero(), vec![]);
+        }
+
+        let total_rate = self.total_rate();
+        let gross_fee = Decimal::from_ratio(returned, 1u128)
+            .mul(total_rate)
+            .to_uint_ceil();
+
+        let mut shares: Vec<(Addr, Uint128)> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let share = e.rate.checked_div(total_rate).unwrap_or_default();
+                let amount = Decimal::from_ratio(gross_fee, 1u128)
+                    .mul(share)
+                    .to_uint_floor();
+                (e.recipient.clone(), amount)
+            })
+            .collect();
+
+        let allocated: Uint128 = shares.iter().map(|(_, amount)| *amount).sum();
+        if let Some((_, first)) = shares.first_mut() {
+            *first += gross_fee - allocated;
+        }
+
+        (returned - gross_fee, gross_fee, shares)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Addr {
+        Addr::unchecked(s)
+    }
+
+    #[test]
+    fn rejects_schedules_at_or_above_one() {
+        FeeSchedule::new(vec![(addr("protocol"), Decimal::percent(100))]).unwrap_err();
+        FeeSchedule::new(vec![
+            (addr("protocol"), Decimal::percent(60)),
+            (addr("affiliate"), Decimal::percent(40)),
+        ])
+        .unwrap_err();
+    }
+
+    #[test]
+    fn single_recipient_matches_old_scalar_rounding() {
+        let schedule =
+            FeeSchedule::new(vec![(addr("protocol"), Decimal::permille(1))]).unwrap();
+        let (net, gross, shares) = schedule.apply(Uint128::from(6290u128));
+        assert_eq!(gross, Uint128::from(7u128));
+        assert_eq!(net, Uint128::from(6283u128));
+        assert_eq!(shares, vec![(addr("protocol"), Uint128::from(7u128))]);
+    }
+
+    #[test]
+    fn multi_recipient_split_never_exceeds_gross_fee() {
+        let schedule = FeeSchedule::new(vec![
+            (addr("protocol"), Decimal::percent(20)),
+            (addr("affiliate"), Decimal::percent(10)),
+        ])
+        .unwrap();
+        let (net, gross, shares) = schedule.apply(Uint128::from(1001u128));
+        let allocated: Uint128 = shares.iter().map(|(_, amount)| *amount).sum();
+        assert_eq!(allocated, gross);
+        assert_eq!(net + gross, Uint128::from(1001u128));
+    }
+}
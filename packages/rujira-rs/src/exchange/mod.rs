@@ -1,12 +1,16 @@
 mod arb;
 mod commitment;
+mod curve_pool;
 mod error;
+mod fee_schedule;
 mod swappable;
 mod swapper;
 
 pub use arb::{Arber, Arbitrage};
 pub use commitment::Commitment;
+pub use curve_pool::CurvePool;
 pub use error::SwapError;
+pub use fee_schedule::{FeeRecipient, FeeSchedule};
 pub use swappable::Swappable;
 pub use swapper::{SwapResult, Swapper};
 
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Attribute, Decimal, Storage, Uint128};
+use cosmwasm_std::{Attribute, Decimal, Storage, Uint128, Uint256};
 use itertools::EitherOrBoth;
 use std::ops::Add;
 
@@ -20,6 +20,57 @@ pub trait Swappable {
     /// Returns the (offer_consumed, bids_returned) amounts
     fn swap(&mut self, offer: Uint128) -> Result<(Uint128, Uint128), SwapError>;
 
+    /// Like `swap`, but expressed as a desired `target_bids` output rather than an input amount:
+    /// estimates the offer needed at `rate()` and delegates to `swap`, so a buy-side caller can
+    /// fill only as far into a level as needed rather than consuming it fully. Because `rate()`
+    /// is the level's instantaneous price, the offer estimate - and so the bids actually
+    /// returned - may overshoot `target_bids` slightly on rounding; a caller assembling an exact
+    /// total across many levels should still check `bids_returned` against its remaining target
+    /// itself, as `Swapper::swap` does for `SwapRequest::Exact`.
+    fn swap_for_output(&mut self, target_bids: Uint128) -> Result<(Uint128, Uint128), SwapError> {
+        if target_bids.is_zero() {
+            return Ok((Uint128::zero(), Uint128::zero()));
+        }
+        let rate = self.rate();
+        if rate.is_zero() {
+            return self.swap(Uint128::zero());
+        }
+        let offer = Decimal::from_ratio(target_bids, 1u128)
+            .checked_div(rate)
+            .map(|d| d.to_uint_ceil())
+            .unwrap_or(target_bids);
+        self.swap(offer)
+    }
+
+    /// Like `swap`, but first checks `rate()` against `limit` (in `offer / bids` terms) and
+    /// skips the level entirely - without mutating it - if its price already crosses the limit,
+    /// rather than consuming it and discarding the result as `Swapper` used to. For a flat-rate
+    /// level this is equivalent: since `rate()` is constant across the fill, any amount consumed
+    /// would realize the same price, so there's no partial slice worth keeping once the level's
+    /// price fails the check. A Swappable whose marginal price moves as it fills (e.g. a curve)
+    /// should override this to find the largest sub-amount whose blended price still clears
+    /// `limit`, rather than inheriting the all-or-nothing default.
+    fn swap_up_to_price(
+        &mut self,
+        offer: Uint128,
+        limit: Decimal,
+    ) -> Result<(Uint128, Uint128), SwapError> {
+        if offer.is_zero() {
+            return Ok((Uint128::zero(), Uint128::zero()));
+        }
+
+        let rate = self.rate();
+        if rate.is_zero() {
+            return Ok((Uint128::zero(), Uint128::zero()));
+        }
+        let price = Decimal::one().checked_div(rate).unwrap_or(Decimal::MAX);
+        if price > limit {
+            return Ok((Uint128::zero(), Uint128::zero()));
+        }
+
+        self.swap(offer)
+    }
+
     /// Commits the result of the Swap.
     /// Storage is provided to commit local state
     /// SwapCommit is returned for commitments that require inter-contract communication
@@ -103,19 +154,18 @@ where
     }
 
     fn swap(&mut self, amount: Uint128) -> Result<(Uint128, Uint128), SwapError> {
-        let total = self.total();
-        let mut remaining = amount;
+        let totals: Vec<Uint128> = self.iter().map(|x| x.total()).collect();
+        let offers = allocate(amount, &totals);
+
         let mut consumed_offer = Uint128::zero();
         let mut consumed_bids = Uint128::zero();
-        for x in self.iter_mut() {
-            if remaining.is_zero() {
-                break;
+        for (x, offer) in self.iter_mut().zip(offers) {
+            if offer.is_zero() {
+                continue;
             }
-            let offer = amount.multiply_ratio(x.total(), total).min(remaining);
             let (c_offer, c_bids) = x.swap(offer)?;
             consumed_offer += c_offer;
             consumed_bids += c_bids;
-            remaining -= c_offer;
         }
         Ok((consumed_offer, consumed_bids))
     }
@@ -126,6 +176,59 @@ where
     }
 }
 
+/// Splits `amount` across `totals` via the largest-remainder (Hamilton) method, each item capped
+/// at its own `totals[i]`: every item first receives `floor(amount * totals[i] / total)`, then
+/// the residual left by flooring is handed out one unit at a time to the items with the largest
+/// fractional remainder `(amount * totals[i]) mod total`, skipping any item already at its own
+/// capacity. Since a floored share can never reach an item's full capacity unless `amount` has
+/// already reached `total`, a single lap normally exhausts the residual; the outer loop only
+/// matters if it doesn't, so the full `amount` (up to `Σ totals`) always ends up allocated
+/// instead of being lost to rounding.
+fn allocate(amount: Uint128, totals: &[Uint128]) -> Vec<Uint128> {
+    let n = totals.len();
+    let mut offers = vec![Uint128::zero(); n];
+
+    let total = totals.iter().fold(Uint128::zero(), |acc, t| acc + *t);
+    if total.is_zero() || amount.is_zero() {
+        return offers;
+    }
+    let amount = amount.min(total);
+
+    let total_256 = Uint256::from(total);
+    let mut remainders: Vec<(usize, Uint256)> = Vec::with_capacity(n);
+    let mut allocated = Uint128::zero();
+    for (i, t) in totals.iter().enumerate() {
+        let scaled = Uint256::from(amount) * Uint256::from(*t);
+        let floor = Uint128::try_from(scaled / total_256).unwrap();
+        offers[i] = floor;
+        allocated += floor;
+        remainders.push((i, scaled - Uint256::from(floor) * total_256));
+    }
+    remainders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut leftover = (amount - allocated).u128();
+    while leftover > 0 {
+        let mut progressed = false;
+        for &(i, _) in &remainders {
+            if leftover == 0 {
+                break;
+            }
+            if offers[i] >= totals[i] {
+                continue;
+            }
+            offers[i] += Uint128::one();
+            leftover -= 1;
+            progressed = true;
+        }
+        // Every item is already at capacity; the residual has nowhere left to go.
+        if !progressed {
+            break;
+        }
+    }
+
+    offers
+}
+
 #[cfg(test)]
 mod tests {
     use crate::exchange::testing::TestItem;
@@ -202,15 +305,43 @@ mod tests {
 
     #[test]
     fn vec_swappable_rounding_behaviour_same_price() {
-        // Totals: 1 + 1 + 1; Offer: 2 -> each target share is 2/3;
-        // total consumed = 0 because multiply_ratio always floors
+        // Totals: 1 + 1 + 1; Offer: 2 -> each target's floored share is 0, but the
+        // largest-remainder allocation spreads the 2 leftover units across two of the three
+        // pools instead of losing them to rounding.
         let mut v = vec![item(1), item(1), item(1)];
         let (consumed_offer, consumed_bids) = v.swap(Uint128::new(2)).unwrap();
 
-        assert_eq!(consumed_offer, Uint128::new(0));
-        assert_eq!(consumed_bids, Uint128::new(0));
+        assert_eq!(consumed_offer, Uint128::new(2));
+        assert_eq!(consumed_bids, Uint128::new(2));
+
+        let remaining: u128 = v.iter().map(|x| x.amount.u128()).sum();
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn vec_swappable_remainder_skips_pools_already_at_capacity() {
+        // Totals: 0 + 1 + 1; Offer: 2 -> the empty pool can never receive a remainder unit,
+        // so both leftover units must land on the two pools that still have capacity.
+        let mut v = vec![item(0), item(1), item(1)];
+        let (consumed_offer, consumed_bids) = v.swap(Uint128::new(2)).unwrap();
+
+        assert_eq!(consumed_offer, Uint128::new(2));
+        assert_eq!(consumed_bids, Uint128::new(2));
+        assert_eq!(v[0].amount, Uint128::new(0));
+        assert_eq!(v[1].amount, Uint128::new(0));
+        assert_eq!(v[2].amount, Uint128::new(0));
+    }
 
+    #[test]
+    fn vec_swappable_consumes_exactly_min_offer_and_total_capacity() {
+        // Totals: 2 + 2 + 2; Offer: 5 -> consumed_offer must equal min(5, 6) = 5 regardless of
+        // how the rounding remainder is distributed across the pools.
+        let mut v = vec![item(2), item(2), item(2)];
+        let (consumed_offer, consumed_bids) = v.swap(Uint128::new(5)).unwrap();
+
+        assert_eq!(consumed_offer, Uint128::new(5));
+        assert_eq!(consumed_bids, Uint128::new(5));
         let remaining: u128 = v.iter().map(|x| x.amount.u128()).sum();
-        assert_eq!(remaining, 3);
+        assert_eq!(remaining, 1);
     }
 }
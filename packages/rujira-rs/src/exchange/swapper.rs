@@ -1,17 +1,16 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Attribute, Decimal, Event, Storage, Uint128};
-use std::ops::Mul;
+use cosmwasm_std::{Addr, Attribute, Decimal, Event, Storage, Uint128};
 
-use crate::fin::SwapRequest;
+use crate::fin::{FillPolicy, SwapRequest};
 
-use super::{commitment::Commitment, error::SwapError, Swappable};
+use super::{commitment::Commitment, error::SwapError, FeeSchedule, Swappable};
 
 /// Executes a swap over an Iterator<Swappable>, consuming the offer and returning the returned amount
 #[cw_serde]
 pub struct Swapper<T> {
     event_prefix: String,
     events: Vec<Event>,
-    fee: Decimal,
+    fee_schedule: FeeSchedule,
     req: SwapRequest,
     consumed_offer: Uint128,
     remaining_offer: Uint128,
@@ -20,11 +19,16 @@ pub struct Swapper<T> {
 }
 
 impl<T: Swappable> Swapper<T> {
-    pub fn new(event_prefix: &str, offer: Uint128, req: SwapRequest, fee: Decimal) -> Self {
+    pub fn new(
+        event_prefix: &str,
+        offer: Uint128,
+        req: SwapRequest,
+        fee_schedule: FeeSchedule,
+    ) -> Self {
         Self {
             event_prefix: event_prefix.to_string(),
             events: vec![],
-            fee,
+            fee_schedule,
             req,
             consumed_offer: Uint128::zero(),
             remaining_offer: offer,
@@ -37,40 +41,28 @@ impl<T: Swappable> Swapper<T> {
     where
         T: std::fmt::Debug,
     {
-        for mut v in iter {
-            let (offer, bids) = v.swap(self.remaining_offer)?;
-
-            // If we've breached reached a SwapRequest::Limit, don't commit this step and break
-            if let SwapRequest::Limit { price: limit, .. } = self.req {
-                if !bids.is_zero() {
-                    let achieved = Decimal::from_ratio(offer, bids);
-                    if achieved > limit {
-                        break;
-                    }
-                }
-            }
-
-            let attrs = v.attributes();
-            self.events
-                .push(event(&v, &self.event_prefix, offer, bids, &attrs));
-            self.pending.push(v);
-            self.consumed_offer += offer;
-            self.remaining_offer -= offer;
-            self.returned += bids;
-            if self.remaining_offer.is_zero() {
-                break;
+        match self.req {
+            SwapRequest::Exact { exact_return, .. } => {
+                self.swap_for_exact_return(iter, exact_return)?
             }
+            _ => self.swap_for_exact_offer(iter)?,
         }
 
-        let fee = Decimal::from_ratio(self.returned, 1u128)
-            .mul(self.fee)
-            .to_uint_ceil();
+        let (net, fee, fee_breakdown) = self.fee_schedule.apply(self.returned);
+        self.returned = net;
 
-        self.returned -= fee;
+        for (recipient, amount) in fee_breakdown.iter() {
+            if !amount.is_zero() {
+                self.events
+                    .push(fee_event(&self.event_prefix, recipient, *amount));
+            }
+        }
 
         match self.req {
-            SwapRequest::Min { min_return, .. } => {
-                if self.returned < min_return {
+            SwapRequest::Min {
+                min_return, policy, ..
+            } => {
+                if policy == FillPolicy::FillOrKill && self.returned < min_return {
                     return Err(SwapError::InsufficientReturn {
                         expected: min_return,
                         returned: self.returned,
@@ -91,12 +83,144 @@ impl<T: Swappable> Swapper<T> {
         Ok(SwapResult {
             events: self.events.clone(),
             fee_amount: fee,
+            fee_breakdown,
             return_amount: self.returned,
             consumed_offer: self.consumed_offer,
             remaining_offer: self.remaining_offer,
         })
     }
 
+    /// Sell-side fill: walks `iter` consuming `remaining_offer` level by level until it's
+    /// exhausted or the iterator runs out. For `SwapRequest::Limit` and `SwapRequest::Min {
+    /// policy: PartialFill, .. }`, each level is filled via `Swappable::swap_up_to_price`
+    /// instead of `swap`. Its default, which every `Swappable` here uses (`CurvePool`
+    /// included), checks the level's `rate()` before the fill and then takes the level whole
+    /// or skips it; no partial slice of a level is committed. After a stop,
+    /// `consumed_offer + remaining_offer` still equals the original offer. The realized price
+    /// stays within `limit` for flat-rate levels; a level whose price moves during the fill is
+    /// filled with all the remaining offer once its starting rate clears, so its realized
+    /// price can pass `limit`.
+    fn swap_for_exact_offer(&mut self, iter: &mut dyn Iterator<Item = T>) -> Result<(), SwapError>
+    where
+        T: std::fmt::Debug,
+    {
+        let limit = self.partial_fill_limit();
+
+        for mut v in iter {
+            let Some(limit) = limit else {
+                let (offer, bids) = v.swap(self.remaining_offer)?;
+                self.commit_step(v, offer, bids);
+                if self.remaining_offer.is_zero() {
+                    break;
+                }
+                continue;
+            };
+
+            let capacity = v.total();
+            let (offer, bids) = v.swap_up_to_price(self.remaining_offer, limit)?;
+            if bids.is_zero() {
+                // Nothing in this level clears the limit; nor will any level after it, since
+                // the book is sorted from best price to worst.
+                break;
+            }
+
+            let partial = bids < capacity && offer < self.remaining_offer;
+            self.commit_step(v, offer, bids);
+            if partial || self.remaining_offer.is_zero() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// The limit `swap_for_exact_offer` stops at, in the same `offer / bids` terms as
+    /// `SwapRequest::Limit`'s `price`: that explicit `price`, or for `SwapRequest::Min { policy:
+    /// PartialFill, .. }` the original offer over `min_return` (still zero-consumed at this
+    /// point, so `consumed_offer + remaining_offer` gives it), or `None` if `min_return` is zero.
+    fn partial_fill_limit(&self) -> Option<Decimal> {
+        match self.req {
+            SwapRequest::Limit { price, .. } => Some(price),
+            SwapRequest::Min {
+                min_return,
+                policy: FillPolicy::PartialFill,
+                ..
+            } => {
+                let original_offer = self.consumed_offer + self.remaining_offer;
+                (!min_return.is_zero())
+                    .then(|| Decimal::from_ratio(original_offer, min_return))
+            }
+            _ => None,
+        }
+    }
+
+    /// Buy-side fill: walks `iter` consuming only as much offer as needed to reach
+    /// `exact_return`'s gross-of-fee target, stopping mid-level via
+    /// `Swappable::swap_for_output` rather than exhausting a level's full capacity. Still
+    /// respects `remaining_offer` as a hard cap - a level that would need more offer than is
+    /// left just consumes what remains instead, and the shortfall surfaces as the usual
+    /// `SwapError::InsufficientReturn` once `swap` deducts the fee and checks the total.
+    fn swap_for_exact_return(
+        &mut self,
+        iter: &mut dyn Iterator<Item = T>,
+        exact_return: Uint128,
+    ) -> Result<(), SwapError>
+    where
+        T: std::fmt::Debug,
+    {
+        let mut remaining_target = self.gross_up(exact_return);
+
+        for mut v in iter {
+            if remaining_target.is_zero() || self.remaining_offer.is_zero() {
+                break;
+            }
+
+            let rate = v.rate();
+            let estimated_offer = if rate.is_zero() {
+                self.remaining_offer
+            } else {
+                Decimal::from_ratio(remaining_target, 1u128)
+                    .checked_div(rate)
+                    .map(|d| d.to_uint_ceil())
+                    .unwrap_or(self.remaining_offer)
+            };
+            let (offer, bids) = if estimated_offer >= self.remaining_offer {
+                // Not enough offer left to reach the target through this level - take what
+                // remains and let the post-loop exact-return check report the shortfall.
+                v.swap(self.remaining_offer)?
+            } else {
+                v.swap_for_output(remaining_target)?
+            };
+
+            remaining_target = remaining_target.saturating_sub(bids);
+            self.commit_step(v, offer, bids);
+        }
+        Ok(())
+    }
+
+    /// Grosses `net` up by the fee schedule's combined rate, the inverse of `swap`'s final
+    /// `fee_schedule.apply` step, so the buy-side loop can target the pre-fee amount the levels
+    /// actually need to produce.
+    fn gross_up(&self, net: Uint128) -> Uint128 {
+        let total_rate = self.fee_schedule.total_rate();
+        if total_rate.is_zero() {
+            return net;
+        }
+        Decimal::from_ratio(net, 1u128)
+            .checked_div(Decimal::one() - total_rate)
+            .map(|d| d.to_uint_ceil())
+            .unwrap_or(net)
+    }
+
+    fn commit_step(&mut self, v: T, offer: Uint128, bids: Uint128) {
+        let attrs = v.attributes();
+        self.events
+            .push(event(&v, &self.event_prefix, offer, bids, &attrs));
+        self.pending.push(v);
+        self.consumed_offer += offer;
+        self.remaining_offer -= offer;
+        self.returned += bids;
+    }
+
     pub fn commit(&self, storage: &mut dyn Storage) -> Result<Commitment, SwapError> {
         let mut res = Commitment::default();
         for pool in self.pending.iter() {
@@ -121,10 +245,20 @@ pub fn event<T: Swappable>(
         .add_attributes(attributes.to_owned())
 }
 
+/// One `{prefix}/fee` event per fee recipient, so downstream splitter contracts can route
+/// payouts without re-deriving the split from `fee_amount` and the schedule.
+fn fee_event(prefix: &str, recipient: &Addr, amount: Uint128) -> Event {
+    Event::new(format!("{prefix}/fee"))
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
 #[derive(Debug)]
 pub struct SwapResult {
     pub events: Vec<Event>,
     pub fee_amount: Uint128,
+    /// Each fee recipient's cut of `fee_amount`, in schedule order.
+    pub fee_breakdown: Vec<(Addr, Uint128)>,
     pub return_amount: Uint128,
     pub consumed_offer: Uint128,
     pub remaining_offer: Uint128,
@@ -141,9 +275,13 @@ mod tests {
     use super::*;
     use std::str::FromStr;
 
+    fn fee_schedule(rate: Decimal) -> FeeSchedule {
+        FeeSchedule::new(vec![(Addr::unchecked("protocol"), rate)]).unwrap()
+    }
+
     #[test]
     fn test_swap_execution() {
-        let fee = Decimal::from_str("0.001").unwrap();
+        let fee = fee_schedule(Decimal::from_str("0.001").unwrap());
         let mut iter = TestIter::new(vec![
             (Decimal::from_str("1.0").unwrap(), Uint128::from(1000u128)),
             (Decimal::from_str("0.95").unwrap(), Uint128::from(1000u128)),
@@ -248,12 +386,13 @@ mod tests {
 
     #[test]
     fn test_swap_variants() {
-        let fee = Decimal::from_str("0.001").unwrap();
+        let fee = fee_schedule(Decimal::from_str("0.001").unwrap());
         for (offer, req, result) in vec![
             (
                 Uint128::from(900u128),
                 SwapRequest::Min {
                     min_return: Uint128::from(1000u128),
+                    policy: FillPolicy::FillOrKill,
                     to: None,
                     callback: None,
                 },
@@ -263,6 +402,7 @@ mod tests {
                 Uint128::from(1100u128),
                 SwapRequest::Min {
                     min_return: Uint128::from(1000u128),
+                    policy: FillPolicy::FillOrKill,
                     to: None,
                     callback: None,
                 },
@@ -324,6 +464,36 @@ mod tests {
                     Uint128::from(5661u128),
                 )),
             ),
+            (
+                // A PartialFill with no min_return shortfall that can't be met even by the best
+                // level: the implied limit (900 / 1000 = 0.9 offer per bid) is below the top
+                // level's price (1.0), so it
+                // halts immediately instead of erroring like FillOrKill would have.
+                Uint128::from(900u128),
+                SwapRequest::Min {
+                    min_return: Uint128::from(1000u128),
+                    policy: FillPolicy::PartialFill,
+                    to: None,
+                    callback: None,
+                },
+                Some((Uint128::zero(), Uint128::zero(), Uint128::from(900u128))),
+            ),
+            (
+                // Same implied limit (1 / 0.85) as the inverted Limit case above, so it halts at
+                // the same level; only the refunded remainder differs since less was offered.
+                Uint128::from(8500u128),
+                SwapRequest::Min {
+                    min_return: Uint128::from(7225u128),
+                    policy: FillPolicy::PartialFill,
+                    to: None,
+                    callback: None,
+                },
+                Some((
+                    Uint128::from(3996u128),
+                    Uint128::from(4u128),
+                    Uint128::from(4161u128),
+                )),
+            ),
         ] {
             let mut iter = TestIter::new(vec![
                 (Decimal::from_str("1.0").unwrap(), Uint128::from(1000u128)),
@@ -335,7 +505,7 @@ mod tests {
                 (Decimal::from_str("0.6").unwrap(), Uint128::from(1000u128)),
             ]);
 
-            let mut s = Swapper::new("some-prefix", offer, req, fee);
+            let mut s = Swapper::new("some-prefix", offer, req, fee.clone());
             let res = s.swap(&mut iter);
             match result {
                 Some((returned, fee, remaining)) => {
@@ -350,4 +520,31 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_swap_exact_return_crosses_a_level_boundary_and_refunds_dust() {
+        let mut iter = TestIter::new(vec![
+            (Decimal::from_str("1.0").unwrap(), Uint128::from(1000u128)),
+            (Decimal::from_str("0.5").unwrap(), Uint128::from(1000u128)),
+        ]);
+
+        let mut s = Swapper::new(
+            "some-prefix",
+            Uint128::from(3000u128),
+            SwapRequest::Exact {
+                exact_return: Uint128::from(1500u128),
+                to: None,
+                callback: None,
+            },
+            FeeSchedule::default(),
+        );
+        let res = s.swap(&mut iter).unwrap();
+
+        // First level fills fully (1000 bids for 1000 offer), the second stops mid-level once
+        // the remaining 500 bids are reached (1000 of its own offer), leaving 1000 of the 3000
+        // sent unspent and refundable.
+        assert_eq!(res.return_amount, Uint128::from(1500u128));
+        assert_eq!(res.consumed_offer, Uint128::from(2000u128));
+        assert_eq!(res.remaining_offer, Uint128::from(1000u128));
+    }
 }
@@ -1,6 +1,6 @@
-use crate::{CallbackData, Layer1Asset};
+use crate::{CallbackData, Layer1Asset, Oracle, OracleError, Premiumable};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
+use cosmwasm_std::{Addr, Binary, Decimal, QuerierWrapper, Uint128};
 
 use super::{price::Price, side::Side, Denoms, Tick};
 
@@ -31,7 +31,67 @@ pub struct InstantiateMsg {
     pub fee_address: String,
 }
 
-pub type OrderTarget = (Side, Price, Option<Uint128>);
+/// A resting order's target side, price and size. `Peg` lets a market maker quote a spread
+/// around the oracle instead of an absolute `Price`, so it doesn't need to resubmit `Order`
+/// every time the oracle moves.
+#[cw_serde]
+pub enum OrderTarget {
+    /// Place/retarget a resting order at a fixed, absolute price.
+    Absolute {
+        side: Side,
+        price: Price,
+        offer_amount: Option<Uint128>,
+    },
+    /// Place/retarget a resting order at `premium`/`discount` percent away from the pair's
+    /// oracle price, re-derived on every `Order` call (see `resolve`).
+    Peg {
+        side: Side,
+        /// Whole percentage points off the oracle mid; a `Side::Base` (bid) order rests this
+        /// far below it, a `Side::Quote` (ask) order this far above it.
+        premium: u8,
+        offer_amount: Option<Uint128>,
+    },
+}
+
+impl OrderTarget {
+    /// Resolves `self` to an absolute `(Side, Price, Option<Uint128>)` ready for order
+    /// placement. `Absolute` targets pass through unchanged; `Peg` targets read `oracles`'
+    /// `oracle_price`, derive a rate off it (`Premiumable::to_rate`'s discount for a
+    /// `Side::Base` bid, the same percentage as a markup for a `Side::Quote` ask), and truncate the
+    /// result through `tick` - the same truncation `Absolute` order placement already applies.
+    pub fn resolve(
+        &self,
+        q: QuerierWrapper,
+        oracles: &[Layer1Asset; 2],
+        tick: &Tick,
+    ) -> Result<(Side, Price, Option<Uint128>), OracleError> {
+        match self {
+            OrderTarget::Absolute {
+                side,
+                price,
+                offer_amount,
+            } => Ok((side.clone(), price.clone(), *offer_amount)),
+            OrderTarget::Peg {
+                side,
+                premium,
+                offer_amount,
+            } => {
+                let oracle = oracles.oracle_price(q)?;
+                let rate = match side {
+                    Side::Base => premium.to_rate(&oracle),
+                    Side::Quote => {
+                        oracle * Decimal::from_ratio(100u16 + u16::from(*premium), 100u16)
+                    }
+                };
+                let price = match side {
+                    Side::Base => tick.truncate_floor(&rate),
+                    Side::Quote => tick.truncate_ceil(&rate),
+                };
+                Ok((side.clone(), Price::from(price), *offer_amount))
+            }
+        }
+    }
+}
 
 /// Callable interfaces
 #[cw_serde]
@@ -61,6 +121,23 @@ pub enum ExecuteMsg {
     DoOrder((Addr, (Vec<OrderTarget>, Option<CallbackData>))),
 }
 
+/// How a `SwapRequest::Min` behaves when the book can't fully satisfy `min_return`.
+#[cw_serde]
+#[derive(Copy)]
+pub enum FillPolicy {
+    /// Consume nothing and error if the aggregate return would fall short of `min_return`.
+    FillOrKill,
+    /// Fill as much of the offer as clears the price implied by `min_return` over the original
+    /// offer, refunding whatever's left unconsumed instead of erroring.
+    PartialFill,
+}
+
+impl Default for FillPolicy {
+    fn default() -> Self {
+        Self::FillOrKill
+    }
+}
+
 #[cw_serde]
 #[serde(untagged)]
 pub enum SwapRequest {
@@ -69,9 +146,12 @@ pub enum SwapRequest {
         to: Option<String>,
         callback: Option<CallbackData>,
     },
-    /// Return at least `min_return` or fail
+    /// Return at least `min_return` or fail, per `policy`
     Min {
         min_return: Uint128,
+        /// Defaults to `FillOrKill`, matching this variant's original all-or-nothing behavior.
+        #[serde(default)]
+        policy: FillPolicy,
         to: Option<String>,
         callback: Option<CallbackData>,
     },
@@ -0,0 +1,171 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Coin, Decimal, Uint128};
+
+use super::Side;
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Previews the result of `Swap` against the current resting order book, without moving
+    /// any funds. Lets front-ends and arb callers (the `Arb`/`DoSwap` path) size a trade before
+    /// committing to a dry-run transaction.
+    #[returns(SimulateSwapResponse)]
+    SimulateSwap {
+        offer: Coin,
+        side: Side,
+        limit_price: Option<Decimal>,
+    },
+}
+
+#[cw_serde]
+pub struct SimulateSwapResponse {
+    pub return_amount: Uint128,
+    pub offer_consumed: Uint128,
+    pub effective_price: Decimal,
+    pub price_impact: Decimal,
+    pub levels_touched: u32,
+}
+
+/// Pure traversal backing `QueryMsg::SimulateSwap`. `book` is the resting order book on the
+/// side opposite `side`, sorted best price first, with quantities already truncated through the
+/// `Tick` used at order placement. Fills `offer` level by level exactly as `Swap` would: for
+/// `Side::Base` each level yields `filled * price` of quote, for `Side::Quote` it yields
+/// `filled / price` of base, `fee_taker` is deducted from each level's output as it's filled,
+/// and traversal stops at the first of `offer` exhausted, the book running out, or `limit_price`
+/// being crossed.
+pub fn simulate_swap(
+    book: &[(Decimal, Uint128)],
+    side: &Side,
+    offer: Uint128,
+    fee_taker: Decimal,
+    limit_price: Option<Decimal>,
+) -> SimulateSwapResponse {
+    let best_price = book.first().map(|(p, _)| *p).unwrap_or_default();
+
+    let mut remaining = offer;
+    let mut output = Uint128::zero();
+    let mut consumed = Uint128::zero();
+    let mut levels_touched = 0u32;
+
+    for (price, quantity) in book {
+        if remaining.is_zero() {
+            break;
+        }
+
+        if let Some(limit) = limit_price {
+            let crossed = match side {
+                Side::Base => *price < limit,
+                Side::Quote => price.inv().map(|inv| inv < limit).unwrap_or(true),
+            };
+            if crossed {
+                break;
+            }
+        }
+
+        let filled = remaining.min(*quantity);
+        let filled_output = match side {
+            Side::Base => filled.mul_floor(*price),
+            Side::Quote => filled.mul_floor(price.inv().unwrap_or_default()),
+        };
+        let fee = filled_output.mul_ceil(fee_taker);
+
+        output += filled_output - fee;
+        consumed += filled;
+        remaining -= filled;
+        levels_touched += 1;
+    }
+
+    let effective_price = if consumed.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(output, consumed)
+    };
+
+    let price_impact = if best_price.is_zero() {
+        Decimal::zero()
+    } else {
+        effective_price
+            .abs_diff(best_price)
+            .checked_div(best_price)
+            .unwrap_or(Decimal::one())
+    };
+
+    SimulateSwapResponse {
+        return_amount: output,
+        offer_consumed: consumed,
+        effective_price,
+        price_impact,
+        levels_touched,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn book(levels: &[(&str, u128)]) -> Vec<(Decimal, Uint128)> {
+        levels
+            .iter()
+            .map(|(p, q)| (Decimal::from_str(p).unwrap(), Uint128::from(*q)))
+            .collect()
+    }
+
+    #[test]
+    fn fills_across_levels_and_charges_fee_per_level() {
+        let book = book(&[("2", 100), ("1.9", 100)]);
+        let fee = Decimal::from_str("0.01").unwrap();
+
+        let res = simulate_swap(&book, &Side::Base, Uint128::from(150u128), fee, None);
+
+        assert_eq!(res.offer_consumed, Uint128::from(150u128));
+        assert_eq!(res.levels_touched, 2);
+        // 100 * 2 * 0.99 + 50 * 1.9 * 0.99 = 198 + 94.05 = 292.05, rounded down per level
+        assert_eq!(res.return_amount, Uint128::from(292u128));
+    }
+
+    #[test]
+    fn stops_when_offer_exhausted_before_book_runs_out() {
+        let book = book(&[("2", 1000)]);
+
+        let res = simulate_swap(
+            &book,
+            &Side::Base,
+            Uint128::from(10u128),
+            Decimal::zero(),
+            None,
+        );
+
+        assert_eq!(res.offer_consumed, Uint128::from(10u128));
+        assert_eq!(res.return_amount, Uint128::from(20u128));
+        assert_eq!(res.levels_touched, 1);
+    }
+
+    #[test]
+    fn stops_at_limit_price() {
+        let book = book(&[("2", 100), ("1", 100)]);
+
+        let res = simulate_swap(
+            &book,
+            &Side::Base,
+            Uint128::from(150u128),
+            Decimal::zero(),
+            Some(Decimal::from_str("1.5").unwrap()),
+        );
+
+        // The second level's price of 1 is below the limit, so it's never touched
+        assert_eq!(res.offer_consumed, Uint128::from(100u128));
+        assert_eq!(res.levels_touched, 1);
+    }
+
+    #[test]
+    fn empty_book_returns_nothing() {
+        let res = simulate_swap(&[], &Side::Base, Uint128::from(100u128), Decimal::zero(), None);
+
+        assert_eq!(res.return_amount, Uint128::zero());
+        assert_eq!(res.offer_consumed, Uint128::zero());
+        assert_eq!(res.effective_price, Decimal::zero());
+        assert_eq!(res.levels_touched, 0);
+    }
+}
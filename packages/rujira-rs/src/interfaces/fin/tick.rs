@@ -1,111 +1,243 @@
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal, Fraction, Uint128};
-use thiserror::Error;
-
-#[cw_serde]
-pub struct Tick(u8);
-
-impl Tick {
-    pub fn new(size: u8) -> Self {
-        Self(size)
-    }
-
-    pub fn validate(&self) -> Result<(), TickError> {
-        if self.0 == 0 {
-            return Err(TickError::Invalid(self.0));
-        }
-        Ok(())
-    }
-
-    pub fn validate_price(&self, v: &Decimal) -> Result<(), TickError> {
-        if v.is_zero() {
-            return Err(TickError::InvalidPrice {
-                price: *v,
-                tick: self.0,
-            });
-        }
-
-        if v.inv().is_none() {
-            return Err(TickError::InvalidPrice {
-                price: *v,
-                tick: self.0,
-            });
-        }
-
-        if v == self.truncate_floor(v) {
-            return Ok(());
-        }
-        Err(TickError::InvalidPrice {
-            price: *v,
-            tick: self.0,
-        })
-    }
-
-    pub fn truncate_floor(&self, v: &Decimal) -> Decimal {
-        self.do_truncate(v, |x, y| x.mul_floor(y))
-    }
-
-    pub fn truncate_ceil(&self, v: &Decimal) -> Decimal {
-        self.do_truncate(v, |x, y| x.mul_ceil(y))
-    }
-
-    fn do_truncate<F>(&self, v: &Decimal, fn_trunc: F) -> Decimal
-    where
-        F: Fn(Uint128, Decimal) -> Uint128,
-    {
-        let int = v.numerator();
-        let len = int.to_string().as_str().bytes().len() as u32;
-        let decimals: u32 = len - self.0 as u32;
-        let pow = Uint128::from(10u128).pow(decimals);
-        let truncated = fn_trunc(Uint128::one(), Decimal::from_ratio(int, pow));
-        Decimal::from_ratio(truncated * pow, v.denominator())
-    }
-}
-
-#[derive(Error, Debug)]
-pub enum TickError {
-    #[error("Invalid Tick {0}")]
-    Invalid(u8),
-
-    #[error("Invalid Price {price} for Tick {tick}")]
-    InvalidPrice { price: Decimal, tick: u8 },
-}
-
-#[cfg(test)]
-mod tests {
-
-    use std::str::FromStr;
-
-    use super::*;
-
-    #[test]
-    fn decimal() {
-        let tick = Tick::new(2u8);
-
-        tick.validate_price(&Decimal::from_str("123").unwrap())
-            .unwrap_err();
-
-        tick.validate_price(&Decimal::from_str("12").unwrap())
-            .unwrap();
-        tick.validate_price(&Decimal::from_str("12.3").unwrap())
-            .unwrap_err();
-        tick.validate_price(&Decimal::from_str("1.2").unwrap())
-            .unwrap();
-
-        tick.validate_price(&Decimal::from_str("0.00000123").unwrap())
-            .unwrap_err();
-
-        assert_eq!(
-            tick.truncate_floor(&Decimal::from_str("0.00000123").unwrap()),
-            Decimal::from_str("0.0000012").unwrap()
-        );
-
-        assert_eq!(
-            tick.truncate_floor(&Decimal::from_str("0.00000129").unwrap()),
-            Decimal::from_str("0.0000012").unwrap()
-        );
-
-        tick.validate_price(&Decimal::from_str("0.00012").unwrap())
-            .unwrap();
-    }
-}
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Fraction, Uint128};
+use thiserror::Error;
+
+#[cw_serde]
+pub struct Tick(u8);
+
+impl Tick {
+    pub fn new(size: u8) -> Self {
+        Self(size)
+    }
+
+    pub fn validate(&self) -> Result<(), TickError> {
+        if self.0 == 0 {
+            return Err(TickError::Invalid(self.0));
+        }
+        Ok(())
+    }
+
+    pub fn validate_price(&self, v: &Decimal) -> Result<(), TickError> {
+        if v.is_zero() {
+            return Err(TickError::InvalidPrice {
+                price: *v,
+                tick: self.0,
+            });
+        }
+
+        if v.inv().is_none() {
+            return Err(TickError::InvalidPrice {
+                price: *v,
+                tick: self.0,
+            });
+        }
+
+        if v == self.truncate_floor(v) {
+            return Ok(());
+        }
+        Err(TickError::InvalidPrice {
+            price: *v,
+            tick: self.0,
+        })
+    }
+
+    pub fn truncate_floor(&self, v: &Decimal) -> Decimal {
+        self.do_truncate(v, |x, y| x.mul_floor(y))
+    }
+
+    pub fn truncate_ceil(&self, v: &Decimal) -> Decimal {
+        self.do_truncate(v, |x, y| x.mul_ceil(y))
+    }
+
+    fn do_truncate<F>(&self, v: &Decimal, fn_trunc: F) -> Decimal
+    where
+        F: Fn(Uint128, Decimal) -> Uint128,
+    {
+        let int = v.numerator();
+        let len = int.to_string().as_str().bytes().len() as u32;
+        let decimals: u32 = len - self.0 as u32;
+        let pow = Uint128::from(10u128).pow(decimals);
+        let truncated = fn_trunc(Uint128::one(), Decimal::from_ratio(int, pow));
+        Decimal::from_ratio(truncated * pow, v.denominator())
+    }
+}
+
+/// A Uniswap-style geometric tick scheme, giving uniform relative price spacing and a compact
+/// integer key for order placement, as an alternative to [`Tick`]'s significant-figure
+/// truncation. `price(i) = base^i`, so every adjacent pair of ticks differs by the same
+/// percentage regardless of magnitude.
+#[cw_serde]
+pub struct GeometricTick {
+    pub base: Decimal,
+}
+
+impl GeometricTick {
+    pub fn new(base: Decimal) -> Self {
+        Self { base }
+    }
+
+    pub fn validate(&self) -> Result<(), TickError> {
+        if self.base <= Decimal::one() {
+            return Err(TickError::InvalidBase(self.base));
+        }
+        Ok(())
+    }
+
+    /// `price(i) = base^i`, by binary exponentiation. Returns `None` if the result over- or
+    /// underflows `Decimal`.
+    pub fn index_to_price(&self, index: i32) -> Option<Decimal> {
+        let (mut sq, mut exp) = if index < 0 {
+            (self.base.inv()?, index.unsigned_abs())
+        } else {
+            (self.base, index as u32)
+        };
+        let mut result = Decimal::one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.checked_mul(sq).ok()?;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                sq = sq.checked_mul(sq).ok()?;
+            }
+        }
+        Some(result)
+    }
+
+    /// `floor(ln(price) / ln(base))`, computed without floating point. The magnitude is built
+    /// from its most significant bit down, keeping each bit whose `index_to_price` does not
+    /// exceed the price (or, below 1, its inverse); a price below 1 that is not itself a
+    /// ladder price steps one index further down, so both sides round toward negative infinity.
+    pub fn price_to_index(&self, price: &Decimal) -> i32 {
+        if *price >= Decimal::one() {
+            self.magnitude(price)
+        } else {
+            // price < 1 maps to a negative index; invert so the magnitude search always runs
+            // against a value >= 1
+            let index = -self.magnitude(&price.inv().unwrap_or(Decimal::MAX));
+            if self.index_to_price(index) == Some(*price) {
+                index
+            } else {
+                index - 1
+            }
+        }
+    }
+
+    fn magnitude(&self, price: &Decimal) -> i32 {
+        let mut index: i64 = 0;
+        for bit in (0..31).rev() {
+            let candidate = index + (1i64 << bit);
+            // `index_to_price` overflowing Decimal's range means the candidate is past any
+            // representable price, so the bit stays clear
+            if matches!(self.index_to_price(candidate as i32), Some(p) if p <= *price) {
+                index = candidate;
+            }
+        }
+        index.try_into().unwrap_or(i32::MAX)
+    }
+
+    /// Accepts a price only if it round-trips through `price_to_index`/`index_to_price` within
+    /// one ULP, tolerating `Decimal` rounding.
+    pub fn validate_price(&self, v: &Decimal) -> Result<(), TickError> {
+        if v.is_zero() {
+            return Err(TickError::InvalidGeometricPrice {
+                price: *v,
+                base: self.base,
+            });
+        }
+
+        let index = self.price_to_index(v);
+        let rebuilt = self.index_to_price(index);
+        let one_ulp = Decimal::raw(1);
+        match rebuilt {
+            Some(p) if p == *v || p.abs_diff(*v) <= one_ulp => Ok(()),
+            _ => Err(TickError::InvalidGeometricPrice {
+                price: *v,
+                base: self.base,
+            }),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TickError {
+    #[error("Invalid Tick {0}")]
+    Invalid(u8),
+
+    #[error("Invalid Price {price} for Tick {tick}")]
+    InvalidPrice { price: Decimal, tick: u8 },
+
+    #[error("Invalid Geometric Tick base {0}")]
+    InvalidBase(Decimal),
+
+    #[error("Invalid Price {price} for Geometric Tick base {base}")]
+    InvalidGeometricPrice { price: Decimal, base: Decimal },
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn decimal() {
+        let tick = Tick::new(2u8);
+
+        tick.validate_price(&Decimal::from_str("123").unwrap())
+            .unwrap_err();
+
+        tick.validate_price(&Decimal::from_str("12").unwrap())
+            .unwrap();
+        tick.validate_price(&Decimal::from_str("12.3").unwrap())
+            .unwrap_err();
+        tick.validate_price(&Decimal::from_str("1.2").unwrap())
+            .unwrap();
+
+        tick.validate_price(&Decimal::from_str("0.00000123").unwrap())
+            .unwrap_err();
+
+        assert_eq!(
+            tick.truncate_floor(&Decimal::from_str("0.00000123").unwrap()),
+            Decimal::from_str("0.0000012").unwrap()
+        );
+
+        assert_eq!(
+            tick.truncate_floor(&Decimal::from_str("0.00000129").unwrap()),
+            Decimal::from_str("0.0000012").unwrap()
+        );
+
+        tick.validate_price(&Decimal::from_str("0.00012").unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn geometric() {
+        let tick = GeometricTick::new(Decimal::from_str("1.0001").unwrap());
+        tick.validate().unwrap();
+
+        assert_eq!(tick.price_to_index(&Decimal::one()), 0);
+        assert_eq!(
+            tick.index_to_price(0).unwrap(),
+            Decimal::from_str("1").unwrap()
+        );
+
+        let price = tick.index_to_price(5).unwrap();
+        assert_eq!(tick.price_to_index(&price), 5);
+        tick.validate_price(&price).unwrap();
+
+        let price = tick.index_to_price(-5).unwrap();
+        assert_eq!(tick.price_to_index(&price), -5);
+        tick.validate_price(&price).unwrap();
+
+        // A price that doesn't sit on the tick ladder is rejected
+        tick.validate_price(&Decimal::from_str("1.00005").unwrap())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn rejects_base_not_above_one() {
+        GeometricTick::new(Decimal::one()).validate().unwrap_err();
+    }
+}
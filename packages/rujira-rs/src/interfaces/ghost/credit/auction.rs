@@ -0,0 +1,57 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, StdResult, Storage, Timestamp};
+use cw_storage_plus::Item;
+
+pub const LIQUIDATION_AUCTION: Item<LiquidationAuction> = Item::new("liquidation-auction");
+
+/// Tracks when an Account's `adjusted_ltv` first crossed into liquidatable territory, so
+/// free-form liquidations - those executed after the preference `messages` queue is exhausted -
+/// can widen their permitted discount the longer the position stays insolvent, rather than
+/// enforcing the flat `liquidation_max_slip` bound from the very first block.
+#[cw_serde]
+#[derive(Default)]
+pub struct LiquidationAuction {
+    pub opened_at: Option<Timestamp>,
+}
+
+impl LiquidationAuction {
+    /// Refresh the auction-open timestamp against the account's current health and return the
+    /// discount currently permitted for free-form liquidation:
+    /// `start + (max - start) * min(1, elapsed / duration)`.
+    ///
+    /// Opens the auction, if not already open, the first time `ltv` exceeds 1, and resets it
+    /// once `ltv` recovers below `liquidation_threshold`.
+    pub fn refresh(
+        storage: &mut dyn Storage,
+        now: Timestamp,
+        ltv: Decimal,
+        liquidation_threshold: Decimal,
+        start_discount: Decimal,
+        max_discount: Decimal,
+        duration: u64,
+    ) -> StdResult<Decimal> {
+        let mut auction = LIQUIDATION_AUCTION.may_load(storage)?.unwrap_or_default();
+
+        if ltv < liquidation_threshold {
+            auction.opened_at = None;
+        } else if ltv > Decimal::one() && auction.opened_at.is_none() {
+            auction.opened_at = Some(now);
+        }
+
+        LIQUIDATION_AUCTION.save(storage, &auction)?;
+
+        Ok(match auction.opened_at {
+            None => start_discount,
+            Some(opened_at) => {
+                let elapsed = now.seconds().saturating_sub(opened_at.seconds());
+                let progress = Decimal::from_ratio(elapsed.min(duration), duration.max(1));
+                let range = if max_discount > start_discount {
+                    max_discount - start_discount
+                } else {
+                    Decimal::zero()
+                };
+                start_discount + range * progress
+            }
+        })
+    }
+}
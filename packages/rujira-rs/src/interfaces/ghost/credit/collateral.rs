@@ -10,36 +10,68 @@ use crate::{OracleError, OracleValue, SecuredAsset, SecuredAssetError};
 #[cw_serde]
 pub enum Collateral {
     Coin(Coin),
+    /// A basket of coins, each carrying an explicit weight in `[0, 1]`; weights must sum to
+    /// exactly one, enforced by `validate`. Lets the borrow side accept a diversified set of
+    /// secured assets as a single collateral position rather than one coin at a time.
+    Basket(Vec<(Coin, Decimal)>),
 }
 
 impl Collateral {
+    /// `self` as `(coin, weight)` pairs, with a bare `Coin` treated as a single-entry basket
+    /// weighted `1`.
+    fn weighted_coins(&self) -> Vec<(Coin, Decimal)> {
+        match self {
+            Collateral::Coin(coin) => vec![(coin.clone(), Decimal::one())],
+            Collateral::Basket(entries) => entries.clone(),
+        }
+    }
+
+    /// Weights must sum to exactly one; a `Coin` is trivially valid.
+    pub fn validate(&self) -> Result<(), CollateralError> {
+        match self {
+            Collateral::Coin(_) => Ok(()),
+            Collateral::Basket(entries) => {
+                let total = entries
+                    .iter()
+                    .fold(Decimal::zero(), |agg, (_, weight)| agg + weight);
+                if total != Decimal::one() {
+                    return Err(CollateralError::InvalidBasketWeights {});
+                }
+                Ok(())
+            }
+        }
+    }
+
     pub fn value_adjusted(
         &self,
         deps: Deps,
         ratios: &BTreeMap<String, Decimal>,
     ) -> Result<Decimal, CollateralError> {
-        self.balance()
-            .into_vec()
+        self.weighted_coins()
             .iter()
-            .try_fold(Decimal::zero(), |agg, v| {
-                Ok(v.value_usd(deps.querier)?
-                    .mul(ratios.get(&v.denom).copied().unwrap_or_default())
+            .try_fold(Decimal::zero(), |agg, (coin, weight)| {
+                Ok(coin
+                    .value_usd(deps.querier)?
+                    .mul(ratios.get(&coin.denom).copied().unwrap_or_default())
+                    .mul(*weight)
                     .add(agg))
             })
     }
 
     pub fn balance(&self) -> NativeBalance {
-        match self {
-            Collateral::Coin(coin) => NativeBalance(vec![coin.clone()]),
-        }
+        self.weighted_coins()
+            .into_iter()
+            .fold(NativeBalance(vec![]), |acc, (coin, _)| acc.add(coin))
     }
 }
 
 impl OracleValue for Collateral {
     fn value_usd(&self, q: cosmwasm_std::QuerierWrapper) -> Result<Decimal, OracleError> {
-        match self {
-            Collateral::Coin(coin) => Ok(coin.value_usd(q)?),
-        }
+        self.weighted_coins()
+            .iter()
+            .try_fold(Decimal::zero(), |agg, (coin, weight)| {
+                Ok(coin.value_usd(q)?.mul(*weight).add(agg))
+            })
     }
 }
 
@@ -47,6 +79,16 @@ impl Display for Collateral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Collateral::Coin(coin) => write!(f, "coin:{}", coin),
+            Collateral::Basket(entries) => {
+                write!(f, "basket:[")?;
+                for (i, (coin, weight)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}@{}", coin, weight)?;
+                }
+                write!(f, "]")
+            }
         }
     }
 }
@@ -70,4 +112,6 @@ pub enum CollateralError {
     Oracle(#[from] OracleError),
     #[error("{0}")]
     SecuredAsset(#[from] SecuredAssetError),
+    #[error("Collateral basket weights must sum to one")]
+    InvalidBasketWeights {},
 }
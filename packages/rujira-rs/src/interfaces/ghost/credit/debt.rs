@@ -1,9 +1,9 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{coins, Coin, Decimal};
+use cosmwasm_std::{coins, Coin, Decimal, QuerierWrapper, Storage, Uint128};
 use cw_utils::NativeBalance;
 use thiserror::Error;
 
-use crate::{ghost::vault::DelegateResponse, OracleError, OracleValue};
+use crate::{ghost::vault::DelegateResponse, EmaOracle, OracleError, OracleValue};
 
 #[cw_serde]
 pub struct Debt(DelegateResponse);
@@ -19,6 +19,31 @@ impl Debt {
     pub fn can_accept(&self, coin: &Coin) -> bool {
         coin.denom == self.0.borrower.denom && coin.amount.le(&self.0.current)
     }
+
+    /// EMA-smoothed USD value of the debt, guarded against staleness. Borrow and liquidation
+    /// checks should prefer this over the instantaneous `value_usd` so a single-block pool
+    /// manipulation can't swing a borrower's valuation into an unfair liquidation.
+    pub fn value_usd_ema(
+        &self,
+        storage: &mut dyn Storage,
+        q: QuerierWrapper,
+        height: u64,
+        smoothing_window: u64,
+        max_staleness_blocks: u64,
+    ) -> Result<Decimal, OracleError> {
+        let spot = self.value_usd(q)?;
+        EmaOracle::refresh(storage, spot, height, smoothing_window, max_staleness_blocks)
+    }
+
+    /// The most this debt could be repaid by a single `Liquidate` call this block: either
+    /// `close_factor * current`, or the full outstanding balance if that's below
+    /// `dust_closeout`, so no economically unrecoverable fragment of debt is left behind
+    pub fn max_repayable(&self, close_factor: Decimal, dust_closeout: Uint128) -> Uint128 {
+        if self.0.current <= dust_closeout {
+            return self.0.current;
+        }
+        self.0.current.mul_floor(close_factor).min(self.0.current)
+    }
 }
 
 impl OracleValue for Debt {
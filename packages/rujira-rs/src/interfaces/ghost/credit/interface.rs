@@ -2,7 +2,8 @@ use std::collections::BTreeMap;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg,
+    coin, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, StdResult, Timestamp, Uint128,
+    WasmMsg,
 };
 use cw_utils::NativeBalance;
 
@@ -22,6 +23,25 @@ pub struct InstantiateMsg {
     pub liquidation_threshold: Decimal,
     /// The maximum collteralization ratio that an Account owner can manually adjust to
     pub adjustment_threshold: Decimal,
+    /// The number of blocks over which debt/collateral EMA valuations fully catch up to the
+    /// latest spot reading
+    pub ema_smoothing_window_blocks: u64,
+    /// The maximum age, in blocks, of an EMA valuation before borrow/liquidation checks must
+    /// reject it as stale
+    pub ema_max_staleness_blocks: u64,
+    /// The maximum fraction of a debt's value that a single `Liquidate` call may repay, eg 0.5
+    /// repays at most half the outstanding debt per call
+    pub liquidation_close_factor: Decimal,
+    /// Below this remaining balance, in the debt token's base units, `close_factor` is waived
+    /// and the debt may be closed out in full, so no unrecoverable dust is left behind
+    pub dust_closeout: Uint128,
+    /// The discount permitted for free-form liquidation the instant an account's auction opens
+    pub auction_start_discount: Decimal,
+    /// The discount permitted once an account's auction has run for `auction_duration`
+    pub auction_max_discount: Decimal,
+    /// Seconds over which the free-form liquidation discount ramps from `auction_start_discount`
+    /// to `auction_max_discount`
+    pub auction_duration: u64,
 }
 
 #[cw_serde]
@@ -109,6 +129,13 @@ pub enum LiquidateMsg {
         msg: Binary,
         funds: Vec<Coin>,
     },
+    /// Route up to `max_collateral` through a liquidation-queue contract's pre-committed
+    /// discount-pool bids, filling the lowest-discount non-empty pool first. Deterministic and
+    /// bounded by the pool's fixed discount, so it always respects `liquidation_max_slip`
+    Queue {
+        contract: String,
+        max_collateral: Coin,
+    },
 }
 
 #[cw_serde]
@@ -134,6 +161,13 @@ pub struct ConfigUpdate {
     pub liquidation_max_slip: Option<Decimal>,
     pub liquidation_threshold: Option<Decimal>,
     pub adjustment_threshold: Option<Decimal>,
+    pub ema_smoothing_window_blocks: Option<u64>,
+    pub ema_max_staleness_blocks: Option<u64>,
+    pub liquidation_close_factor: Option<Decimal>,
+    pub dust_closeout: Option<Uint128>,
+    pub auction_start_discount: Option<Decimal>,
+    pub auction_max_discount: Option<Decimal>,
+    pub auction_duration: Option<u64>,
 }
 
 #[cw_serde]
@@ -182,6 +216,13 @@ pub struct ConfigResponse {
     pub liquidation_max_slip: Decimal,
     pub liquidation_threshold: Decimal,
     pub adjustment_threshold: Decimal,
+    pub ema_smoothing_window_blocks: u64,
+    pub ema_max_staleness_blocks: u64,
+    pub liquidation_close_factor: Decimal,
+    pub dust_closeout: Uint128,
+    pub auction_start_discount: Decimal,
+    pub auction_max_discount: Decimal,
+    pub auction_duration: u64,
 }
 
 #[cw_serde]
@@ -198,6 +239,9 @@ pub struct AccountResponse {
     pub debts: Vec<DebtResponse>,
     pub ltv: Decimal,
     pub liquidation_preferences: LiquidationPreferences,
+    /// When this account's Dutch-auction liquidation discount started ramping, ie the first
+    /// time `ltv` exceeded 1 since it last recovered below `liquidation_threshold`
+    pub auction_opened_at: Option<Timestamp>,
 }
 
 #[cw_serde]
@@ -211,6 +255,9 @@ pub struct CollateralResponse {
 pub struct DebtResponse {
     pub debt: super::Debt,
     pub value: Decimal,
+    /// The most this debt could be repaid by a single `Liquidate` call this block: either
+    /// `close_factor` times the outstanding balance, or the full outstanding balance if that's below `dust_closeout`
+    pub max_repayable: Uint128,
 }
 
 #[cw_serde]
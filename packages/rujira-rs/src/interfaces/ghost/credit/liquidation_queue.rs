@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Uint128};
+use thiserror::Error;
+
+/// A single discount tier of the `LiquidateMsg::Queue` backstop. Bidders deposit the debt
+/// denom here; when the pool is filled, `collateral_value * (1 - discount)` of their deposits
+/// is burned and they're credited a claim on `collateral_value` worth of the liquidated
+/// collateral, denominated
+/// in debt terms so the caller can convert it to a collateral [`Coin`](cosmwasm_std::Coin) via
+/// the oracle price.
+#[cw_serde]
+#[derive(Default)]
+pub struct DiscountPool {
+    /// Discount applied to collateral value when this pool is filled, eg `0.05` means bidders
+    /// receive collateral at a 5% discount to its oracle value
+    pub discount: Decimal,
+    /// Reject new deposits while closed, to let a pool bootstrap liquidity before it's live
+    pub closed: bool,
+    /// Debt-denom balance contributed by each bidder, used to pro-rate fills
+    pub bidders: BTreeMap<Addr, Uint128>,
+    /// Total debt-denom balance available to this pool; the sum of `bidders` values
+    pub balance: Uint128,
+}
+
+impl DiscountPool {
+    pub fn new(discount: Decimal) -> Self {
+        Self {
+            discount,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_closed(&mut self, closed: bool) {
+        self.closed = closed;
+    }
+
+    pub fn deposit(&mut self, bidder: Addr, amount: Uint128) -> Result<(), DiscountPoolError> {
+        if self.closed {
+            return Err(DiscountPoolError::Closed);
+        }
+        *self.bidders.entry(bidder).or_default() += amount;
+        self.balance += amount;
+        Ok(())
+    }
+
+    /// Burn up to `collateral_value` (pre-discount, debt terms) worth of debt token from this
+    /// pool's balance, crediting bidders pro-rata. Returns the amount burned and each bidder's
+    /// discounted collateral-value claim; any rounding remainder from the pro-rata split is
+    /// assigned to the first bidder. Returns an empty fill if the pool is empty or exhausted.
+    pub fn fill(&mut self, collateral_value: Decimal) -> (Uint128, Vec<(Addr, Decimal)>) {
+        if self.balance.is_zero() || collateral_value.is_zero() {
+            return (Uint128::zero(), vec![]);
+        }
+
+        let debt_for_value = collateral_value
+            .checked_mul(Decimal::one() - self.discount.min(Decimal::one()))
+            .unwrap_or(collateral_value)
+            .to_uint_floor();
+        let burned = self.balance.min(debt_for_value);
+        if burned.is_zero() {
+            return (Uint128::zero(), vec![]);
+        }
+
+        let value_filled = Decimal::one() - self.discount.min(Decimal::one());
+        let value_filled = if value_filled.is_zero() {
+            Decimal::zero()
+        } else {
+            Decimal::from_ratio(burned, Uint128::one()).checked_div(value_filled).unwrap_or_default()
+        };
+
+        let mut credits = Vec::with_capacity(self.bidders.len());
+        let mut allocated = Uint128::zero();
+        let bidders: Vec<_> = self.bidders.iter().map(|(a, b)| (a.clone(), *b)).collect();
+        for (bidder, share) in &bidders {
+            let debt_share = burned.multiply_ratio(*share, self.balance);
+            allocated += debt_share;
+            *self.bidders.get_mut(bidder).unwrap() -= debt_share.min(*share);
+            if !debt_share.is_zero() {
+                credits.push((
+                    bidder.clone(),
+                    value_filled.multiply_ratio(debt_share, burned),
+                ));
+            }
+        }
+        // Assign the pro-rata rounding dust to the first bidder, same convention used
+        // elsewhere for fee-splitter remainders
+        if let (Some((bidder, _)), true) = (bidders.first(), allocated < burned) {
+            let dust = burned - allocated;
+            *self.bidders.get_mut(bidder).unwrap() -= dust.min(self.bidders[bidder]);
+            if let Some((_, value)) = credits.iter_mut().find(|(b, _)| b == bidder) {
+                *value += value_filled.multiply_ratio(dust, burned);
+            } else {
+                credits.push((bidder.clone(), value_filled.multiply_ratio(dust, burned)));
+            }
+        }
+
+        self.balance -= burned;
+        self.bidders.retain(|_, amount| !amount.is_zero());
+        (burned, credits)
+    }
+}
+
+/// The full set of discount pools backstopping a credit account's liquidations, keyed by
+/// discount in basis points so eg pool `500` offers a 5% discount.
+#[cw_serde]
+#[derive(Default)]
+pub struct LiquidationQueue {
+    pub pools: BTreeMap<u32, DiscountPool>,
+}
+
+impl LiquidationQueue {
+    pub fn deposit(
+        &mut self,
+        discount_bps: u32,
+        bidder: Addr,
+        amount: Uint128,
+    ) -> Result<(), DiscountPoolError> {
+        self.pools
+            .entry(discount_bps)
+            .or_insert_with(|| {
+                DiscountPool::new(Decimal::from_ratio(discount_bps, 10_000u32))
+            })
+            .deposit(bidder, amount)
+    }
+
+    /// Fill up to `collateral_value` (debt terms) against the lowest-discount non-empty pool
+    /// first, moving on to the next pool once the current one is exhausted. Returns one
+    /// [`PoolFill`] per pool that contributed.
+    pub fn fill(&mut self, collateral_value: Decimal) -> Vec<PoolFill> {
+        let mut remaining = collateral_value;
+        let mut fills = vec![];
+        for (discount_bps, pool) in self.pools.iter_mut() {
+            if remaining.is_zero() {
+                break;
+            }
+            let (burned, credits) = pool.fill(remaining);
+            if burned.is_zero() {
+                continue;
+            }
+            let value_filled: Decimal = credits.iter().map(|(_, v)| *v).sum();
+            remaining = if value_filled >= remaining {
+                Decimal::zero()
+            } else {
+                remaining - value_filled
+            };
+            fills.push(PoolFill {
+                discount_bps: *discount_bps,
+                burned,
+                credits,
+            });
+        }
+        fills
+    }
+}
+
+/// The result of filling a single discount pool during `DoLiquidate`
+#[cw_serde]
+pub struct PoolFill {
+    pub discount_bps: u32,
+    pub burned: Uint128,
+    pub credits: Vec<(Addr, Decimal)>,
+}
+
+#[derive(Error, Debug)]
+pub enum DiscountPoolError {
+    #[error("Discount pool is closed to new deposits")]
+    Closed,
+}
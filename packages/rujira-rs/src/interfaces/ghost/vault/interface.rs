@@ -91,6 +91,12 @@ pub struct StatusResponse {
     pub debt_pool: PoolResponse,
     // Share pool that allocated collected debt interest to lenders
     pub deposit_pool: PoolResponse,
+
+    /// Monotonic index compounded by `debt_rate` on every accrual step, starting at `one()`.
+    /// Borrowers and delegates snapshot this at `Borrow`/`Repay` time so their obligation can
+    /// be re-based exactly via `current * (cumulative_borrow_rate / snapshot)`, independent of
+    /// the pooled `debt_pool` share rounding
+    pub cumulative_borrow_rate: Decimal,
 }
 
 #[cw_serde]
@@ -116,6 +122,23 @@ pub struct BorrowerResponse {
     pub shares: Uint128,
     /// The remaining amount of borrowable funds for this borrower
     pub available: Uint128,
+    /// `cumulative_borrow_rate` snapshotted at this borrower's last state-changing `MarketMsg`
+    pub borrow_rate_index: Decimal,
+    /// `current` re-based onto the vault's latest `cumulative_borrow_rate`, ie the exact amount
+    /// owed including interest accrued since `borrow_rate_index` was last snapshotted
+    pub accrued: Uint128,
+}
+
+impl BorrowerResponse {
+    /// `current * (cumulative_borrow_rate / borrow_rate_index)`, computed on index atomics so
+    /// the ratio is exact regardless of either `Decimal`'s magnitude
+    pub fn owed(&self, cumulative_borrow_rate: Decimal) -> Uint128 {
+        if self.borrow_rate_index.is_zero() {
+            return self.current;
+        }
+        self.current
+            .multiply_ratio(cumulative_borrow_rate.atomics(), self.borrow_rate_index.atomics())
+    }
 }
 
 #[cw_serde]
@@ -131,6 +154,22 @@ pub struct DelegateResponse {
     pub current: Uint128,
     /// The shares allocated to the current debt
     pub shares: Uint128,
+    /// `cumulative_borrow_rate` snapshotted at this delegate's last state-changing `MarketMsg`
+    pub borrow_rate_index: Decimal,
+    /// `current` re-based onto the vault's latest `cumulative_borrow_rate`
+    pub accrued: Uint128,
+}
+
+impl DelegateResponse {
+    /// `current * (cumulative_borrow_rate / borrow_rate_index)`, mirroring
+    /// [`BorrowerResponse::owed`] but snapshotted independently per delegate
+    pub fn owed(&self, cumulative_borrow_rate: Decimal) -> Uint128 {
+        if self.borrow_rate_index.is_zero() {
+            return self.current;
+        }
+        self.current
+            .multiply_ratio(cumulative_borrow_rate.atomics(), self.borrow_rate_index.atomics())
+    }
 }
 
 impl OracleValue for DelegateResponse {
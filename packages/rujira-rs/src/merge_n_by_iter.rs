@@ -1,117 +1,201 @@
-use itertools::{put_back, PutBack};
-use std::{cmp::Ordering, iter::Fuse};
-
-/// MergeByIter extended to support an arbitrary list of items, sorted and merged by a comparator
-pub struct MergeNByIter<I: Iterator, F> {
-    iters: Vec<PutBack<Fuse<I>>>,
-    cmp_fn: F,
-}
-
-impl<I, F> MergeNByIter<I, F>
-where
-    I: Iterator,
-    I::Item: Clone,
-    F: Fn(&I::Item, &I::Item) -> Ordering,
-{
-    pub fn new<IntoIter>(iterators: impl IntoIterator<Item = IntoIter>, cmp_fn: F) -> Self
-    where
-        IntoIter: IntoIterator<Item = I::Item, IntoIter = I>,
-    {
-        Self {
-            iters: iterators
-                .into_iter()
-                .map(|iter| put_back(iter.into_iter().fuse()))
-                .collect(),
-            cmp_fn,
-        }
-    }
-}
-
-impl<I, F> Iterator for MergeNByIter<I, F>
-where
-    I: Iterator,
-    I::Item: Clone,
-    F: Fn(&I::Item, &I::Item) -> Ordering,
-{
-    type Item = Vec<I::Item>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut best: Option<I::Item> = None;
-        let mut items: Vec<(&mut PutBack<Fuse<I>>, I::Item)> = vec![];
-        for lane in &mut self.iters {
-            match (best.clone(), lane.next()) {
-                (_, None) => continue,
-                (None, Some(next)) => {
-                    best = Some(next.clone());
-                    items.push((lane, next));
-                }
-                (Some(b), Some(next)) => {
-                    let cmp = (self.cmp_fn)(&b, &next);
-                    match cmp {
-                        Ordering::Greater => {
-                            // If we discard values we need to put them back on their iterator
-                            for i in &mut items {
-                                i.0.put_back(i.1.clone());
-                            }
-                            best = Some(next.clone());
-                            items = vec![(lane, next)];
-                        }
-                        Ordering::Equal => {
-                            items.push((lane, next));
-                        }
-                        Ordering::Less => {
-                            lane.put_back(next);
-                        }
-                    }
-                }
-            }
-        }
-
-        if items.is_empty() {
-            None
-        } else {
-            Some(items.into_iter().map(|x| x.1).collect())
-        }
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use proptest::prelude::{Strategy, *};
-
-    use super::*;
-
-    proptest! {
-        #![proptest_config(ProptestConfig {
-            cases: 10000,
-            ..Default::default()
-        })]
-        #[test]
-        fn test_ordering_is_correct(
-            ranges in prop::collection::vec(
-                (0i32..100i32, 0i32..100i32).prop_map(|(start, len)| start..(start + len)),
-                1..10
-            )
-        ) {
-            let vecs: Vec<Vec<i32>> = ranges.iter().map(|range| range.clone().collect()).collect();
-            let iter = MergeNByIter::new(
-                vecs.clone().into_iter(),
-                |a: &i32, b: &i32| a.cmp(b)
-            );
-            let all = iter.collect::<Vec<Vec<i32>>>();
-            let flattened: Vec<i32> = all.iter().flatten().copied().collect();
-
-            prop_assert_eq!(vecs.concat().len(), flattened.len());
-            for group in all {
-                let first = group[0];
-                for item in &group {
-                    prop_assert_eq!(first, *item);
-                }
-            }
-            for n in 1..flattened.len() {
-            // We can just check that N=1 >= N on a flat list, as we've tested group membership above
-                prop_assert!(flattened[n-1] <= flattened[n])
-            }
-        }
-    }
-}
+use std::{cmp::Ordering, iter::Fuse};
+
+/// MergeByIter extended to support an arbitrary list of items, sorted and merged by a comparator.
+///
+/// Internally this keeps a binary heap of each lane's current head, keyed by `cmp_fn`. Since
+/// `cmp_fn` is a runtime comparator rather than the item type's intrinsic `Ord`,
+/// `std::collections::BinaryHeap` (which requires `Ord` and has no way to take a custom
+/// comparator) doesn't fit, so the heap is hand-rolled over `(item, lane_index)` pairs using
+/// `cmp_fn` directly. This keeps `next()` at O(log K) per emitted item instead of the O(K) linear
+/// lane scan a naive merge would do.
+pub struct MergeNByIter<I: Iterator, F> {
+    iters: Vec<Fuse<I>>,
+    heap: Vec<(I::Item, usize)>,
+    cmp_fn: F,
+}
+
+impl<I, F> MergeNByIter<I, F>
+where
+    I: Iterator,
+    F: Fn(&I::Item, &I::Item) -> Ordering,
+{
+    pub fn new<IntoIter>(iterators: impl IntoIterator<Item = IntoIter>, cmp_fn: F) -> Self
+    where
+        IntoIter: IntoIterator<Item = I::Item, IntoIter = I>,
+    {
+        let mut iters: Vec<Fuse<I>> = iterators
+            .into_iter()
+            .map(|iter| iter.into_iter().fuse())
+            .collect();
+
+        let mut heap = Vec::with_capacity(iters.len());
+        for (lane_index, iter) in iters.iter_mut().enumerate() {
+            if let Some(item) = iter.next() {
+                heap_push(&mut heap, (item, lane_index), &cmp_fn);
+            }
+        }
+
+        Self {
+            iters,
+            heap,
+            cmp_fn,
+        }
+    }
+}
+
+impl<I, F> Iterator for MergeNByIter<I, F>
+where
+    I: Iterator,
+    F: Fn(&I::Item, &I::Item) -> Ordering,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (first, lane) = heap_pop(&mut self.heap, &self.cmp_fn)?;
+        if let Some(next_item) = self.iters[lane].next() {
+            heap_push(&mut self.heap, (next_item, lane), &self.cmp_fn);
+        }
+
+        let mut group = vec![first];
+        // Drain every other lane whose current head ties the first one, so equal elements across
+        // lanes still come out grouped together, matching the old linear-scan behavior.
+        while let Some(top) = self.heap.first() {
+            if (self.cmp_fn)(&top.0, &group[0]) != Ordering::Equal {
+                break;
+            }
+            let (item, lane) = heap_pop(&mut self.heap, &self.cmp_fn).unwrap();
+            if let Some(next_item) = self.iters[lane].next() {
+                heap_push(&mut self.heap, (next_item, lane), &self.cmp_fn);
+            }
+            group.push(item);
+        }
+
+        Some(group)
+    }
+}
+
+/// Sifts a newly-pushed entry up until the min-heap property (parent <= both children, per
+/// `cmp_fn` on the entry's `.0`) is restored.
+fn heap_push<T>(heap: &mut Vec<(T, usize)>, value: (T, usize), cmp_fn: &impl Fn(&T, &T) -> Ordering) {
+    heap.push(value);
+    let mut idx = heap.len() - 1;
+    while idx > 0 {
+        let parent = (idx - 1) / 2;
+        if cmp_fn(&heap[idx].0, &heap[parent].0) == Ordering::Less {
+            heap.swap(idx, parent);
+            idx = parent;
+        } else {
+            break;
+        }
+    }
+}
+
+/// Removes and returns the minimum entry (per `cmp_fn`), sifting the last entry down from the
+/// root to restore the min-heap property.
+fn heap_pop<T>(heap: &mut Vec<(T, usize)>, cmp_fn: &impl Fn(&T, &T) -> Ordering) -> Option<(T, usize)> {
+    if heap.is_empty() {
+        return None;
+    }
+    let last = heap.len() - 1;
+    heap.swap(0, last);
+    let result = heap.pop();
+
+    let mut idx = 0;
+    let len = heap.len();
+    loop {
+        let left = 2 * idx + 1;
+        let right = 2 * idx + 2;
+        let mut smallest = idx;
+        if left < len && cmp_fn(&heap[left].0, &heap[smallest].0) == Ordering::Less {
+            smallest = left;
+        }
+        if right < len && cmp_fn(&heap[right].0, &heap[smallest].0) == Ordering::Less {
+            smallest = right;
+        }
+        if smallest == idx {
+            break;
+        }
+        heap.swap(idx, smallest);
+        idx = smallest;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::{Strategy, *};
+
+    use super::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 10000,
+            ..Default::default()
+        })]
+        #[test]
+        fn test_ordering_is_correct(
+            ranges in prop::collection::vec(
+                (0i32..100i32, 0i32..100i32).prop_map(|(start, len)| start..(start + len)),
+                1..10
+            )
+        ) {
+            let vecs: Vec<Vec<i32>> = ranges.iter().map(|range| range.clone().collect()).collect();
+            let iter = MergeNByIter::new(
+                vecs.clone().into_iter(),
+                |a: &i32, b: &i32| a.cmp(b)
+            );
+            let all = iter.collect::<Vec<Vec<i32>>>();
+            let flattened: Vec<i32> = all.iter().flatten().copied().collect();
+
+            prop_assert_eq!(vecs.concat().len(), flattened.len());
+            for group in all {
+                let first = group[0];
+                for item in &group {
+                    prop_assert_eq!(first, *item);
+                }
+            }
+            for n in 1..flattened.len() {
+            // We can just check that N=1 >= N on a flat list, as we've tested group membership above
+                prop_assert!(flattened[n-1] <= flattened[n])
+            }
+        }
+
+        #[test]
+        fn test_matches_the_groups_a_linear_scan_would_produce(
+            ranges in prop::collection::vec(
+                (0i32..20i32, 0i32..20i32).prop_map(|(start, len)| start..(start + len)),
+                1..8
+            )
+        ) {
+            let vecs: Vec<Vec<i32>> = ranges.iter().map(|range| range.clone().collect()).collect();
+
+            let mut all_items: Vec<i32> = vecs.concat();
+            all_items.sort_unstable();
+
+            let iter = MergeNByIter::new(vecs.into_iter(), |a: &i32, b: &i32| a.cmp(b));
+            let flattened: Vec<i32> = iter.flatten().collect();
+
+            prop_assert_eq!(all_items, flattened);
+        }
+    }
+
+    #[test]
+    fn empty_lanes_are_simply_skipped() {
+        let lanes: Vec<Vec<i32>> = vec![vec![], vec![1, 3], vec![], vec![2]];
+        let merged: Vec<Vec<i32>> = MergeNByIter::new(lanes, |a: &i32, b: &i32| a.cmp(b)).collect();
+        assert_eq!(merged, vec![vec![1], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn a_fully_exhausted_lane_never_gets_pushed_back_onto_the_heap() {
+        let lanes: Vec<Vec<i32>> = vec![vec![1], vec![1, 2, 3]];
+        let mut merged = MergeNByIter::new(lanes, |a: &i32, b: &i32| a.cmp(b));
+        assert_eq!(merged.next(), Some(vec![1, 1]));
+        assert_eq!(merged.next(), Some(vec![2]));
+        assert_eq!(merged.next(), Some(vec![3]));
+        assert_eq!(merged.next(), None);
+        // Fused iterators must stay exhausted rather than cycling back to earlier items.
+        assert_eq!(merged.next(), None);
+    }
+}
@@ -1,4 +1,8 @@
-use cosmwasm_std::{Coin, Decimal, OverflowError, QuerierWrapper, Uint128};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Coin, Decimal, OverflowError, QuerierWrapper, StdError, Storage, Uint128,
+};
+use cw_storage_plus::Item;
 use cw_utils::NativeBalance;
 use std::ops::Add;
 use thiserror::Error;
@@ -15,6 +19,71 @@ use crate::{
 pub trait Oracle {
     fn tor_price(&self, q: QuerierWrapper) -> Result<Decimal, OracleError>;
     fn oracle_price(&self, q: QuerierWrapper) -> Result<Decimal, OracleError>;
+
+    /// Tries `self` (reported at `reported_at`) then each of `fallbacks` in turn via
+    /// `oracle_price`, skipping any source whose call errors, returns zero, or is older than
+    /// `opts.max_staleness_blocks`. Returns the first source to pass, unless a second
+    /// source also passes - in which case the first two to pass are cross-checked against
+    /// `opts.max_deviation` (when set) and the higher-priority one is returned only if they agree, so a
+    /// lone bad report can't silently slip into `value_usd` or the order book's `oracles` pair.
+    /// `OracleError::Unavailable` if no source passes, or the top two disagree beyond tolerance.
+    fn checked_price(
+        &self,
+        q: QuerierWrapper,
+        reported_at: u64,
+        fallbacks: &[(&dyn Oracle, u64)],
+        opts: &PriceOpts,
+    ) -> Result<Decimal, OracleError> {
+        let mut accepted: Vec<Decimal> = vec![];
+
+        let sources = std::iter::once((self.oracle_price(q), reported_at))
+            .chain(fallbacks.iter().map(|(o, height)| (o.oracle_price(q), *height)));
+
+        for (price, height) in sources {
+            let Ok(price) = price else { continue };
+            if price_is_fresh(price, height, opts) {
+                accepted.push(price);
+                if accepted.len() == 2 {
+                    break;
+                }
+            }
+        }
+
+        match accepted.as_slice() {
+            [] => Err(OracleError::Unavailable {}),
+            [price] => Ok(*price),
+            [primary, fallback, ..] => {
+                if let Some(max_deviation) = opts.max_deviation {
+                    let deviation = primary
+                        .abs_diff(*fallback)
+                        .checked_div(*primary)
+                        .unwrap_or(Decimal::one());
+                    if deviation > max_deviation {
+                        return Err(OracleError::Unavailable {});
+                    }
+                }
+                Ok(*primary)
+            }
+        }
+    }
+}
+
+/// A price report is fresh if it's non-zero and was reported no further back than
+/// `opts.max_staleness_blocks` from `opts.height`.
+fn price_is_fresh(price: Decimal, reported_at: u64, opts: &PriceOpts) -> bool {
+    !price.is_zero() && opts.height.saturating_sub(reported_at) <= opts.max_staleness_blocks
+}
+
+/// Controls for [`Oracle::checked_price`]'s freshness and cross-source sanity checks.
+#[cw_serde]
+pub struct PriceOpts {
+    /// The current block height, compared against each source's own report height.
+    pub height: u64,
+    /// A price reported more than this many blocks before `height` is rejected as stale.
+    pub max_staleness_blocks: u64,
+    /// When two sources both pass the freshness check, the largest fractional disagreement
+    /// between them tolerated before the pair is rejected outright. `None` skips the check.
+    pub max_deviation: Option<Decimal>,
 }
 
 pub trait OracleValue {
@@ -52,6 +121,44 @@ impl<T: Oracle> Oracle for [T; 2] {
     }
 }
 
+/// A minimal mirror of `rujira-staking`'s `QueryMsg::RedemptionRate {}`. Kept local rather than
+/// depending on the contract crate (which would invert the usual package -> contract dependency
+/// direction); the wire format just needs to match.
+#[cw_serde]
+enum RedemptionRateQuery {
+    RedemptionRate {},
+}
+
+/// The liquid bond share token's price, sourced from a `rujira-staking` contract's
+/// `RedemptionRate` query rather than from the network oracle. `oracle_price`/`tor_price` both
+/// return `size() / shares()` in bond-token terms; there's no separate "oracle" vs "tor"
+/// reading since the rate isn't a market price, so the two methods agree. The contract's own
+/// `guard_liquid_redemption_rate` high-water mark makes the rate monotonic non-decreasing except
+/// across an explicit unbond, so it's safe to use as a collateral oracle.
+#[cw_serde]
+pub struct LiquidBondShare {
+    pub staking: Addr,
+    /// The bond token the share redeems into, for composing the share's USD value via its
+    /// `tor_price`.
+    pub bond: Layer1Asset,
+}
+
+impl Oracle for LiquidBondShare {
+    fn tor_price(&self, q: QuerierWrapper) -> Result<Decimal, OracleError> {
+        self.oracle_price(q)
+    }
+    fn oracle_price(&self, q: QuerierWrapper) -> Result<Decimal, OracleError> {
+        Ok(q.query_wasm_smart(&self.staking, &RedemptionRateQuery::RedemptionRate {})?)
+    }
+}
+
+impl OracleValue for LiquidBondShare {
+    /// `size()/shares()` converted to bond tokens, then to USD via the bond token's `tor_price`.
+    fn value_usd(&self, q: QuerierWrapper) -> Result<Decimal, OracleError> {
+        Ok(self.oracle_price(q)?.checked_mul(self.bond.tor_price(q)?)?)
+    }
+}
+
 impl OracleValue for Coin {
     fn value_usd(&self, q: QuerierWrapper) -> Result<Decimal, OracleError> {
         Ok(SecuredAsset::from_denom(&self.denom)?
@@ -82,6 +189,67 @@ pub enum OracleError {
     OraclePrice(#[from] OraclePriceError),
     #[error("{0}")]
     Overflow(#[from] OverflowError),
+    #[error("{0}")]
+    Std(#[from] StdError),
     #[error("Unavailable")]
     Unavailable {},
+    #[error("Stale: last updated at height {last_update_height}, now {height}")]
+    Stale { last_update_height: u64, height: u64 },
+}
+
+pub const EMA_ORACLE: Item<EmaOracle> = Item::new("ema-oracle");
+
+/// Exponential moving average of an `OracleValue`'s spot price, guarding borrow/liquidation
+/// checks against a single-block pool manipulation swinging a valuation. Persists
+/// `(last_value, last_update_height)` so each refresh blends in the new spot reading rather
+/// than trusting it outright.
+#[cw_serde]
+#[derive(Default)]
+pub struct EmaOracle {
+    pub last_value: Decimal,
+    pub last_update_height: u64,
+}
+
+impl EmaOracle {
+    /// Blend `spot`, read at `height`, into the persisted EMA and save the result.
+    ///
+    /// `alpha = min(1, elapsed_blocks / smoothing_window)` controls how much weight the new
+    /// reading gets; a reading taken `smoothing_window` blocks or more after the last one is
+    /// trusted outright, while one taken immediately after barely moves the average. Returns
+    /// `OracleError::Stale` instead of a value if the last update is further back than
+    /// `max_staleness_blocks`, since a smoothed value built on a stale base can't be trusted.
+    pub fn refresh(
+        storage: &mut dyn Storage,
+        spot: Decimal,
+        height: u64,
+        smoothing_window: u64,
+        max_staleness_blocks: u64,
+    ) -> Result<Decimal, OracleError> {
+        let ema = EMA_ORACLE.may_load(storage)?.unwrap_or_default();
+
+        let elapsed = height.saturating_sub(ema.last_update_height);
+        if ema.last_update_height != 0 && elapsed > max_staleness_blocks {
+            return Err(OracleError::Stale {
+                last_update_height: ema.last_update_height,
+                height,
+            });
+        }
+
+        let value = if ema.last_update_height == 0 {
+            spot
+        } else {
+            let alpha = Decimal::from_ratio(elapsed.min(smoothing_window), smoothing_window.max(1));
+            alpha * spot + (Decimal::one() - alpha) * ema.last_value
+        };
+
+        EMA_ORACLE.save(
+            storage,
+            &EmaOracle {
+                last_value: value,
+                last_update_height: height,
+            },
+        )?;
+
+        Ok(value)
+    }
 }
@@ -0,0 +1,16 @@
+use cosmwasm_std::Decimal;
+
+/// Derives a resting order price from an oracle mid price and a premium/discount in whole
+/// percentage points, for pegged order targets that should track the oracle rather than sit at
+/// a fixed price.
+pub trait Premiumable {
+    /// `oracle * (100 - self) / 100`: `self` percent below the oracle. It takes no side, so the
+    /// result is the same whichever side of the book the order rests on.
+    fn to_rate(&self, oracle: &Decimal) -> Decimal;
+}
+
+impl Premiumable for u8 {
+    fn to_rate(&self, oracle: &Decimal) -> Decimal {
+        oracle * Decimal::from_ratio(100 - self, 100u16)
+    }
+}
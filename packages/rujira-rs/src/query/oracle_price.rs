@@ -7,7 +7,6 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Decimal, QuerierWrapper, StdError};
 use std::{
     num::{ParseIntError, TryFromIntError},
-    ops::Sub,
     str::FromStr,
 };
 use thiserror::Error;
@@ -22,26 +21,135 @@ impl TryFrom<QueryOraclePriceResponse> for OraclePrice {
     type Error = TryFromOraclePriceError;
     fn try_from(v: QueryOraclePriceResponse) -> Result<Self, Self::Error> {
         match v.price {
-            Some(price_data) => {
-                // Trim fractional digits > 18
-                let len = price_data.price.len();
-                let fractional_len = {
-                    let mut parts_iter = price_data.price.split('.');
-                    parts_iter.next().unwrap(); // split always returns at least one element
-                    parts_iter.next().unwrap_or_default().len()
-                };
-                let price_str = &price_data.price
-                    [..len.sub(fractional_len.checked_sub(18).unwrap_or_default())];
-                Ok(Self {
-                    symbol: price_data.symbol,
-                    price: Decimal::from_str(price_str)?,
-                })
-            }
+            Some(price_data) => Ok(Self {
+                symbol: price_data.symbol,
+                price: parse_oracle_price(&price_data.price)?,
+            }),
             None => Err(TryFromOraclePriceError::NotFound {}),
         }
     }
 }
 
+/// Parses a THORChain oracle price report into a `Decimal`. Reports can carry more than
+/// `Decimal`'s 18 fractional digits and may arrive in scientific notation (`1.2e-5`, `3E6`);
+/// `Decimal::from_str` handles neither, so `raw` is first normalized to a plain fixed-point
+/// string via `normalize_exponent`, then rounded half-up to 18 fractional digits rather than
+/// truncated - slicing off the extra digits (the prior approach) always rounds toward zero and
+/// silently biases every price down.
+fn parse_oracle_price(raw: &str) -> Result<Decimal, TryFromOraclePriceError> {
+    let normalized = normalize_exponent(raw)?;
+
+    if normalized.matches('.').count() > 1 {
+        return Err(TryFromOraclePriceError::InvalidFormat(raw.to_string()));
+    }
+    let mut parts = normalized.splitn(2, '.');
+    let integer_part = parts.next().unwrap_or_default();
+    let fractional_part = parts.next().unwrap_or_default();
+    if integer_part.is_empty()
+        || !integer_part.bytes().all(|b| b.is_ascii_digit())
+        || !fractional_part.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(TryFromOraclePriceError::InvalidFormat(raw.to_string()));
+    }
+
+    if fractional_part.len() <= 18 {
+        return Ok(Decimal::from_str(&normalized)?);
+    }
+
+    let (kept, rest) = fractional_part.split_at(18);
+    let rounded = if rest.as_bytes()[0] >= b'5' {
+        round_up_last_digit(integer_part, kept)
+    } else {
+        (integer_part.to_string(), kept.to_string())
+    };
+
+    Ok(Decimal::from_str(&format!("{}.{}", rounded.0, rounded.1))?)
+}
+
+/// Rewrites scientific notation (`1.2e-5`, `3E6`) into a plain fixed-point decimal string by
+/// shifting the decimal point by the exponent; input without an `e`/`E` passes through unchanged.
+fn normalize_exponent(raw: &str) -> Result<String, TryFromOraclePriceError> {
+    let Some(e_pos) = raw.find(['e', 'E']) else {
+        return Ok(raw.to_string());
+    };
+    let (mantissa, exp_str) = raw.split_at(e_pos);
+    let exp: i32 = exp_str[1..]
+        .parse()
+        .map_err(|_| TryFromOraclePriceError::InvalidFormat(raw.to_string()))?;
+
+    if mantissa.matches('.').count() > 1 {
+        return Err(TryFromOraclePriceError::InvalidFormat(raw.to_string()));
+    }
+    let mut mantissa_parts = mantissa.splitn(2, '.');
+    let integer_part = mantissa_parts.next().unwrap_or_default();
+    let fractional_part = mantissa_parts.next().unwrap_or_default();
+    if !integer_part.bytes().all(|b| b.is_ascii_digit())
+        || !fractional_part.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(TryFromOraclePriceError::InvalidFormat(raw.to_string()));
+    }
+
+    let mut digits: String = integer_part.chars().chain(fractional_part.chars()).collect();
+    // The decimal point currently sits after `integer_part.len()` digits; `exp` shifts it.
+    let mut point = integer_part.len() as i32 + exp;
+
+    if point <= 0 {
+        digits = "0".repeat((-point) as usize) + &digits;
+        point = 0;
+    } else if point as usize > digits.len() {
+        digits.extend(std::iter::repeat('0').take(point as usize - digits.len()));
+    }
+
+    let (int_str, frac_str) = digits.split_at(point as usize);
+    let int_str = if int_str.is_empty() { "0" } else { int_str };
+    Ok(if frac_str.is_empty() {
+        int_str.to_string()
+    } else {
+        format!("{int_str}.{frac_str}")
+    })
+}
+
+/// Adds one unit to the last digit of `fractional`, propagating any carry leftward through its
+/// digits and, if they're all nines, into `integer` as well.
+fn round_up_last_digit(integer: &str, fractional: &str) -> (String, String) {
+    let mut frac_digits: Vec<u8> = fractional.bytes().collect();
+    let mut carry = true;
+    for d in frac_digits.iter_mut().rev() {
+        if !carry {
+            break;
+        }
+        if *d == b'9' {
+            *d = b'0';
+        } else {
+            *d += 1;
+            carry = false;
+        }
+    }
+    let fractional = String::from_utf8(frac_digits).unwrap();
+
+    if !carry {
+        return (integer.to_string(), fractional);
+    }
+
+    let mut int_digits: Vec<u8> = integer.bytes().collect();
+    for d in int_digits.iter_mut().rev() {
+        if !carry {
+            break;
+        }
+        if *d == b'9' {
+            *d = b'0';
+        } else {
+            *d += 1;
+            carry = false;
+        }
+    }
+    if carry {
+        int_digits.insert(0, b'1');
+    }
+
+    (String::from_utf8(int_digits).unwrap(), fractional)
+}
+
 #[derive(Error, Debug)]
 pub enum TryFromOraclePriceError {
     #[error("{0}")]
@@ -54,6 +162,8 @@ pub enum TryFromOraclePriceError {
     Layer1Asset(#[from] Layer1AssetError),
     #[error("Oracle price not found")]
     NotFound {},
+    #[error("invalid oracle price format: {0}")]
+    InvalidFormat(String),
 }
 
 impl OraclePrice {
@@ -65,6 +175,44 @@ impl OraclePrice {
         let res = QueryOraclePriceResponse::get(q, req)?;
         Ok(OraclePrice::try_from(res)?)
     }
+
+    /// Tries each of `symbols` in priority order via `load`, accepting the first whose price
+    /// agrees with `pool_tor_price` within `max_deviation` (the relative difference
+    /// `|oracle - pool_tor| / pool_tor`), paired with the index of the symbol it came from.
+    ///
+    /// A `pool_tor_price` of `None` or zero has nothing to cross-check against, so the
+    /// deviation guard is skipped and the first available price is accepted outright. A source
+    /// that errors (including a transient `NotFound`) is treated as invalid and the chain just
+    /// advances to the next symbol rather than failing outright.
+    pub fn load_checked(
+        q: QuerierWrapper,
+        symbols: &[&str],
+        pool_tor_price: Option<Decimal>,
+        max_deviation: Decimal,
+    ) -> Result<(Self, usize), TryFromOraclePriceError> {
+        let pool_tor_price = pool_tor_price.filter(|p| !p.is_zero());
+
+        for (index, symbol) in symbols.iter().enumerate() {
+            let Ok(price) = Self::load(q, symbol) else {
+                continue;
+            };
+
+            if let Some(pool_tor_price) = pool_tor_price {
+                let deviation = price
+                    .price
+                    .abs_diff(pool_tor_price)
+                    .checked_div(pool_tor_price)
+                    .unwrap_or(Decimal::one());
+                if deviation > max_deviation {
+                    continue;
+                }
+            }
+
+            return Ok((price, index));
+        }
+
+        Err(TryFromOraclePriceError::NotFound {})
+    }
 }
 
 #[derive(Error, Debug)]
@@ -127,4 +275,52 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn price_parsing_rounds_half_up_instead_of_truncating() {
+        // The 19th fractional digit is 5, so the kept 18th digit rounds up rather than being
+        // dropped as the old truncating slice would.
+        assert_eq!(
+            parse_oracle_price("0.1234567890123456785").unwrap(),
+            Decimal::from_str("0.123456789012345679").unwrap()
+        );
+
+        // A carry that propagates through 18 nines all the way into the integer part.
+        assert_eq!(
+            parse_oracle_price("0.9999999999999999995").unwrap(),
+            Decimal::one()
+        );
+        assert_eq!(
+            parse_oracle_price("4.9999999999999999995").unwrap(),
+            Decimal::from_str("5").unwrap()
+        );
+    }
+
+    #[test]
+    fn price_parsing_accepts_scientific_notation() {
+        assert_eq!(
+            parse_oracle_price("1.2e-5").unwrap(),
+            Decimal::from_str("0.000012").unwrap()
+        );
+        assert_eq!(
+            parse_oracle_price("3E6").unwrap(),
+            Decimal::from_str("3000000").unwrap()
+        );
+        assert_eq!(
+            parse_oracle_price("1.23456e2").unwrap(),
+            Decimal::from_str("123.456").unwrap()
+        );
+    }
+
+    #[test]
+    fn price_parsing_rejects_malformed_input() {
+        assert!(matches!(
+            parse_oracle_price("1.2.3"),
+            Err(TryFromOraclePriceError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            parse_oracle_price("12a3"),
+            Err(TryFromOraclePriceError::InvalidFormat(_))
+        ));
+    }
 }